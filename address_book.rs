@@ -0,0 +1,68 @@
+//! The individual AddressBook sources (iCloud, Exchange, Google, ...) so the
+//! user can choose which ones feed name resolution.
+
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::{readonly, AnalyzerResult};
+
+const SOURCE_DB: &str = "AddressBook-v22.abcddb";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressBookSource {
+	/// Folder name under `Sources`, passed back in `addressBookSources`
+	pub id: String,
+	/// Account name, e.g. "iCloud" or an Exchange address
+	pub name: Option<String>,
+	pub contacts: i64
+}
+
+/// Every source database under `address_book/Sources`.
+pub fn source_paths(address_book: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+	let mut paths = Vec::new();
+	for entry in fs::read_dir(address_book.join("Sources"))? {
+		let entry = entry?;
+		let path = entry.path().join(SOURCE_DB);
+		if path.is_file() {
+			paths.push((entry.file_name().to_string_lossy().into_owned(), path));
+		}
+	}
+	paths.sort();
+	Ok(paths)
+}
+
+pub fn list_sources(address_book: &Path) -> AnalyzerResult<Vec<AddressBookSource>> {
+	let mut sources = Vec::new();
+	for (id, path) in source_paths(address_book)? {
+		let db = readonly::open(&path)?;
+		sources.push(AddressBookSource {
+			name: source_name(&db),
+			contacts: readonly::prepare(&db, "SELECT COUNT(*) FROM ZABCDRECORD")?
+				.query_row([], |row| row.get(0))?,
+			id
+		});
+		let _ = db.close();
+	}
+	Ok(sources)
+}
+
+/// Opens only the sources whose id is in `ids`.
+pub fn open_sources(address_book: &Path, ids: &[String]) -> AnalyzerResult<Vec<Connection>> {
+	source_paths(address_book)?
+		.into_iter()
+		.filter(|(id, _)| ids.contains(id))
+		.map(|(_, path)| readonly::open(path))
+		.collect()
+}
+
+/// Older AddressBook versions don't name their containers.
+fn source_name(db: &Connection) -> Option<String> {
+	readonly::prepare(db, "SELECT ZNAME FROM ZABCDCONTAINER WHERE ZNAME IS NOT NULL LIMIT 1")
+		.ok()?
+		.query_row([], |row| row.get(0))
+		.ok()
+}
@@ -0,0 +1,227 @@
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::{fs, io};
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use prost::Message as ProstMessage;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::stats::stats::YearsStats;
+use crate::{storage, AnalyzerResult};
+
+const INDEX_FILE: &str = "archive.json";
+const ARCHIVE_DIR: &str = "archive";
+/// Where the device key lives without a Keychain, and where earlier
+/// versions kept it on macOS
+const DEVICE_KEY_FILE: &str = "device.key";
+#[cfg(target_os = "macos")]
+const KEYCHAIN_SERVICE: &str = "Messages Wrapped";
+#[cfg(target_os = "macos")]
+const KEYCHAIN_ACCOUNT: &str = "archive key";
+/// `errSecItemNotFound`
+#[cfg(target_os = "macos")]
+const ERR_SEC_ITEM_NOT_FOUND: i32 = -25300;
+const NONCE_LEN: usize = 12;
+
+/// Index entry for an archived run. The stats themselves are stored encrypted
+/// with the device key in a separate file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveEntry {
+	pub id: String,
+	pub created_at: u64,
+	pub years: Vec<i32>,
+	pub size: usize
+}
+
+pub fn list() -> AnalyzerResult<Vec<ArchiveEntry>> {
+	storage::read_json(INDEX_FILE)
+}
+
+/// Encrypts and stores a generated `YearsStats`, returning its index entry.
+pub fn store(stats: &YearsStats) -> AnalyzerResult<ArchiveEntry> {
+	let created_at = SystemTime::now()
+		.duration_since(SystemTime::UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs();
+	let id = format!("{}-{}", created_at, hex::encode(rand::thread_rng().gen::<[u8; 4]>()));
+
+	let bytes = stats.encode_to_vec();
+	fs::create_dir_all(archive_dir())?;
+	fs::write(entry_path(&id), seal(&bytes)?)?;
+
+	let entry = ArchiveEntry { id, created_at, years: stats.years.clone(), size: bytes.len() };
+	let mut entries = list()?;
+	entries.push(entry.clone());
+	storage::write_json(INDEX_FILE, &entries)?;
+
+	Ok(entry)
+}
+
+/// Decrypts an archived run. Returns `None` if no entry has this id.
+pub fn open(id: &str) -> AnalyzerResult<Option<YearsStats>> {
+	if !is_valid_id(id) {
+		return Ok(None);
+	}
+
+	let contents = match fs::read(entry_path(id)) {
+		Ok(contents) => contents,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+		Err(e) => return Err(e.into())
+	};
+	let bytes = unseal(&contents)?;
+
+	let stats = YearsStats::decode(bytes.as_slice())
+		.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+	Ok(Some(stats))
+}
+
+/// Removes an archived run. Returns whether it existed.
+pub fn delete(id: &str) -> AnalyzerResult<bool> {
+	let mut entries = list()?;
+	let Some(position) = entries.iter().position(|entry| entry.id == id) else {
+		return Ok(false);
+	};
+	entries.remove(position);
+	storage::write_json(INDEX_FILE, &entries)?;
+
+	match fs::remove_file(entry_path(id)) {
+		Ok(()) => Ok(true),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(true),
+		Err(e) => Err(e.into())
+	}
+}
+
+/// Encrypts local data with the device key. The nonce is prepended.
+pub fn seal(bytes: &[u8]) -> AnalyzerResult<Vec<u8>> {
+	let cipher = Aes256Gcm::new_from_slice(&device_key()?)
+		.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+	let nonce_bytes = rand::thread_rng().gen::<[u8; NONCE_LEN]>();
+	let encrypted = cipher
+		.encrypt(Nonce::from_slice(&nonce_bytes), bytes)
+		.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+
+	let mut contents = nonce_bytes.to_vec();
+	contents.extend(encrypted);
+	Ok(contents)
+}
+
+/// Decrypts what `seal` produced.
+pub fn unseal(contents: &[u8]) -> AnalyzerResult<Vec<u8>> {
+	if contents.len() < NONCE_LEN {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "Sealed data is truncated").into());
+	}
+
+	let (nonce_bytes, encrypted) = contents.split_at(NONCE_LEN);
+	let cipher = Aes256Gcm::new_from_slice(&device_key()?)
+		.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+	let bytes = cipher
+		.decrypt(Nonce::from_slice(nonce_bytes), encrypted)
+		.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+	Ok(bytes)
+}
+
+/// Loads the per-device archive key from the login Keychain, creating it on
+/// first use. A key left in the data directory by an earlier version is moved
+/// into the Keychain so existing archives stay readable.
+#[cfg(target_os = "macos")]
+fn device_key() -> AnalyzerResult<[u8; 32]> {
+	use security_framework::passwords::{get_generic_password, set_generic_password};
+
+	match get_generic_password(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT) {
+		Ok(bytes) => return to_key(bytes),
+		Err(e) if e.code() != ERR_SEC_ITEM_NOT_FOUND => {
+			return Err(io::Error::new(io::ErrorKind::Other, e).into())
+		}
+		Err(_) => {}
+	}
+
+	let path = storage::data_dir().join(DEVICE_KEY_FILE);
+	let key = match fs::read(&path) {
+		Ok(bytes) => to_key(bytes)?,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => rand::thread_rng().gen::<[u8; 32]>(),
+		Err(e) => return Err(e.into())
+	};
+	set_generic_password(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT, &key)
+		.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+	match fs::remove_file(&path) {
+		Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+		_ => {}
+	}
+	Ok(key)
+}
+
+/// Loads the per-device archive key, creating it on first use. Without a
+/// Keychain the key sits next to the data it protects, readable by anything
+/// running as the user, so the archive and cache are only obfuscated there:
+/// they can't be read off a copied file, but are no safer than the account.
+#[cfg(not(target_os = "macos"))]
+fn device_key() -> AnalyzerResult<[u8; 32]> {
+	let path = storage::ensure_data_dir()?.join(DEVICE_KEY_FILE);
+
+	match fs::read(&path) {
+		Ok(bytes) => to_key(bytes),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => {
+			let key = rand::thread_rng().gen::<[u8; 32]>();
+			write_private(&path, &key)?;
+			Ok(key)
+		}
+		Err(e) => Err(e.into())
+	}
+}
+
+/// Removes the archive key from the Keychain, leaving anything sealed with
+/// it unreadable. The file-based key goes with the data directory.
+pub fn forget_device_key() -> AnalyzerResult<()> {
+	#[cfg(target_os = "macos")]
+	{
+		use security_framework::passwords::delete_generic_password;
+
+		match delete_generic_password(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT) {
+			Err(e) if e.code() != ERR_SEC_ITEM_NOT_FOUND => {
+				return Err(io::Error::new(io::ErrorKind::Other, e).into())
+			}
+			_ => {}
+		}
+	}
+	Ok(())
+}
+
+fn to_key(bytes: Vec<u8>) -> AnalyzerResult<[u8; 32]> {
+	bytes
+		.try_into()
+		.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Device key is corrupt").into())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn write_private(path: &std::path::Path, contents: &[u8]) -> io::Result<()> {
+	use std::io::Write;
+	use std::os::unix::fs::OpenOptionsExt;
+
+	fs::OpenOptions::new()
+		.write(true)
+		.create_new(true)
+		.mode(0o600)
+		.open(path)?
+		.write_all(contents)
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &std::path::Path, contents: &[u8]) -> io::Result<()> {
+	fs::write(path, contents)
+}
+
+fn archive_dir() -> PathBuf {
+	storage::data_dir().join(ARCHIVE_DIR)
+}
+
+fn entry_path(id: &str) -> PathBuf {
+	archive_dir().join(format!("{}.bin", id))
+}
+
+fn is_valid_id(id: &str) -> bool {
+	!id.is_empty() && id.chars().all(|c| c.is_ascii_hexdigit() || c == '-')
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+
+use crate::{readonly, AnalyzerResult};
+
+/// Metadata for a single attachment row. File contents are never read.
+#[derive(Debug, Clone)]
+pub struct Attachment {
+	pub mime_type: Option<String>,
+	pub uti: Option<String>,
+	pub transfer_name: Option<String>,
+	pub total_bytes: i64
+}
+
+impl Attachment {
+	pub fn is_photo(&self) -> bool {
+		self.mime_type.as_deref().is_some_and(|mime| mime.starts_with("image/") && mime != "image/gif")
+	}
+
+	pub fn is_gif(&self) -> bool {
+		self.mime_type.as_deref() == Some("image/gif") ||
+			self.uti.as_deref() == Some("com.compuserve.gif")
+	}
+
+	pub fn is_video(&self) -> bool {
+		self.mime_type.as_deref().is_some_and(|mime| mime.starts_with("video/"))
+	}
+
+	/// Audio messages recorded in Messages are stored as .caf files.
+	pub fn is_voice_memo(&self) -> bool {
+		self.uti.as_deref() == Some("com.apple.coreaudio-format") ||
+			self.mime_type.as_deref() == Some("audio/x-caf") ||
+			self.transfer_name
+				.as_deref()
+				.is_some_and(|name| name.to_lowercase().ends_with(".caf"))
+	}
+
+	pub fn is_vcard(&self) -> bool {
+		matches!(self.mime_type.as_deref(), Some("text/vcard" | "text/x-vcard")) ||
+			self.uti.as_deref() == Some("public.vcard") ||
+			self.transfer_name
+				.as_deref()
+				.is_some_and(|name| name.to_lowercase().ends_with(".vcf"))
+	}
+}
+
+/// Attachments keyed by the ROWID of the message they belong to.
+pub struct Attachments {
+	by_message: HashMap<i32, Vec<Attachment>>
+}
+
+impl Attachments {
+	pub fn new(db: &Connection) -> AnalyzerResult<Self> {
+		let mut statement = readonly::prepare(
+			db,
+			"SELECT j.message_id, a.mime_type, a.uti, a.transfer_name, a.total_bytes
+			FROM message_attachment_join j
+			JOIN attachment a ON a.ROWID = j.attachment_id"
+		)?;
+
+		let mut by_message: HashMap<i32, Vec<Attachment>> = HashMap::new();
+		let rows = statement.query_map([], |row| {
+			Ok((
+				row.get::<_, i32>(0)?,
+				Attachment {
+					mime_type: row.get(1)?,
+					uti: row.get(2)?,
+					transfer_name: row.get(3)?,
+					total_bytes: row.get::<_, Option<i64>>(4)?.unwrap_or(0)
+				}
+			))
+		})?;
+		for row in rows {
+			let (message_id, attachment) = row?;
+			by_message.entry(message_id).or_default().push(attachment);
+		}
+
+		Ok(Self { by_message })
+	}
+
+	pub fn for_message(&self, message_id: i32) -> &[Attachment] {
+		self.by_message.get(&message_id).map(Vec::as_slice).unwrap_or_default()
+	}
+}
@@ -0,0 +1,83 @@
+//! Proof of size for uploads. The server publishes an HMAC key and every
+//! upload carries an HMAC over its length and the stats schema, so the backend
+//! can turn away oversized or malformed uploads before storing anything. The
+//! MAC covers only those two values, never the payload, so the server learns
+//! nothing it couldn't already see from the request.
+
+use std::io;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use hmac::{Hmac, Mac};
+use reqwest::Method;
+use serde::Deserialize;
+use sha2::Sha256;
+
+use crate::network::PinnedClient;
+use crate::{build_info, AnalyzerResult};
+
+/// Key the server currently accepts attestations for.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerKey {
+	/// Lets the server rotate keys without rejecting uploads in flight
+	pub key_id: String,
+	/// Base64
+	key: String
+}
+
+#[derive(Debug, Clone)]
+pub struct Attestation {
+	pub key_id: String,
+	pub length: usize,
+	pub schema: String,
+	/// Hex HMAC-SHA256 of `"{length}:{schema}"`
+	pub mac: String
+}
+
+impl Attestation {
+	pub fn new(key: &ServerKey, length: usize) -> AnalyzerResult<Self> {
+		let secret = STANDARD
+			.decode(&key.key)
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+		let schema = build_info::schema_hash();
+		let mut mac = Hmac::<Sha256>::new_from_slice(&secret)
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+		mac.update(format!("{}:{}", length, schema).as_bytes());
+
+		Ok(Self {
+			key_id: key.key_id.clone(),
+			length,
+			schema,
+			mac: hex::encode(mac.finalize().into_bytes())
+		})
+	}
+
+	pub fn headers(&self) -> [(&'static str, String); 4] {
+		[
+			("X-Attestation-Key-Id", self.key_id.clone()),
+			("X-Payload-Length", self.length.to_string()),
+			("X-Schema-Hash", self.schema.clone()),
+			("X-Payload-Attestation", self.mac.clone())
+		]
+	}
+}
+
+/// Fetches the published key from the API at `base_url`.
+pub async fn fetch_key(network: &PinnedClient, base_url: &str) -> AnalyzerResult<ServerKey> {
+	let url = format!("{}/api/upload-key", base_url);
+	let response = network
+		.request(Method::GET, &url)?
+		.timeout(Duration::from_secs(10))
+		.send()
+		.await
+		.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+	let response = network.verify(response)?;
+	if !response.status().is_success() {
+		let message = format!("Fetching the upload key failed with status {}", response.status());
+		return Err(io::Error::new(io::ErrorKind::Other, message).into());
+	}
+
+	Ok(response.json().await.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?)
+}
@@ -0,0 +1,65 @@
+//! Separates automated senders (bank alerts, 2FA codes, delivery bots) from
+//! people, so they don't show up in relationship stats.
+
+use imessage_database::tables::messages::Message;
+
+use crate::chats::Chats;
+use crate::contacts::Contacts;
+use crate::handles::Handles;
+use crate::options::FetchOptions;
+
+/// Local parts of email senders that never belong to a person
+const BOT_MAILBOXES: &[&str] =
+	&["alerts", "donotreply", "do-not-reply", "no-reply", "noreply", "notifications", "notify"];
+
+/// Whether `handle_id` looks like an automated sender. Anyone saved in my
+/// contacts counts as a person unless listed in `automated_senders`.
+pub fn is_automated(handle_id: &str, contacts: &Contacts, options: &FetchOptions) -> bool {
+	let listed = |list: &Option<Vec<String>>| {
+		list.iter().flatten().any(|entry| entry.eq_ignore_ascii_case(handle_id))
+	};
+	if listed(&options.automated_senders) {
+		return true;
+	}
+	if listed(&options.human_senders) || contacts.get_name(handle_id).is_some() {
+		return false;
+	}
+
+	if let Some((mailbox, _)) = handle_id.split_once('@') {
+		return BOT_MAILBOXES.contains(&mailbox.to_ascii_lowercase().as_str());
+	}
+	let digits = handle_id.strip_prefix('+').unwrap_or(handle_id);
+	// Short codes are 3-6 digits; alphanumeric sender IDs like "AMAZON" aren't
+	// phone numbers at all
+	if digits.chars().all(|c| c.is_ascii_digit()) {
+		(3..=6).contains(&digits.len())
+	} else {
+		!handle_id.is_empty()
+	}
+}
+
+/// Splits messages into (people, automated senders), keeping date order.
+/// Group chats always count as people. Returns everything as people when
+/// filtering is turned off.
+pub fn split(
+	messages: Vec<Message>, contacts: &Contacts, handles: &Handles, chats: &Chats,
+	options: &FetchOptions
+) -> (Vec<Message>, Vec<Message>) {
+	if !options.filter_automated_senders.unwrap_or(true) {
+		return (messages, Vec::new());
+	}
+
+	let (automated, people): (Vec<Message>, Vec<Message>) =
+		messages.into_iter().partition(|message| {
+			let counterpart = match message.chat_id.and_then(|id| chats.get(id)) {
+				Some(chat) if chat.is_group() => None,
+				Some(chat) => chat.members.first().copied(),
+				None => message.handle_id.filter(|&handle| handle != 0)
+			};
+			counterpart
+				.and_then(|handle| handles.get(handle))
+				.is_some_and(|handle_id| is_automated(handle_id, contacts, options))
+		});
+
+	(people, automated)
+}
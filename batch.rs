@@ -0,0 +1,30 @@
+//! Wrappeds for several people on one Mac, e.g. family members with their own
+//! macOS accounts or chat.db backups. Each person is analyzed in turn with
+//! the shared options; only what belongs to one person's databases is reset.
+
+use napi_derive::napi;
+
+use crate::options::FetchOptions;
+
+/// One person's databases for `fetch_stats_batch`.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct BatchPerson {
+	/// Tells the results and progress updates apart, e.g. the account name
+	pub label: String,
+	pub chat_db_path: String,
+	pub address_book_path: String
+}
+
+/// `options` pointed at `person`'s databases. The handles and AddressBook
+/// sources they name belong to someone else's databases, so they're
+/// detected again for each person.
+pub fn options_for(options: &FetchOptions, person: &BatchPerson) -> FetchOptions {
+	FetchOptions {
+		chat_db_path: Some(person.chat_db_path.clone()),
+		address_book_path: Some(person.address_book_path.clone()),
+		my_handles: None,
+		address_book_sources: None,
+		..options.clone()
+	}
+}
@@ -0,0 +1,5 @@
+//! Needs the `cli` feature: `cargo run --features cli --bin messages-wrapped`.
+
+fn main() {
+	std::process::exit(messages_wrapped::cli::main());
+}
@@ -0,0 +1,29 @@
+//! Identifies the code that produced a payload. Everything here is fixed at
+//! compile time and contains no build timestamps, so reproducible builds of
+//! the same commit stamp identical metadata.
+
+use sha2::{Digest, Sha256};
+
+use crate::stats::stats::{BuildInfo, YearsStats};
+
+const PROTO: &str = include_str!("stats.proto");
+
+/// Metadata of this build. The commit comes from `MESSAGES_WRAPPED_GIT_COMMIT`
+/// at compile time, set by the release build.
+pub fn current() -> BuildInfo {
+	BuildInfo {
+		crate_version: env!("CARGO_PKG_VERSION").to_string(),
+		git_commit: option_env!("MESSAGES_WRAPPED_GIT_COMMIT").map(String::from),
+		schema_hash: schema_hash()
+	}
+}
+
+/// Stamps `stats` with this build's metadata before it's encrypted.
+pub fn stamp(stats: &mut YearsStats) {
+	stats.build = Some(current());
+}
+
+/// First 16 hex characters of the SHA-256 of stats.proto.
+pub fn schema_hash() -> String {
+	hex::encode(&Sha256::digest(PROTO.as_bytes())[..8])
+}
@@ -0,0 +1,64 @@
+//! Keeps a background Messages sync from aborting the run. chat.db is read
+//! live, so SQLite may report it busy or locked while Messages writes; we wait
+//! on a busy timeout first and then retry the whole query a few times.
+
+use std::thread;
+use std::time::Duration;
+
+use rusqlite::{Connection, ErrorCode};
+
+use crate::progress::Reporter;
+use crate::{AnalyzerError, AnalyzerResult};
+
+/// How long a single statement waits for a lock before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_RETRIES: u32 = 3;
+const FIRST_BACKOFF: Duration = Duration::from_millis(500);
+
+pub fn configure(db: &Connection) -> AnalyzerResult<()> {
+	db.busy_timeout(BUSY_TIMEOUT)?;
+	Ok(())
+}
+
+/// Runs `operation`, retrying with doubling backoff while the database is
+/// busy or locked. Other errors are returned straight away. Each wait is
+/// reported as a "waitingForDatabase" update at the run's current percent.
+pub fn with_retry<T, F>(progress: &Reporter, mut operation: F) -> AnalyzerResult<T>
+where
+	F: FnMut() -> AnalyzerResult<T>
+{
+	let mut backoff = FIRST_BACKOFF;
+	let mut attempt = 0;
+	loop {
+		match operation() {
+			Err(e) if attempt < MAX_RETRIES && is_busy(&e) => {
+				attempt += 1;
+				let percent = progress.last().map_or(0.0, |last| last.percent);
+				let detail = format!(
+					"chat.db is busy, retrying in {:?} ({}/{})",
+					backoff,
+					attempt,
+					MAX_RETRIES
+				);
+				progress.report_detail("waitingForDatabase", percent, detail);
+				thread::sleep(backoff);
+				backoff *= 2;
+			}
+			result => return result
+		}
+	}
+}
+
+fn is_busy(error: &AnalyzerError) -> bool {
+	match error {
+		AnalyzerError::Sql(rusqlite::Error::SqliteFailure(e, _)) => {
+			matches!(e.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+		}
+		// imessage_database wraps the SQLite error, only its message survives
+		AnalyzerError::Table(e) => {
+			let message = e.to_string();
+			message.contains("database is locked") || message.contains("database is busy")
+		}
+		_ => false
+	}
+}
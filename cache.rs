@@ -0,0 +1,408 @@
+//! Incremental analysis. Finished years are kept as encrypted protobuf
+//! snapshots in the data directory's `cache` folder, next to an index with
+//! the chat.db modification time, the highest message ROWID analyzed (the
+//! watermark) and how many messages each month had. A re-run reads only the
+//! rows above the watermark to find the months that gained messages and
+//! recomputes from the first year they touch; earlier years come from the
+//! cache. Deleted messages or different options start over. Each chat.db has
+//! its own slot, so batch runs over several people don't evict each other;
+//! the default one stays at the top of the folder.
+//!
+//! Snapshots are per year, not per month: a year's stats are leaderboards,
+//! streaks and reply times that can't be added up from monthly parts, so a
+//! year that gained messages is recomputed whole, even for one message in
+//! December. Months are only tracked to find that year.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use std::{fs, io};
+
+use prost::Message as ProstMessage;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::insights::local_time;
+use crate::options::FetchOptions;
+use crate::stats::stats::{YearStats, YearsStats};
+use crate::{archive, build_info, insights, paths, readonly, storage, supplemental, AnalyzerResult};
+
+const CACHE_DIR: &str = "cache";
+const INDEX_FILE: &str = "index.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheIndex {
+	/// Hash of the options and build the snapshots were computed with
+	pub fingerprint: String,
+	#[serde(flatten)]
+	pub chat_db: ChatDbState,
+	pub years: Vec<i32>
+}
+
+/// What chat.db looked like when it was analyzed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatDbState {
+	/// Modification time of chat.db or its write-ahead log, whichever is
+	/// later, in unix milliseconds
+	pub modified: u64,
+	/// Highest message ROWID
+	pub watermark: i64,
+	/// Messages at or below the watermark, so deletions can be noticed
+	pub message_count: i64,
+	/// Messages per month, keyed YYYY-MM in local time
+	pub months: BTreeMap<String, i64>
+}
+
+/// What a run can take from the cache.
+pub enum Plan {
+	/// Nothing was added since the cached run, every year can be reused
+	Unchanged(YearsStats),
+	/// Years before the first one with new messages are reused, the rest are
+	/// recomputed
+	Partial { reused: Vec<YearStats>, chat_db: ChatDbState },
+	/// Nothing usable is cached
+	Full(ChatDbState)
+}
+
+/// Compares chat.db with the cached index to decide what has to be analyzed.
+pub fn plan(chat_db_path: &Path, options: &FetchOptions) -> AnalyzerResult<Plan> {
+	let dir = slot_dir(chat_db_path);
+	let modified = chat_db_modified(chat_db_path);
+	let index = read_index(&dir)?.filter(|index| index.fingerprint == fingerprint(options));
+	let Some(index) = index else {
+		return Ok(Plan::Full(scan(chat_db_path, modified, None)?.0));
+	};
+
+	// Always scanned: Messages writes new rows to chat.db-wal and only
+	// touches chat.db itself on checkpoints, so its modification time can't
+	// tell whether anything arrived
+	let (state, previous_count) = scan(chat_db_path, modified, Some(&index.chat_db))?;
+	match change(&index.chat_db, &state, previous_count) {
+		Change::Rewritten => Ok(Plan::Full(scan(chat_db_path, modified, None)?.0)),
+		Change::None => unchanged(&dir, index),
+		Change::From(changed_year) => {
+			let reused_years: Vec<i32> =
+				index.years.iter().copied().filter(|&year| year < changed_year).collect();
+			Ok(Plan::Partial { reused: load_years(&dir, &reused_years)?, chat_db: state })
+		}
+	}
+}
+
+/// How chat.db changed since a cached run.
+#[derive(Debug, PartialEq, Eq)]
+enum Change {
+	None,
+	/// Messages were added, the earliest of them in this year
+	From(i32),
+	/// Rows at or below the old watermark disappeared
+	Rewritten
+}
+
+/// Compares the `cached` state with the `current` scan, which found
+/// `previous_count` rows at or below the cached watermark.
+fn change(cached: &ChatDbState, current: &ChatDbState, previous_count: i64) -> Change {
+	// Fewer rows at or below the old watermark means messages were deleted,
+	// which can change any year
+	if previous_count != cached.message_count {
+		return Change::Rewritten;
+	}
+
+	current
+		.months
+		.iter()
+		.filter(|&(month, &count)| cached.months.get(month) != Some(&count))
+		.filter_map(|(month, _)| month.get(..4)?.parse::<i32>().ok())
+		.min()
+		.map_or(Change::None, Change::From)
+}
+
+fn unchanged(dir: &Path, index: CacheIndex) -> AnalyzerResult<Plan> {
+	let stats = load_years(dir, &index.years)?;
+	Ok(Plan::Unchanged(YearsStats { years: index.years, stats, build: None }))
+}
+
+/// `options` limited to the years after the reused ones, so only those are
+/// read from chat.db.
+pub fn resume_options(options: &FetchOptions, reused: &[YearStats]) -> FetchOptions {
+	let mut options = options.clone();
+	if let Some(last) = reused.iter().map(|year| year.year).max() {
+		let resume = format!("{}-01-01", last + 1);
+		if options.from.as_ref().map_or(true, |from| *from < resume) {
+			options.from = Some(resume);
+		}
+	}
+	options
+}
+
+/// Adds the reused years back in front of the recomputed ones and compares
+/// each year with the one before again.
+pub fn merge(stats: &mut YearsStats, reused: Vec<YearStats>, options: &FetchOptions) {
+	if reused.is_empty() {
+		return;
+	}
+	stats.stats.retain(|year| !reused.iter().any(|cached| cached.year == year.year));
+	stats.stats.extend(reused);
+	stats.stats.sort_by_key(|year| year.year);
+	stats.years = stats.stats.iter().map(|year| year.year).collect();
+	insights::compare_years(stats, options);
+}
+
+/// Snapshots every year of a finished run. Runs cut short by the time budget
+/// aren't cached, their missing stats would stick around.
+pub fn store(
+	stats: &YearsStats, chat_db: ChatDbState, options: &FetchOptions
+) -> AnalyzerResult<()> {
+	if stats.stats.iter().any(|year| !year.skipped_stats.is_empty()) {
+		return Ok(());
+	}
+
+	let dir = slot_dir(&options.chat_db_path());
+	fs::create_dir_all(&dir)?;
+	for year in &stats.stats {
+		fs::write(year_path(&dir, year.year), archive::seal(&year.encode_to_vec())?)?;
+	}
+
+	let years = stats.years.clone();
+	let index = CacheIndex { fingerprint: fingerprint(options), chat_db, years };
+	let tmp_path = dir.join(format!("{}.tmp", INDEX_FILE));
+	fs::write(&tmp_path, serde_json::to_vec_pretty(&index)?)?;
+	fs::rename(&tmp_path, dir.join(INDEX_FILE))?;
+	Ok(())
+}
+
+/// Deletes every snapshot. Returns whether anything was cached.
+pub fn invalidate() -> io::Result<bool> {
+	match fs::remove_dir_all(cache_dir()) {
+		Ok(()) => Ok(true),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+		Err(e) => Err(e)
+	}
+}
+
+/// The default chat.db's cached index and the snapshots' size on disk,
+/// `None` when nothing is cached.
+pub fn status() -> AnalyzerResult<Option<(CacheIndex, u64)>> {
+	let Some(index) = read_index(&cache_dir())? else {
+		return Ok(None);
+	};
+	let size = fs::read_dir(cache_dir())?
+		.filter_map(Result::ok)
+		.filter_map(|entry| entry.metadata().ok())
+		.filter(|metadata| metadata.is_file())
+		.map(|metadata| metadata.len())
+		.sum();
+	Ok(Some((index, size)))
+}
+
+/// Whether the cache was built from chat.db as it is now, going by the
+/// modification times of chat.db and its write-ahead log.
+pub fn is_current(index: &CacheIndex, chat_db_path: &Path) -> bool {
+	index.chat_db.modified == chat_db_modified(chat_db_path)
+}
+
+/// Counts messages per month. With `previous`, only rows above its watermark
+/// are read and added to its months. Also returns how many rows are at or
+/// below the previous watermark now.
+fn scan(
+	chat_db_path: &Path, modified: u64, previous: Option<&ChatDbState>
+) -> AnalyzerResult<(ChatDbState, i64)> {
+	let db = readonly::open(chat_db_path)?;
+	let watermark = previous.map_or(0, |state| state.watermark);
+	let mut months = previous.map(|state| state.months.clone()).unwrap_or_default();
+
+	let previous_count: i64 =
+		readonly::prepare(&db, "SELECT COUNT(*) FROM message WHERE ROWID <= ?1")?
+			.query_row([watermark], |row| row.get(0))?;
+
+	let mut statement = readonly::prepare(&db, "SELECT ROWID, date FROM message WHERE ROWID > ?1")?;
+	let mut rows = statement.query([watermark])?;
+	let mut highest = watermark;
+	let mut added = 0;
+	while let Some(row) = rows.next()? {
+		let (rowid, date): (i64, i64) = (row.get(0)?, row.get(1)?);
+		highest = highest.max(rowid);
+		added += 1;
+		if let Some(time) = local_time(date) {
+			*months.entry(time.format("%Y-%m").to_string()).or_default() += 1;
+		}
+	}
+	drop(rows);
+	drop(statement);
+	let _ = db.close();
+
+	let state =
+		ChatDbState { modified, watermark: highest, message_count: previous_count + added, months };
+	Ok((state, previous_count))
+}
+
+fn load_years(dir: &Path, years: &[i32]) -> AnalyzerResult<Vec<YearStats>> {
+	years
+		.iter()
+		.map(|&year| {
+			let bytes = archive::unseal(&fs::read(year_path(dir, year))?)?;
+			Ok(YearStats::decode(bytes.as_slice())
+				.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?)
+		})
+		.collect()
+}
+
+fn read_index(dir: &Path) -> AnalyzerResult<Option<CacheIndex>> {
+	match fs::read(dir.join(INDEX_FILE)) {
+		Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+		Err(e) => Err(e.into())
+	}
+}
+
+/// Hashes everything besides chat.db that the numbers depend on: the options
+/// that change them, this build, the supplemental databases and when the
+/// AddressBook last changed.
+fn fingerprint(options: &FetchOptions) -> String {
+	let options = FetchOptions {
+		time_budget_seconds: None,
+		provenance: None,
+		archive: None,
+		upload_transport: None,
+		upload_target: None,
+		low_impact: None,
+		engine: None,
+		cache: None,
+		..options.clone()
+	};
+	let inputs = format!(
+		"{:?}{:?}{:?}{}",
+		options,
+		build_info::current(),
+		supplemental::list().unwrap_or_default(),
+		modified_millis(&options.address_book_path())
+	);
+	hex::encode(&Sha256::digest(inputs.as_bytes())[..16])
+}
+
+/// The later modification time of chat.db and chat.db-wal, where new rows
+/// land until the next checkpoint.
+fn chat_db_modified(chat_db_path: &Path) -> u64 {
+	let mut wal = chat_db_path.as_os_str().to_owned();
+	wal.push("-wal");
+	modified_millis(chat_db_path).max(modified_millis(Path::new(&wal)))
+}
+
+fn modified_millis(path: &Path) -> u64 {
+	fs::metadata(path)
+		.and_then(|metadata| metadata.modified())
+		.ok()
+		.and_then(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok())
+		.map_or(0, |since| since.as_millis() as u64)
+}
+
+fn cache_dir() -> PathBuf {
+	storage::data_dir().join(CACHE_DIR)
+}
+
+/// Where the snapshots of `chat_db_path` go: the cache folder itself for the
+/// default chat.db, a subfolder named by a hash of the path for others.
+fn slot_dir(chat_db_path: &Path) -> PathBuf {
+	if chat_db_path == paths::chat_db() {
+		return cache_dir();
+	}
+	let hash = Sha256::digest(chat_db_path.to_string_lossy().as_bytes());
+	cache_dir().join(hex::encode(&hash[..8]))
+}
+
+fn year_path(dir: &Path, year: i32) -> PathBuf {
+	dir.join(format!("{}.bin", year))
+}
+
+#[cfg(test)]
+mod tests {
+	use rusqlite::Connection;
+
+	use super::*;
+
+	/// Apple-epoch seconds at noon UTC on the 15th, clear of any time zone's
+	/// month boundary
+	const JUNE_2023: i64 = 708_523_200;
+	const DECEMBER_2023: i64 = 724_334_400;
+	const MARCH_2024: i64 = 732_196_800;
+	const DECEMBER_2024: i64 = 755_956_800;
+
+	/// A chat.db with just the columns `scan` reads.
+	fn chat_db(name: &str, dates: &[i64]) -> PathBuf {
+		let path = std::env::temp_dir()
+			.join(format!("messages-wrapped-cache-{}-{}.db", name, std::process::id()));
+		let _ = fs::remove_file(&path);
+		let db = Connection::open(&path).unwrap();
+		db.execute("CREATE TABLE message (ROWID INTEGER PRIMARY KEY, date INTEGER)", []).unwrap();
+		add(&path, dates);
+		path
+	}
+
+	fn add(path: &Path, dates: &[i64]) {
+		let db = Connection::open(path).unwrap();
+		for date in dates {
+			db.execute("INSERT INTO message (date) VALUES (?1)", [date]).unwrap();
+		}
+	}
+
+	fn months(counts: &[(&str, i64)]) -> BTreeMap<String, i64> {
+		counts.iter().map(|&(month, count)| (month.to_string(), count)).collect()
+	}
+
+	#[test]
+	fn scan_counts_months_above_the_watermark() {
+		let path = chat_db("scan", &[JUNE_2023, JUNE_2023, DECEMBER_2023]);
+		let (first, _) = scan(&path, 0, None).unwrap();
+		assert_eq!(first.watermark, 3);
+		assert_eq!(first.message_count, 3);
+		assert_eq!(first.months, months(&[("2023-06", 2), ("2023-12", 1)]));
+
+		add(&path, &[MARCH_2024]);
+		let (second, previous_count) = scan(&path, 0, Some(&first)).unwrap();
+		assert_eq!(previous_count, 3);
+		assert_eq!(second.watermark, 4);
+		assert_eq!(second.months, months(&[("2023-06", 2), ("2023-12", 1), ("2024-03", 1)]));
+		let _ = fs::remove_file(&path);
+	}
+
+	#[test]
+	fn unchanged_database_reuses_everything() {
+		let path = chat_db("unchanged", &[JUNE_2023, MARCH_2024]);
+		let (cached, _) = scan(&path, 0, None).unwrap();
+		let (current, previous_count) = scan(&path, 0, Some(&cached)).unwrap();
+		assert_eq!(change(&cached, &current, previous_count), Change::None);
+		let _ = fs::remove_file(&path);
+	}
+
+	#[test]
+	fn appended_messages_recompute_from_their_year() {
+		let path = chat_db("appended", &[JUNE_2023, MARCH_2024]);
+		let (cached, _) = scan(&path, 0, None).unwrap();
+
+		add(&path, &[DECEMBER_2024]);
+		let (current, previous_count) = scan(&path, 0, Some(&cached)).unwrap();
+		assert_eq!(change(&cached, &current, previous_count), Change::From(2024));
+
+		// A late-synced message in an older month moves the start back
+		add(&path, &[DECEMBER_2023]);
+		let (current, previous_count) = scan(&path, 0, Some(&cached)).unwrap();
+		assert_eq!(change(&cached, &current, previous_count), Change::From(2023));
+		let _ = fs::remove_file(&path);
+	}
+
+	#[test]
+	fn deleted_messages_start_over() {
+		let path = chat_db("rewritten", &[JUNE_2023, MARCH_2024]);
+		let (cached, _) = scan(&path, 0, None).unwrap();
+
+		let db = Connection::open(&path).unwrap();
+		db.execute("DELETE FROM message WHERE ROWID = 1", []).unwrap();
+		drop(db);
+		add(&path, &[DECEMBER_2024]);
+		let (current, previous_count) = scan(&path, 0, Some(&cached)).unwrap();
+		assert_eq!(previous_count, 1);
+		assert_eq!(change(&cached, &current, previous_count), Change::Rewritten);
+		let _ = fs::remove_file(&path);
+	}
+}
@@ -0,0 +1,313 @@
+//! PNG share cards (message count, top contact, top emoji) drawn locally, so
+//! a wrapped can be shared as images without the web service. The fonts are
+//! embedded; characters they have no glyph for, color emoji among them, are
+//! left out rather than drawn as boxes. Word clouds are drawn on request.
+
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use image::codecs::png::PngEncoder;
+use image::{ColorType, ImageEncoder, Rgba, RgbaImage};
+use serde::Serialize;
+
+use crate::stats::stats::{WordCloudWord, YearStats, YearsStats};
+use crate::AnalyzerResult;
+
+const REGULAR: &[u8] = include_bytes!("fonts/DejaVuSans.ttf");
+const BOLD: &[u8] = include_bytes!("fonts/DejaVuSans-Bold.ttf");
+/// Portrait, the size stories and most messengers show uncropped
+const WIDTH: u32 = 1080;
+const HEIGHT: u32 = 1350;
+const MARGIN: f32 = 96.0;
+const WHITE: [u8; 3] = [255, 255, 255];
+/// Pixel sizes of the least and most used words of a word cloud
+const MIN_WORD_SIZE: f32 = 28.0;
+const MAX_WORD_SIZE: f32 = 150.0;
+/// Space kept between words
+const WORD_GAP: f32 = 8.0;
+/// Pixels the spiral moves outward per radian, and how far it goes
+const SPIRAL_SPACING: f32 = 2.0;
+const SPIRAL_STEPS: usize = 6000;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Card {
+	pub year: i32,
+	/// "messageCount", "topContact", "topEmoji" or "wordCloud"
+	pub kind: &'static str,
+	pub path: PathBuf
+}
+
+struct Fonts<'a> {
+	regular: FontRef<'a>,
+	bold: FontRef<'a>
+}
+
+/// What goes on a card, top to bottom.
+struct Content {
+	kind: &'static str,
+	heading: String,
+	headline: String,
+	caption: String,
+	/// Top and bottom of the background gradient
+	colors: [[u8; 3]; 2]
+}
+
+/// Writes every card the stats have data for into `out_dir`, named
+/// `<year>-<kind>.png`.
+pub fn render_all(stats: &YearsStats, out_dir: &Path) -> AnalyzerResult<Vec<Card>> {
+	let fonts = fonts()?;
+	fs::create_dir_all(out_dir)?;
+
+	let mut cards = Vec::new();
+	for year in &stats.stats {
+		for content in contents(year) {
+			// An emoji the fonts can't draw would leave the card blank
+			if !content.headline.chars().any(|c| fonts.bold.glyph_id(c).0 != 0) {
+				continue;
+			}
+			let path = out_dir.join(format!("{}-{}.png", year.year, content.kind));
+			fs::write(&path, render(&fonts, &content)?)?;
+			cards.push(Card { year: year.year, kind: content.kind, path });
+		}
+	}
+	Ok(cards)
+}
+
+/// Writes a word cloud of each year that has one into `out_dir`, named
+/// `<year>-wordCloud.png`. Kept apart from `render_all` since laying out the
+/// words takes far longer than drawing a card.
+pub fn render_word_clouds(stats: &YearsStats, out_dir: &Path) -> AnalyzerResult<Vec<Card>> {
+	let fonts = fonts()?;
+	fs::create_dir_all(out_dir)?;
+
+	let mut cards = Vec::new();
+	for year in &stats.stats {
+		let Some(cloud) = year.word_cloud.as_ref().filter(|cloud| !cloud.words.is_empty()) else {
+			continue;
+		};
+		let mut card = background([[191, 90, 242], [10, 132, 255]]);
+		let heading = format!("My words of {}", year.year);
+		draw_centered(&mut card, &fonts.regular, &heading, 56.0, MARGIN + 56.0);
+		draw_cloud(&mut card, &fonts.bold, &cloud.words);
+		draw_centered(&mut card, &fonts.bold, "Messages Wrapped", 36.0, HEIGHT as f32 - MARGIN);
+
+		let path = out_dir.join(format!("{}-wordCloud.png", year.year));
+		fs::write(&path, encode(&card)?)?;
+		cards.push(Card { year: year.year, kind: "wordCloud", path });
+	}
+	Ok(cards)
+}
+
+fn fonts() -> AnalyzerResult<Fonts<'static>> {
+	let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+	Ok(Fonts {
+		regular: FontRef::try_from_slice(REGULAR).map_err(invalid)?,
+		bold: FontRef::try_from_slice(BOLD).map_err(invalid)?
+	})
+}
+
+fn contents(year: &YearStats) -> Vec<Content> {
+	let mut contents = Vec::new();
+
+	if let Some(count) = year.message_count.as_ref().filter(|c| c.sent + c.received > 0) {
+		contents.push(Content {
+			kind: "messageCount",
+			heading: format!("My {} in messages", year.year),
+			headline: grouped(i64::from(count.sent) + i64::from(count.received)),
+			caption: format!(
+				"{} sent · {} received",
+				grouped(count.sent.into()),
+				grouped(count.received.into())
+			),
+			colors: [[10, 132, 255], [94, 92, 230]]
+		});
+	}
+
+	let top_contact = year.top_individual_chats.as_ref().and_then(|result| result.chats.first());
+	if let Some(chat) = top_contact {
+		contents.push(Content {
+			kind: "topContact",
+			heading: format!("My top contact of {}", year.year),
+			headline: chat.name.clone(),
+			caption: format!(
+				"{} messages between us",
+				grouped(i64::from(chat.sent) + i64::from(chat.received))
+			),
+			colors: [[255, 55, 95], [255, 159, 10]]
+		});
+	}
+
+	let top_emoji = year
+		.word_count
+		.as_ref()
+		.and_then(|count| count.emojis.as_ref())
+		.and_then(|emojis| emojis.sent.first());
+	if let Some(emoji) = top_emoji {
+		contents.push(Content {
+			kind: "topEmoji",
+			heading: format!("My emoji of {}", year.year),
+			headline: emoji.key.clone(),
+			caption: format!("Sent {} times", grouped(emoji.count.into())),
+			colors: [[48, 209, 88], [100, 210, 255]]
+		});
+	}
+
+	contents
+}
+
+fn render(fonts: &Fonts, content: &Content) -> AnalyzerResult<Vec<u8>> {
+	let mut card = background(content.colors);
+
+	let center = HEIGHT as f32 / 2.0;
+	draw_centered(&mut card, &fonts.regular, &content.heading, 56.0, center - 220.0);
+	draw_centered(&mut card, &fonts.bold, &content.headline, 200.0, center + 60.0);
+	draw_centered(&mut card, &fonts.regular, &content.caption, 48.0, center + 200.0);
+	draw_centered(&mut card, &fonts.bold, "Messages Wrapped", 36.0, HEIGHT as f32 - MARGIN);
+	encode(&card)
+}
+
+/// A blank card fading from the first color at the top to the second.
+fn background([top, bottom]: [[u8; 3]; 2]) -> RgbaImage {
+	RgbaImage::from_fn(WIDTH, HEIGHT, |_, y| {
+		let t = y as f32 / HEIGHT as f32;
+		let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+		Rgba([mix(top[0], bottom[0]), mix(top[1], bottom[1]), mix(top[2], bottom[2]), 255])
+	})
+}
+
+fn encode(card: &RgbaImage) -> AnalyzerResult<Vec<u8>> {
+	let mut png = Cursor::new(Vec::new());
+	PngEncoder::new(&mut png).write_image(card.as_raw(), WIDTH, HEIGHT, ColorType::Rgba8.into())?;
+	Ok(png.into_inner())
+}
+
+/// Places words largest first along a spiral out from the middle of the
+/// card, each at the first spot where it overlaps no word placed before.
+/// Words that find no spot are left out. Bigger words are drawn more opaque.
+fn draw_cloud(card: &mut RgbaImage, font: &FontRef, words: &[WordCloudWord]) {
+	let area = [MARGIN, MARGIN + 120.0, WIDTH as f32 - MARGIN, HEIGHT as f32 - MARGIN - 80.0];
+	let center = ((area[0] + area[2]) / 2.0, (area[1] + area[3]) / 2.0);
+	let mut placed: Vec<[f32; 4]> = Vec::new();
+
+	for word in words {
+		let glyphs: Vec<char> = word.word.chars().filter(|&c| font.glyph_id(c).0 != 0).collect();
+		if glyphs.is_empty() {
+			continue;
+		}
+		let weight = word.weight.clamp(0.0, 1.0);
+		let scale = PxScale::from(MIN_WORD_SIZE + (MAX_WORD_SIZE - MIN_WORD_SIZE) * weight.sqrt());
+		let scaled = font.as_scaled(scale);
+		let width = line_width(font, scale, &glyphs);
+		let height = scaled.ascent() - scaled.descent();
+
+		let spot = (0..SPIRAL_STEPS)
+			.map(|step| {
+				let angle = step as f32 * 0.1;
+				let left = center.0 + SPIRAL_SPACING * angle * angle.cos() - width / 2.0;
+				let top = center.1 + SPIRAL_SPACING * angle * angle.sin() - height / 2.0;
+				[left, top, left + width, top + height]
+			})
+			.find(|rect| fits(rect, &area) && !placed.iter().any(|other| overlaps(rect, other)));
+		let Some(rect) = spot else { continue };
+		draw(card, font, &glyphs, scale, rect[0], rect[1] + scaled.ascent(), 0.55 + 0.45 * weight);
+		placed.push(rect);
+	}
+}
+
+/// Whether `rect` (left, top, right, bottom) lies within `area`.
+fn fits(rect: &[f32; 4], area: &[f32; 4]) -> bool {
+	rect[0] >= area[0] && rect[1] >= area[1] && rect[2] <= area[2] && rect[3] <= area[3]
+}
+
+/// Whether two rects come closer than `WORD_GAP`.
+fn overlaps(a: &[f32; 4], b: &[f32; 4]) -> bool {
+	a[0] < b[2] + WORD_GAP &&
+		b[0] < a[2] + WORD_GAP &&
+		a[1] < b[3] + WORD_GAP &&
+		b[1] < a[3] + WORD_GAP
+}
+
+/// Draws one line of white text centered on the card with its baseline at
+/// `baseline`, shrunk from `size` pixels until it fits between the margins.
+fn draw_centered(card: &mut RgbaImage, font: &FontRef, text: &str, size: f32, baseline: f32) {
+	let glyphs: Vec<char> = text.chars().filter(|&c| font.glyph_id(c).0 != 0).collect();
+	let max_width = WIDTH as f32 - 2.0 * MARGIN;
+	let mut scale = PxScale::from(size);
+	let mut width = line_width(font, scale, &glyphs);
+	if width > max_width {
+		scale = PxScale::from(size * max_width / width);
+		width = line_width(font, scale, &glyphs);
+	}
+
+	draw(card, font, &glyphs, scale, (WIDTH as f32 - width) / 2.0, baseline, 1.0);
+}
+
+/// Draws `glyphs` in white from `x` with their baseline at `baseline`,
+/// blended over the card at `opacity`.
+fn draw(
+	card: &mut RgbaImage,
+	font: &FontRef,
+	glyphs: &[char],
+	scale: PxScale,
+	mut x: f32,
+	baseline: f32,
+	opacity: f32
+) {
+	let scaled = font.as_scaled(scale);
+	let mut previous = None;
+	for &c in glyphs {
+		let id = scaled.glyph_id(c);
+		if let Some(previous) = previous {
+			x += scaled.kern(previous, id);
+		}
+		let glyph = id.with_scale_and_position(scale, point(x, baseline));
+		if let Some(outline) = font.outline_glyph(glyph) {
+			let bounds = outline.px_bounds();
+			outline.draw(|gx, gy, coverage| {
+				let px = bounds.min.x as i64 + i64::from(gx);
+				let py = bounds.min.y as i64 + i64::from(gy);
+				if px < 0 || py < 0 || px >= i64::from(WIDTH) || py >= i64::from(HEIGHT) {
+					return;
+				}
+				let pixel = card.get_pixel_mut(px as u32, py as u32);
+				for (channel, &white) in pixel.0.iter_mut().zip(&WHITE) {
+					let under = *channel as f32;
+					*channel = (under + (white as f32 - under) * coverage * opacity) as u8;
+				}
+			});
+		}
+		x += scaled.h_advance(id);
+		previous = Some(id);
+	}
+}
+
+fn line_width(font: &FontRef, scale: PxScale, glyphs: &[char]) -> f32 {
+	let scaled = font.as_scaled(scale);
+	let mut width = 0.0;
+	let mut previous = None;
+	for &c in glyphs {
+		let id = scaled.glyph_id(c);
+		if let Some(previous) = previous {
+			width += scaled.kern(previous, id);
+		}
+		width += scaled.h_advance(id);
+		previous = Some(id);
+	}
+	width
+}
+
+/// 12345 as "12,345".
+fn grouped(value: i64) -> String {
+	let digits = value.unsigned_abs().to_string();
+	let mut grouped = String::new();
+	for (index, digit) in digits.chars().enumerate() {
+		if index > 0 && (digits.len() - index) % 3 == 0 {
+			grouped.push(',');
+		}
+		grouped.push(digit);
+	}
+	if value < 0 { format!("-{}", grouped) } else { grouped }
+}
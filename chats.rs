@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+
+use crate::{readonly, AnalyzerResult};
+
+#[derive(Debug, Clone)]
+pub struct ChatInfo {
+	pub guid: String,
+	pub chat_identifier: String,
+	pub display_name: Option<String>,
+	/// Handle ROWIDs of everyone in the chat other than me
+	pub members: Vec<i32>
+}
+
+impl ChatInfo {
+	pub fn is_group(&self) -> bool {
+		self.members.len() > 1
+	}
+}
+
+/// Chats keyed by ROWID, with their members.
+pub struct Chats {
+	by_id: HashMap<i32, ChatInfo>
+}
+
+impl Chats {
+	pub fn new(db: &Connection) -> AnalyzerResult<Self> {
+		let mut by_id = HashMap::new();
+
+		let mut statement =
+			readonly::prepare(db, "SELECT ROWID, guid, chat_identifier, display_name FROM chat")?;
+		let rows = statement.query_map([], |row| {
+			Ok((
+				row.get::<_, i32>(0)?,
+				ChatInfo {
+					guid: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+					chat_identifier: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+					display_name: row
+						.get::<_, Option<String>>(3)?
+						.filter(|name| !name.is_empty()),
+					members: Vec::new()
+				}
+			))
+		})?;
+		for row in rows {
+			let (id, chat) = row?;
+			by_id.insert(id, chat);
+		}
+
+		let mut statement =
+			readonly::prepare(db, "SELECT chat_id, handle_id FROM chat_handle_join")?;
+		let rows =
+			statement.query_map([], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?)))?;
+		for row in rows {
+			let (chat_id, handle_id) = row?;
+			if let Some(chat) = by_id.get_mut(&chat_id) {
+				chat.members.push(handle_id);
+			}
+		}
+
+		Ok(Self { by_id })
+	}
+
+	pub fn get(&self, chat_id: i32) -> Option<&ChatInfo> {
+		self.by_id.get(&chat_id)
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (&i32, &ChatInfo)> {
+		self.by_id.iter()
+	}
+}
@@ -0,0 +1,141 @@
+//! Terminal front end for power users who want to audit the pipeline without
+//! the Electron app. Built with the `cli` feature.
+
+use std::path::PathBuf;
+use std::{env, fs, io};
+
+use prost::Message as ProstMessage;
+
+use crate::options::FetchOptions;
+use crate::progress::Reporter;
+use crate::report::UploadReport;
+use crate::stats::stats::YearsStats;
+use crate::transport::HttpTransport;
+use crate::{generate_stats, send_stats, AnalyzerResult};
+
+const USAGE: &str = "\
+Usage: messages-wrapped [options]
+
+Options:
+  --chat-db <path>        chat.db to analyze (default: this user's)
+  --address-book <path>   AddressBook folder for contact names
+  --year <year>           Only analyze this year
+  --from <YYYY-MM-DD>     First day to analyze
+  --to <YYYY-MM-DD>       Last day to analyze
+  --format <format>       summary (default), json or proto
+  --engine <engine>       parallel (default) or fused
+  --output <path>         Write the stats here instead of stdout, needed for
+                          json and proto
+  --upload                Upload the stats after confirming what they disclose
+  --api-url <url>         Upload to this server instead of the default
+  -h, --help              Show this help";
+
+#[derive(Debug, Default)]
+struct Args {
+	options: FetchOptions,
+	format: String,
+	output: Option<PathBuf>,
+	upload: bool,
+	api_url: Option<String>
+}
+
+/// Runs the CLI and returns the process exit code.
+pub fn main() -> i32 {
+	let args = match parse_args(env::args().skip(1)) {
+		Ok(Some(args)) => args,
+		Ok(None) => {
+			println!("{}", USAGE);
+			return 0;
+		}
+		Err(message) => {
+			eprintln!("{}\n\n{}", message, USAGE);
+			return 2;
+		}
+	};
+
+	match run(args) {
+		Ok(()) => 0,
+		Err(e) => {
+			eprintln!("messages-wrapped: {}", e);
+			1
+		}
+	}
+}
+
+fn parse_args(mut argv: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+	let mut args = Args { format: String::from("summary"), ..Default::default() };
+	let mut year = None;
+
+	while let Some(flag) = argv.next() {
+		let mut value = || argv.next().ok_or_else(|| format!("{} needs a value", flag));
+		match flag.as_str() {
+			"--chat-db" => args.options.chat_db_path = Some(value()?),
+			"--address-book" => args.options.address_book_path = Some(value()?),
+			"--year" => {
+				let value = value()?;
+				year = Some(value.parse::<i32>().map_err(|_| format!("Invalid year {}", value))?);
+			}
+			"--from" => args.options.from = Some(value()?),
+			"--to" => args.options.to = Some(value()?),
+			"--format" => args.format = value()?,
+			"--engine" => args.options.engine = Some(value()?),
+			"--output" => args.output = Some(PathBuf::from(value()?)),
+			"--upload" => args.upload = true,
+			"--api-url" => args.api_url = Some(value()?),
+			"-h" | "--help" => return Ok(None),
+			_ => return Err(format!("Unknown option {}", flag))
+		}
+	}
+
+	if !matches!(args.format.as_str(), "summary" | "json" | "proto") {
+		return Err(format!("Unknown format {}", args.format));
+	}
+	// The library logs its progress to stdout, which would corrupt these
+	if args.format != "summary" && args.output.is_none() {
+		return Err(format!("--format {} needs --output", args.format));
+	}
+	if let Some(year) = year {
+		if args.options.from.is_some() || args.options.to.is_some() {
+			return Err(String::from("--year can't be combined with --from or --to"));
+		}
+		args.options.from = Some(format!("{}-01-01", year));
+		args.options.to = Some(format!("{}-12-31", year));
+	}
+	Ok(Some(args))
+}
+
+fn run(args: Args) -> AnalyzerResult<()> {
+	let (stats, warnings, _) = generate_stats(&args.options, &Reporter::default())?;
+	for warning in &warnings {
+		eprintln!("warning: {}", warning.message);
+	}
+
+	let output = match args.format.as_str() {
+		"json" => serde_json::to_vec_pretty(&stats)?,
+		"proto" => stats.encode_to_vec(),
+		_ => serde_json::to_vec_pretty(&UploadReport::new(&stats))?
+	};
+	match &args.output {
+		Some(path) => fs::write(path, output)?,
+		None => io::Write::write_all(&mut io::stdout(), &output)?
+	}
+
+	if args.upload && confirm_upload(&stats)? {
+		let runtime = tokio::runtime::Runtime::new()?;
+		let transport = HttpTransport::new(args.api_url);
+		let (share_url, _, _, _, _) = runtime.block_on(send_stats(&stats, &transport, true))?;
+		println!("\nShared at {}", share_url);
+	}
+
+	Ok(())
+}
+
+/// Shows what the upload would disclose and asks whether to go ahead.
+/// Anything but yes, including a closed stdin, declines.
+fn confirm_upload(stats: &YearsStats) -> io::Result<bool> {
+	let report = serde_json::to_string_pretty(&UploadReport::new(stats))?;
+	eprint!("\nThe upload would disclose:\n{}\n\nUpload? [y/N] ", report);
+	let mut answer = String::new();
+	io::stdin().read_line(&mut answer)?;
+	Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
@@ -0,0 +1,104 @@
+use serde::Serialize;
+
+use crate::stats::stats::{YearStats, YearsStats};
+
+/// Side-by-side comparison of two archived years, computed locally.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YearComparison {
+	pub first_year: i32,
+	pub second_year: i32,
+	pub volume: VolumeTrend,
+	pub shared_top_contacts: Vec<String>,
+	pub biggest_risers: Vec<Riser>
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumeTrend {
+	pub first_sent: i32,
+	pub first_received: i32,
+	pub second_sent: i32,
+	pub second_received: i32,
+	/// Change in total messages from the first year to the second, in percent
+	pub percent_change: f64
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Riser {
+	pub name: String,
+	pub first_count: i32,
+	pub second_count: i32
+}
+
+/// Picks `year` out of an archived run, or its latest year if not given.
+pub fn select_year(stats: &YearsStats, year: Option<i32>) -> Option<&YearStats> {
+	match year {
+		Some(year) => stats.stats.iter().find(|s| s.year == year),
+		None => stats.stats.iter().max_by_key(|s| s.year)
+	}
+}
+
+/// Lists up to `risers` contacts as the biggest risers.
+pub fn compare(first: &YearStats, second: &YearStats, risers: usize) -> YearComparison {
+	let (first_sent, first_received) = message_totals(first);
+	let (second_sent, second_received) = message_totals(second);
+	let first_total = (first_sent + first_received) as f64;
+	let second_total = (second_sent + second_received) as f64;
+
+	let first_contacts = top_contacts(first);
+	let second_contacts = top_contacts(second);
+
+	let shared_top_contacts = second_contacts
+		.iter()
+		.filter(|(name, _)| first_contacts.iter().any(|(other, _)| other == name))
+		.map(|(name, _)| name.clone())
+		.collect();
+
+	let mut biggest_risers: Vec<Riser> = second_contacts
+		.iter()
+		.map(|(name, second_count)| Riser {
+			name: name.clone(),
+			first_count: first_contacts
+				.iter()
+				.find(|(other, _)| other == name)
+				.map_or(0, |(_, count)| *count),
+			second_count: *second_count
+		})
+		.filter(|riser| riser.second_count > riser.first_count)
+		.collect();
+	biggest_risers.sort_by_key(|riser| -(riser.second_count - riser.first_count));
+	biggest_risers.truncate(risers);
+
+	YearComparison {
+		first_year: first.year,
+		second_year: second.year,
+		volume: VolumeTrend {
+			first_sent,
+			first_received,
+			second_sent,
+			second_received,
+			percent_change: if first_total > 0.0 {
+				(second_total - first_total) / first_total * 100.0
+			} else {
+				0.0
+			}
+		},
+		shared_top_contacts,
+		biggest_risers
+	}
+}
+
+fn message_totals(year: &YearStats) -> (i32, i32) {
+	year.message_count.as_ref().map_or((0, 0), |count| (count.sent, count.received))
+}
+
+/// Names and message totals of the year's top one-on-one chats.
+fn top_contacts(year: &YearStats) -> Vec<(String, i32)> {
+	year.top_individual_chats
+		.iter()
+		.flat_map(|result| &result.chats)
+		.map(|chat| (chat.name.clone(), chat.sent + chat.received))
+		.collect()
+}
@@ -0,0 +1,46 @@
+//! Brotli compression of payloads before they are sealed.
+
+use std::io::{Read, Write};
+
+use brotli::enc::writer::CompressorWriter;
+use brotli::enc::BrotliEncoderParams;
+
+use crate::AnalyzerResult;
+
+const BUFFER_SIZE: usize = 4096;
+
+pub fn compress(data: &[u8]) -> AnalyzerResult<Vec<u8>> {
+	let mut compressed = Vec::new();
+	let params = BrotliEncoderParams { quality: 11, lgwin: 22, ..Default::default() };
+	let mut compressor = CompressorWriter::with_params(&mut compressed, BUFFER_SIZE, &params);
+	compressor.write_all(data)?;
+	compressor.flush()?;
+	// Dropping the writer finishes the stream
+	drop(compressor);
+	Ok(compressed)
+}
+
+/// Reverses `compress`.
+pub fn decompress(data: &[u8]) -> AnalyzerResult<Vec<u8>> {
+	let mut decompressed = Vec::new();
+	brotli::Decompressor::new(data, BUFFER_SIZE).read_to_end(&mut decompressed)?;
+	Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn round_trips() {
+		let payload = "topTexters nightOwl 😂 haha ok lol ".repeat(50).into_bytes();
+		let compressed = compress(&payload).unwrap();
+		assert!(compressed.len() < payload.len());
+		assert_eq!(decompress(&compressed).unwrap(), payload);
+	}
+
+	#[test]
+	fn round_trips_empty_payload() {
+		assert!(decompress(&compress(&[]).unwrap()).unwrap().is_empty());
+	}
+}
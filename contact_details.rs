@@ -0,0 +1,150 @@
+//! Extra AddressBook fields (birthday, company, related names like "Mom")
+//! keyed by handle, for stats that can use them.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Datelike, TimeZone, Utc};
+use rusqlite::Connection;
+
+use crate::{readonly, AnalyzerResult};
+
+/// Seconds between the Unix epoch and the Core Data epoch (2001-01-01 UTC)
+const CORE_DATA_EPOCH_OFFSET: i64 = 978_307_200;
+
+#[derive(Debug, Clone, Default)]
+pub struct ContactDetail {
+	pub organization: Option<String>,
+	/// (month, day)
+	pub birthday: Option<(u32, u32)>,
+	/// How I labelled them on a card, e.g. "Mother" or "Sister"
+	pub relationship: Option<String>
+}
+
+#[derive(Default)]
+pub struct ContactDetails {
+	by_handle: HashMap<String, ContactDetail>
+}
+
+struct Record {
+	name: String,
+	detail: ContactDetail
+}
+
+impl ContactDetails {
+	/// Related names only count from my own card, found through
+	/// `my_handles`: on anyone else's card "Mother" is their mother.
+	pub fn new(address_book_dbs: &[Connection], my_handles: &[String]) -> Self {
+		let mine: HashSet<String> = my_handles
+			.iter()
+			.map(|handle| normalize(handle))
+			.filter(|handle| !handle.is_empty())
+			.collect();
+		let mut details = Self::default();
+		for db in address_book_dbs {
+			if let Err(e) = details.load(db, &mine) {
+				eprintln!("Skipping contact details from one AddressBook source: {:?}", e);
+			}
+		}
+		details
+	}
+
+	pub fn get(&self, handle_id: &str) -> Option<&ContactDetail> {
+		self.by_handle.get(&normalize(handle_id))
+	}
+
+	fn load(&mut self, db: &Connection, my_handles: &HashSet<String>) -> AnalyzerResult<()> {
+		let mut records: HashMap<i64, Record> = HashMap::new();
+		let mut statement = readonly::prepare(
+			db,
+			"SELECT Z_PK, ZFIRSTNAME, ZLASTNAME, ZORGANIZATION, ZBIRTHDAY FROM ZABCDRECORD"
+		)?;
+		let rows = statement.query_map([], |row| {
+			let first = row.get::<_, Option<String>>(1)?.unwrap_or_default();
+			let last = row.get::<_, Option<String>>(2)?.unwrap_or_default();
+			Ok((
+				row.get::<_, i64>(0)?,
+				format!("{} {}", first, last).trim().to_string(),
+				row.get::<_, Option<String>>(3)?.filter(|org| !org.is_empty()),
+				row.get::<_, Option<f64>>(4)?
+			))
+		})?;
+		for row in rows {
+			let (id, name, organization, birthday) = row?;
+			let detail = ContactDetail {
+				organization,
+				birthday: birthday.and_then(month_day),
+				relationship: None
+			};
+			records.insert(id, Record { name, detail });
+		}
+
+		let mut handles = Vec::new();
+		for query in [
+			"SELECT ZOWNER, ZFULLNUMBER FROM ZABCDPHONENUMBER",
+			"SELECT ZOWNER, ZADDRESS FROM ZABCDEMAILADDRESS"
+		] {
+			let mut statement = readonly::prepare(db, query)?;
+			let rows = statement.query_map([], |row| {
+				Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+			})?;
+			for row in rows {
+				if let (owner, Some(handle)) = row? {
+					handles.push((owner, normalize(&handle)));
+				}
+			}
+		}
+		let my_cards: HashSet<i64> = handles
+			.iter()
+			.filter(|(_, handle)| my_handles.contains(handle))
+			.map(|&(owner, _)| owner)
+			.collect();
+
+		let mut relationships: HashMap<String, String> = HashMap::new();
+		let mut statement =
+			readonly::prepare(db, "SELECT ZOWNER, ZLABEL, ZNAME FROM ZABCDRELATEDNAME")?;
+		let rows = statement.query_map([], |row| {
+			Ok((
+				row.get::<_, Option<i64>>(0)?,
+				row.get::<_, Option<String>>(1)?,
+				row.get::<_, Option<String>>(2)?
+			))
+		})?;
+		for row in rows {
+			let (Some(owner), Some(label), Some(name)) = row? else { continue };
+			if my_cards.contains(&owner) {
+				relationships.insert(name.trim().to_lowercase(), clean_label(&label));
+			}
+		}
+
+		for (owner, handle) in handles {
+			let Some(record) = records.get(&owner).filter(|r| !r.name.is_empty()) else {
+				continue;
+			};
+			let relationship = relationships.get(&record.name.to_lowercase()).cloned();
+			self.by_handle.insert(handle, ContactDetail { relationship, ..record.detail.clone() });
+		}
+
+		Ok(())
+	}
+}
+
+/// Phone numbers match on their last ten digits so "+1 (555) 123-4567" and
+/// "5551234567" are the same handle; emails ignore case.
+fn normalize(handle: &str) -> String {
+	if handle.contains('@') {
+		return handle.trim().to_lowercase();
+	}
+	let digits: String = handle.chars().filter(char::is_ascii_digit).collect();
+	digits[digits.len().saturating_sub(10)..].to_string()
+}
+
+/// AddressBook stores built-in labels as `_$!<Mother>!$_`.
+fn clean_label(label: &str) -> String {
+	label.trim_start_matches("_$!<").trim_end_matches(">!$_").to_string()
+}
+
+fn month_day(birthday: f64) -> Option<(u32, u32)> {
+	// Birthdays are stored at midday UTC, so the UTC date is the right one
+	let date = Utc.timestamp_opt(birthday as i64 + CORE_DATA_EPOCH_OFFSET, 0).single()?;
+	Some((date.month(), date.day()))
+}
@@ -0,0 +1,88 @@
+//! Migrations and iCloud sync hiccups can leave the same message in chat.db
+//! twice, either with the same GUID or re-imported under a new one.
+
+use std::collections::HashSet;
+
+use imessage_database::tables::messages::Message;
+
+use crate::insights::apple_seconds;
+
+/// Chat, sender, direction, second and text of a message
+type ContentKey = (Option<i32>, Option<i32>, bool, i64, String);
+
+/// Drops repeated messages, keeping the first. Messages are the same when
+/// they share a GUID, or when they have the same chat, sender, direction,
+/// text and second, so one text sent to two chats at once is kept in both.
+/// Text-less rows are only matched by GUID since photos sent together look
+/// identical otherwise. Returns how many were dropped.
+pub fn remove_duplicates(messages: &mut Vec<Message>) -> usize {
+	let mut guids: HashSet<String> = HashSet::with_capacity(messages.len());
+	let mut contents: HashSet<ContentKey> = HashSet::new();
+
+	let before = messages.len();
+	messages.retain(|message| {
+		if !guids.insert(message.guid.clone()) {
+			return false;
+		}
+		match message.text.as_deref().filter(|text| !text.is_empty()) {
+			Some(text) if message.date != 0 => contents.insert((
+				message.chat_id,
+				message.handle_id,
+				message.is_from_me,
+				apple_seconds(message.date),
+				text.to_string()
+			)),
+			_ => true
+		}
+	});
+	before - messages.len()
+}
+
+#[cfg(test)]
+mod tests {
+	use imessage_database::tables::table::Table;
+	use rusqlite::Connection;
+
+	use super::*;
+
+	/// 2024-01-01 in Apple-epoch nanoseconds
+	const DATE: i64 = 725_760_000_000_000_000;
+
+	/// `Message` has no constructor, so it is read from a one-row query.
+	fn message(guid: &str, chat_id: i32, date: i64, text: &str) -> Message {
+		let db = Connection::open_in_memory().unwrap();
+		db.query_row(
+			"SELECT 1 AS rowid, ?1 AS guid, ?2 AS chat_id, 5 AS handle_id, ?3 AS date, \
+			 ?4 AS text, 0 AS is_from_me, 1 AS is_read, 0 AS num_attachments, 0 AS num_replies",
+			rusqlite::params![guid, chat_id, date, text],
+			Message::from_row
+		)
+		.unwrap()
+	}
+
+	fn guids(messages: &[Message]) -> Vec<&str> {
+		messages.iter().map(|message| message.guid.as_str()).collect()
+	}
+
+	#[test]
+	fn drops_repeated_guids() {
+		let mut messages = vec![message("a", 1, DATE, "hi"), message("a", 1, DATE + 5, "hey")];
+		assert_eq!(remove_duplicates(&mut messages), 1);
+		assert_eq!(guids(&messages), ["a"]);
+	}
+
+	#[test]
+	fn drops_reimported_copies_in_the_same_chat() {
+		// Within the same second, whatever the sub-second part
+		let mut messages = vec![message("a", 1, DATE, "hi"), message("b", 1, DATE + 1_000, "hi")];
+		assert_eq!(remove_duplicates(&mut messages), 1);
+		assert_eq!(guids(&messages), ["a"]);
+	}
+
+	#[test]
+	fn keeps_one_text_sent_to_two_chats() {
+		let mut messages = vec![message("a", 1, DATE, "hi"), message("b", 2, DATE, "hi")];
+		assert_eq!(remove_duplicates(&mut messages), 0);
+		assert_eq!(guids(&messages), ["a", "b"]);
+	}
+}
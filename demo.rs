@@ -0,0 +1,245 @@
+//! A small fictional chat.db for showing a sample wrapped before the user has
+//! granted Full Disk Access. Generated on demand with a fixed seed, so every
+//! run produces the same demo.
+
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rusqlite::{params, Connection};
+
+use crate::AnalyzerResult;
+
+const SEED: u64 = 2024;
+const YEAR_START_UNIX: i64 = 1_704_067_200; // 2024-01-01 UTC
+const DAYS: i64 = 366;
+/// Seconds between the Unix epoch and the Apple epoch (2001-01-01 UTC)
+const APPLE_EPOCH_OFFSET: i64 = 978_307_200;
+pub const MY_HANDLE: &str = "me@example.com";
+
+/// (handle, how likely we talk on a given day)
+const PEOPLE: &[(&str, f64)] = &[
+	("alex.rivera@example.com", 0.8),
+	("sam.okafor@example.com", 0.6),
+	("jordan.lee@example.com", 0.45),
+	("priya.nair@example.com", 0.3),
+	("mom@example.com", 0.35),
+	("taylor.brooks@example.com", 0.15)
+];
+
+/// (display name, indexes into PEOPLE, how likely it is active on a day)
+const GROUPS: &[(&str, &[usize], f64)] =
+	&[("Roommates", &[0, 1, 2], 0.5), ("Family", &[4, 5], 0.2)];
+
+const PHRASES: &[&str] = &[
+	"hey what are you up to",
+	"lol",
+	"omg yes",
+	"did you see that",
+	"on my way",
+	"running 10 min late sorry",
+	"haha that's amazing",
+	"ok",
+	"wanna grab dinner tonight?",
+	"I'm so tired",
+	"can you send me the address",
+	"that movie was so good",
+	"happy friday!!",
+	"call me when you're free",
+	"no way 😂",
+	"love you ❤️",
+	"sounds good",
+	"what time works for you?",
+	"I just got home",
+	"bring snacks",
+	"did you finish the show yet",
+	"that's so funny",
+	"ugh mondays",
+	"see you soon",
+	"coffee tomorrow?",
+	"k",
+	"thank you so much!",
+	"the game last night was wild",
+	"I need a vacation",
+	"🔥🔥🔥"
+];
+
+/// chat.db tables the pipeline reads. Also used by the importers
+pub const SCHEMA: &str = "
+CREATE TABLE handle (
+	ROWID INTEGER PRIMARY KEY, id TEXT NOT NULL, country TEXT, service TEXT NOT NULL,
+	uncanonicalized_id TEXT, person_centric_id TEXT
+);
+CREATE TABLE chat (
+	ROWID INTEGER PRIMARY KEY, guid TEXT NOT NULL, style INTEGER, state INTEGER,
+	account_id TEXT, properties BLOB, chat_identifier TEXT, service_name TEXT, room_name TEXT,
+	account_login TEXT, is_archived INTEGER DEFAULT 0, last_addressed_handle TEXT,
+	display_name TEXT, group_id TEXT, is_filtered INTEGER DEFAULT 0,
+	successful_query INTEGER, engram_id TEXT, server_change_token TEXT,
+	ck_sync_state INTEGER DEFAULT 0, original_group_id TEXT,
+	last_read_message_timestamp INTEGER DEFAULT 0, cloudkit_record_id TEXT,
+	last_addressed_sim_id TEXT, is_blackholed INTEGER DEFAULT 0, syndication_date INTEGER DEFAULT 0,
+	syndication_type INTEGER DEFAULT 0
+);
+CREATE TABLE chat_handle_join (chat_id INTEGER, handle_id INTEGER);
+CREATE TABLE chat_message_join (
+	chat_id INTEGER, message_id INTEGER, message_date INTEGER DEFAULT 0
+);
+CREATE TABLE chat_recoverable_message_join (
+	chat_id INTEGER, message_id INTEGER, delete_date INTEGER
+);
+CREATE TABLE attachment (
+	ROWID INTEGER PRIMARY KEY, guid TEXT, created_date INTEGER DEFAULT 0, filename TEXT,
+	uti TEXT, mime_type TEXT, transfer_state INTEGER DEFAULT 0, is_outgoing INTEGER DEFAULT 0,
+	transfer_name TEXT, total_bytes INTEGER DEFAULT 0, is_sticker INTEGER DEFAULT 0,
+	hide_attachment INTEGER DEFAULT 0, emoji_image_short_description TEXT
+);
+CREATE TABLE message_attachment_join (message_id INTEGER, attachment_id INTEGER);
+CREATE TABLE message (
+	ROWID INTEGER PRIMARY KEY, guid TEXT UNIQUE NOT NULL, text TEXT, replace INTEGER DEFAULT 0,
+	service_center TEXT, handle_id INTEGER DEFAULT 0, subject TEXT, country TEXT,
+	attributedBody BLOB, version INTEGER DEFAULT 0, type INTEGER DEFAULT 0, service TEXT,
+	account TEXT, account_guid TEXT, error INTEGER DEFAULT 0, date INTEGER, date_read INTEGER,
+	date_delivered INTEGER, is_delivered INTEGER DEFAULT 0, is_finished INTEGER DEFAULT 0,
+	is_emote INTEGER DEFAULT 0, is_from_me INTEGER DEFAULT 0, is_empty INTEGER DEFAULT 0,
+	is_delayed INTEGER DEFAULT 0, is_auto_reply INTEGER DEFAULT 0, is_prepared INTEGER DEFAULT 0,
+	is_read INTEGER DEFAULT 0, is_system_message INTEGER DEFAULT 0, is_sent INTEGER DEFAULT 0,
+	has_dd_results INTEGER DEFAULT 0, is_service_message INTEGER DEFAULT 0,
+	is_forward INTEGER DEFAULT 0, was_downgraded INTEGER DEFAULT 0,
+	is_archive INTEGER DEFAULT 0, cache_has_attachments INTEGER DEFAULT 0,
+	cache_roomnames TEXT, was_data_detected INTEGER DEFAULT 0, was_deduplicated INTEGER DEFAULT 0,
+	is_audio_message INTEGER DEFAULT 0, is_played INTEGER DEFAULT 0, date_played INTEGER,
+	item_type INTEGER DEFAULT 0, other_handle INTEGER DEFAULT 0, group_title TEXT,
+	group_action_type INTEGER DEFAULT 0, share_status INTEGER DEFAULT 0,
+	share_direction INTEGER DEFAULT 0, is_expirable INTEGER DEFAULT 0,
+	expire_state INTEGER DEFAULT 0, message_action_type INTEGER DEFAULT 0,
+	message_source INTEGER DEFAULT 0, associated_message_guid TEXT,
+	associated_message_type INTEGER DEFAULT 0, balloon_bundle_id TEXT, payload_data BLOB,
+	expressive_send_style_id TEXT, associated_message_range_location INTEGER DEFAULT 0,
+	associated_message_range_length INTEGER DEFAULT 0, time_expressive_send_played INTEGER,
+	message_summary_info BLOB, ck_sync_state INTEGER DEFAULT 0, ck_record_id TEXT,
+	ck_record_change_tag TEXT, destination_caller_id TEXT, sr_ck_sync_state INTEGER DEFAULT 0,
+	sr_ck_record_id TEXT, sr_ck_record_change_tag TEXT, is_corrupt INTEGER DEFAULT 0,
+	reply_to_guid TEXT, sort_id INTEGER, is_spam INTEGER DEFAULT 0,
+	has_unseen_mention INTEGER DEFAULT 0, thread_originator_guid TEXT,
+	thread_originator_part TEXT, syndication_ranges TEXT, was_delivered_quietly INTEGER DEFAULT 0,
+	did_notify_recipient INTEGER DEFAULT 0, synced_syndication_ranges TEXT,
+	date_retracted INTEGER DEFAULT 0, date_edited INTEGER DEFAULT 0,
+	was_detonated INTEGER DEFAULT 0, part_count INTEGER, is_stewie INTEGER DEFAULT 0,
+	is_kt_verified INTEGER DEFAULT 0, is_sos INTEGER DEFAULT 0, is_critical INTEGER DEFAULT 0,
+	bia_reference_id TEXT, fallback_hash TEXT, associated_message_emoji TEXT
+);
+";
+
+/// Writes the demo database to `path`, replacing anything there.
+pub fn write_database(path: &Path) -> AnalyzerResult<()> {
+	let _ = std::fs::remove_file(path);
+	let mut db = Connection::open(path)?;
+	db.execute_batch(SCHEMA)?;
+
+	let tx = db.transaction()?;
+	for (index, (handle, _)) in PEOPLE.iter().enumerate() {
+		let rowid = index as i64 + 1;
+		tx.execute(
+			"INSERT INTO handle (ROWID, id, country, service) VALUES (?1, ?2, 'us', 'iMessage')",
+			params![rowid, handle]
+		)?;
+		tx.execute(
+			"INSERT INTO chat (ROWID, guid, style, chat_identifier, service_name)
+			VALUES (?1, ?2, 45, ?3, 'iMessage')",
+			params![rowid, format!("iMessage;-;{}", handle), handle]
+		)?;
+		tx.execute("INSERT INTO chat_handle_join VALUES (?1, ?1)", params![rowid])?;
+	}
+	for (index, (name, members, _)) in GROUPS.iter().enumerate() {
+		let rowid = (PEOPLE.len() + index) as i64 + 1;
+		let identifier = format!("chat{}", 1000 + index);
+		tx.execute(
+			"INSERT INTO chat (ROWID, guid, style, chat_identifier, service_name, display_name)
+			VALUES (?1, ?2, 43, ?3, 'iMessage', ?4)",
+			params![rowid, format!("iMessage;+;{}", identifier), identifier, name]
+		)?;
+		for &member in members.iter() {
+			tx.execute(
+				"INSERT INTO chat_handle_join VALUES (?1, ?2)",
+				params![rowid, member as i64 + 1]
+			)?;
+		}
+	}
+
+	let mut rng = StdRng::seed_from_u64(SEED);
+	let mut next_rowid = 1i64;
+	for day in 0..DAYS {
+		let day_start = YEAR_START_UNIX + day * 86_400;
+		let direct = PEOPLE
+			.iter()
+			.enumerate()
+			.map(|(index, (_, chance))| (index as i64 + 1, vec![index as i64 + 1], *chance));
+		let groups = GROUPS.iter().enumerate().map(|(index, (_, members, chance))| {
+			let handles = members.iter().map(|&member| member as i64 + 1).collect();
+			((PEOPLE.len() + index) as i64 + 1, handles, *chance)
+		});
+
+		let chats: Vec<(i64, Vec<i64>, f64)> = direct.chain(groups).collect();
+
+		for (chat_id, handles, chance) in chats {
+			if !rng.gen_bool(chance) {
+				continue;
+			}
+			// Mostly afternoons and evenings, local time doesn't matter for a demo
+			let mut time = day_start + rng.gen_range(8 * 3600..23 * 3600);
+			let mut last_guid: Option<String> = None;
+			for _ in 0..rng.gen_range(2..14) {
+				let from_me = rng.gen_bool(0.5);
+				let handle = if from_me && handles.len() > 1 {
+					0
+				} else {
+					handles[rng.gen_range(0..handles.len())]
+				};
+				let guid = format!("DEMO-{:08}", next_rowid);
+
+				let tapback = last_guid.is_some() && rng.gen_bool(0.08);
+				let (text, associated_guid, associated_type) = match &last_guid {
+					Some(target) if tapback => {
+						(None, Some(format!("p:0/{}", target)), rng.gen_range(2000..2006))
+					}
+					_ => (Some(PHRASES[rng.gen_range(0..PHRASES.len())]), None, 0)
+				};
+				let apple_nanos = (time - APPLE_EPOCH_OFFSET) * 1_000_000_000;
+
+				tx.execute(
+					"INSERT INTO message (
+						ROWID, guid, text, handle_id, service, account, date, date_read,
+						date_delivered, is_from_me, is_read, is_sent, is_delivered, is_finished,
+						destination_caller_id, associated_message_guid, associated_message_type
+					) VALUES (?1, ?2, ?3, ?4, 'iMessage', ?5, ?6, ?6, ?6, ?7, 1, ?7, 1, 1, ?8, ?9, ?10)",
+					params![
+						next_rowid,
+						guid,
+						text,
+						if from_me { handles[0] } else { handle },
+						format!("e:{}", MY_HANDLE),
+						apple_nanos,
+						from_me,
+						MY_HANDLE,
+						associated_guid,
+						associated_type
+					]
+				)?;
+				tx.execute(
+					"INSERT INTO chat_message_join VALUES (?1, ?2, ?3)",
+					params![chat_id, next_rowid, apple_nanos]
+				)?;
+
+				if !tapback {
+					last_guid = Some(guid);
+				}
+				next_rowid += 1;
+				time += rng.gen_range(15..1200);
+			}
+		}
+	}
+	tx.commit()?;
+
+	Ok(())
+}
@@ -0,0 +1,95 @@
+//! Format of encrypted share payloads.
+//!
+//! Version 2 is the `MWE` magic, a version byte, a random 12-byte nonce and
+//! the AES-256-GCM ciphertext. Version 1 payloads, uploaded before the
+//! envelope existed, are bare ciphertext under an all-zero nonce; they are
+//! still opened so old share links keep decoding.
+
+use std::io;
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use rand::Rng;
+
+use crate::AnalyzerResult;
+
+pub const VERSION: u8 = 2;
+const MAGIC: &[u8; 3] = b"MWE";
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + NONCE_LEN;
+
+/// Encrypts `plaintext` with a fresh random nonce into a version 2 envelope.
+pub fn seal(key: &[u8], plaintext: &[u8]) -> AnalyzerResult<Vec<u8>> {
+	let nonce_bytes = rand::thread_rng().gen::<[u8; NONCE_LEN]>();
+	let encrypted = cipher(key)?
+		.encrypt(Nonce::from_slice(&nonce_bytes), plaintext)
+		.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+
+	let mut envelope = Vec::with_capacity(HEADER_LEN + encrypted.len());
+	envelope.extend_from_slice(MAGIC);
+	envelope.push(VERSION);
+	envelope.extend_from_slice(&nonce_bytes);
+	envelope.extend(encrypted);
+	Ok(envelope)
+}
+
+/// Decrypts a version 2 envelope or a legacy version 1 payload.
+pub fn open(key: &[u8], envelope: &[u8]) -> AnalyzerResult<Vec<u8>> {
+	let cipher = cipher(key)?;
+
+	let is_versioned = envelope.len() >= HEADER_LEN
+		&& envelope.starts_with(MAGIC)
+		&& envelope[MAGIC.len()] == VERSION;
+	if is_versioned {
+		let (nonce_bytes, encrypted) = envelope[MAGIC.len() + 1..].split_at(NONCE_LEN);
+		if let Ok(plaintext) = cipher.decrypt(Nonce::from_slice(nonce_bytes), encrypted) {
+			return Ok(plaintext);
+		}
+		// A legacy ciphertext can start with the magic by chance, fall through
+	}
+
+	cipher
+		.decrypt(Nonce::from_slice(&[0u8; NONCE_LEN]), envelope)
+		.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()).into())
+}
+
+/// Version and nonce of a versioned envelope, `None` for legacy payloads.
+pub fn header(envelope: &[u8]) -> Option<(u8, &[u8])> {
+	if envelope.len() < HEADER_LEN || !envelope.starts_with(MAGIC) {
+		return None;
+	}
+	Some((envelope[MAGIC.len()], &envelope[MAGIC.len() + 1..HEADER_LEN]))
+}
+
+fn cipher(key: &[u8]) -> AnalyzerResult<Aes256Gcm> {
+	Ok(Aes256Gcm::new_from_slice(key).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const KEY: [u8; 32] = [7; 32];
+
+	#[test]
+	fn seal_and_open_round_trip() {
+		let sealed = seal(&KEY, b"stats").unwrap();
+		assert_eq!(header(&sealed).map(|(version, _)| version), Some(VERSION));
+		assert_eq!(open(&KEY, &sealed).unwrap(), b"stats");
+	}
+
+	#[test]
+	fn opens_legacy_payloads() {
+		let legacy = cipher(&KEY)
+			.unwrap()
+			.encrypt(Nonce::from_slice(&[0u8; NONCE_LEN]), &b"stats"[..])
+			.unwrap();
+		assert_eq!(open(&KEY, &legacy).unwrap(), b"stats");
+	}
+
+	#[test]
+	fn rejects_the_wrong_key() {
+		let sealed = seal(&KEY, b"stats").unwrap();
+		assert!(open(&[8; 32], &sealed).is_err());
+	}
+}
@@ -0,0 +1,120 @@
+use std::collections::HashSet;
+
+use imessage_database::tables::messages::Message;
+
+use crate::chats::Chats;
+use crate::handles::Handles;
+use crate::options::FetchOptions;
+
+/// Drops messages the user asked to leave out of their wrapped: whole chats
+/// from `excluded_chats` (GUID or chat identifier), and for every handle in
+/// `excluded_handles` our one-on-one chat plus their messages in groups.
+/// Returns how many messages were dropped.
+pub fn apply(
+	messages: &mut Vec<Message>, handles: &Handles, chats: &Chats, options: &FetchOptions
+) -> usize {
+	let excluded_handles: HashSet<String> =
+		options.excluded_handles.iter().flatten().map(|h| h.to_lowercase()).collect();
+	let excluded_chats: HashSet<&str> =
+		options.excluded_chats.iter().flatten().map(String::as_str).collect();
+	if excluded_handles.is_empty() && excluded_chats.is_empty() {
+		return 0;
+	}
+
+	let is_excluded_handle = |rowid: i32| {
+		handles.get(rowid).is_some_and(|id| excluded_handles.contains(&id.to_lowercase()))
+	};
+	remove(messages, chats, &excluded_chats, is_excluded_handle)
+}
+
+/// Drops the messages of the chats in `excluded_chats`, and for every handle
+/// ROWID `is_excluded_handle` accepts our one-on-one chat plus their messages
+/// in groups.
+fn remove(
+	messages: &mut Vec<Message>, chats: &Chats, excluded_chats: &HashSet<&str>,
+	is_excluded_handle: impl Fn(i32) -> bool
+) -> usize {
+	let chat_ids: HashSet<i32> = chats
+		.iter()
+		.filter(|(_, chat)| {
+			excluded_chats.contains(chat.guid.as_str()) ||
+				excluded_chats.contains(chat.chat_identifier.as_str()) ||
+				(!chat.is_group() && chat.members.iter().any(|&member| is_excluded_handle(member)))
+		})
+		.map(|(&id, _)| id)
+		.collect();
+
+	let before = messages.len();
+	messages.retain(|message| {
+		let in_chat = message.chat_id.is_some_and(|id| chat_ids.contains(&id));
+		let from_handle = !message.is_from_me && message.handle_id.is_some_and(&is_excluded_handle);
+		!in_chat && !from_handle
+	});
+	before - messages.len()
+}
+
+#[cfg(test)]
+mod tests {
+	use imessage_database::tables::table::Table;
+	use rusqlite::Connection;
+
+	use super::*;
+
+	const EXCLUDED_HANDLE: i32 = 2;
+	const FRIEND: i32 = 3;
+
+	/// One-on-one chats with the excluded handle (1) and a friend (3), a group
+	/// with both (2), and chats excluded by identifier (4) and by GUID (5).
+	fn chats() -> Chats {
+		let db = Connection::open_in_memory().unwrap();
+		db.execute_batch(
+			"CREATE TABLE chat (ROWID INTEGER PRIMARY KEY, guid TEXT, chat_identifier TEXT, \
+			 display_name TEXT);
+			 CREATE TABLE chat_handle_join (chat_id INTEGER, handle_id INTEGER);
+			 INSERT INTO chat VALUES
+				 (1, 'iMessage;-;+15550001', '+15550001', NULL),
+				 (2, 'iMessage;+;chat200', 'chat200', 'Group'),
+				 (3, 'iMessage;-;+15550002', '+15550002', NULL),
+				 (4, 'SMS;-;work', 'work', NULL),
+				 (5, 'iMessage;+;chat500', 'chat500', NULL);
+			 INSERT INTO chat_handle_join VALUES (1, 2), (2, 2), (2, 3), (3, 3), (4, 3), (5, 3);"
+		)
+		.unwrap();
+		Chats::new(&db).unwrap()
+	}
+
+	/// `Message` has no constructor, so it is read from a one-row query. A
+	/// handle of 0 is a message from me.
+	fn message(guid: &str, chat_id: i32, handle_id: i32) -> Message {
+		let db = Connection::open_in_memory().unwrap();
+		db.query_row(
+			"SELECT 1 AS rowid, ?1 AS guid, ?2 AS chat_id, ?3 AS handle_id, ?4 AS is_from_me, \
+			 0 AS date, 1 AS is_read, 0 AS num_attachments, 0 AS num_replies",
+			rusqlite::params![guid, chat_id, handle_id, handle_id == 0],
+			Message::from_row
+		)
+		.unwrap()
+	}
+
+	#[test]
+	fn drops_excluded_chats_and_handles() {
+		let mut messages = vec![
+			message("their one-on-one", 1, EXCLUDED_HANDLE),
+			message("my one-on-one", 1, 0),
+			message("theirs in the group", 2, EXCLUDED_HANDLE),
+			message("friend in the group", 2, FRIEND),
+			message("mine in the group", 2, 0),
+			message("friend one-on-one", 3, FRIEND),
+			message("by identifier", 4, FRIEND),
+			message("by guid", 5, 0)
+		];
+		let excluded_chats = HashSet::from(["work", "iMessage;+;chat500"]);
+
+		let removed = remove(&mut messages, &chats(), &excluded_chats, |rowid| {
+			rowid == EXCLUDED_HANDLE
+		});
+		assert_eq!(removed, 5);
+		let kept: Vec<&str> = messages.iter().map(|message| message.guid.as_str()).collect();
+		assert_eq!(kept, ["friend in the group", "mine in the group", "friend one-on-one"]);
+	}
+}
@@ -0,0 +1,146 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use imessage_database::tables::messages::Message;
+use rand::Rng;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::chats::Chats;
+use crate::contacts::Contacts;
+use crate::handles::Handles;
+use crate::AnalyzerResult;
+
+/// Group chats bigger than this are left out, they would connect everyone
+const MAX_GROUP_SIZE: usize = 50;
+const ME: &str = "me";
+
+#[derive(Debug, Serialize)]
+pub struct Node {
+	pub id: String,
+	pub label: String
+}
+
+#[derive(Debug, Serialize)]
+pub struct Edge {
+	pub source: String,
+	pub target: String,
+	/// "group" for shared group chats, "direct" for one-on-one messages with me
+	pub kind: &'static str,
+	pub weight: i32
+}
+
+#[derive(Debug, Serialize)]
+pub struct SocialGraph {
+	pub nodes: Vec<Node>,
+	pub edges: Vec<Edge>
+}
+
+impl SocialGraph {
+	/// Builds the graph of me and my contacts. Contacts are linked by the
+	/// number of group chats they share, and linked to me by the number of
+	/// one-on-one messages we exchanged. With `pseudonymize`, ids and labels
+	/// are salted hashes that differ on every export.
+	pub fn new(
+		messages: &[Message], contacts: &Contacts, handles: &Handles, chats: &Chats,
+		pseudonymize: bool
+	) -> Self {
+		let salt: [u8; 16] = rand::thread_rng().gen();
+		let node_id = |handle: i32| {
+			let handle_id = handles.get(handle).cloned().unwrap_or_default();
+			if pseudonymize {
+				let digest = Sha256::new().chain_update(salt).chain_update(&handle_id).finalize();
+				let id = format!("person-{}", hex::encode(&digest[..4]));
+				(id.clone(), id)
+			} else {
+				let label = contacts.get_name(&handle_id).unwrap_or_else(|| handle_id.clone());
+				(handle_id, label)
+			}
+		};
+
+		let mut direct_counts: HashMap<i32, i32> = HashMap::new();
+		for message in messages {
+			let Some(chat) = message.chat_id.and_then(|id| chats.get(id)) else { continue };
+			if let [member] = chat.members.as_slice() {
+				*direct_counts.entry(*member).or_default() += 1;
+			}
+		}
+
+		let mut group_counts: BTreeMap<(i32, i32), i32> = BTreeMap::new();
+		for (_, chat) in chats.iter() {
+			if !chat.is_group() || chat.members.len() > MAX_GROUP_SIZE {
+				continue;
+			}
+			for (i, &a) in chat.members.iter().enumerate() {
+				for &b in &chat.members[i + 1..] {
+					*group_counts.entry((a.min(b), a.max(b))).or_default() += 1;
+				}
+			}
+		}
+
+		let mut ids: BTreeMap<i32, (String, String)> = BTreeMap::new();
+		let mut id_of = |handle: i32| ids.entry(handle).or_insert_with(|| node_id(handle)).0.clone();
+
+		let mut edges = Vec::new();
+		for (&handle, &weight) in &direct_counts {
+			edges.push(Edge { source: ME.to_string(), target: id_of(handle), kind: "direct", weight });
+		}
+		for (&(a, b), &weight) in &group_counts {
+			edges.push(Edge { source: id_of(a), target: id_of(b), kind: "group", weight });
+		}
+		edges.sort_by(|a, b| (&a.source, &a.target).cmp(&(&b.source, &b.target)));
+
+		let mut nodes = vec![Node { id: ME.to_string(), label: String::from("Me") }];
+		nodes.extend(ids.into_values().map(|(id, label)| Node { id, label }));
+
+		Self { nodes, edges }
+	}
+
+	pub fn write(&self, path: &Path, format: &str) -> AnalyzerResult<()> {
+		let contents = match format {
+			"json" => serde_json::to_string_pretty(self)?,
+			_ => self.to_graphml()
+		};
+		fs::write(path, contents)?;
+		Ok(())
+	}
+
+	fn to_graphml(&self) -> String {
+		let mut xml = String::from(
+			"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+			 <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n\
+			 <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n\
+			 <key id=\"kind\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>\n\
+			 <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"int\"/>\n\
+			 <graph id=\"messages\" edgedefault=\"undirected\">\n"
+		);
+		for node in &self.nodes {
+			xml.push_str(&format!(
+				"<node id=\"{}\"><data key=\"label\">{}</data></node>\n",
+				escape(&node.id),
+				escape(&node.label)
+			));
+		}
+		for edge in &self.edges {
+			xml.push_str(&format!(
+				"<edge source=\"{}\" target=\"{}\"><data key=\"kind\">{}</data><data \
+				 key=\"weight\">{}</data></edge>\n",
+				escape(&edge.source),
+				escape(&edge.target),
+				edge.kind,
+				edge.weight
+			));
+		}
+		xml.push_str("</graph>\n</graphml>\n");
+		xml
+	}
+}
+
+fn escape(value: &str) -> String {
+	value
+		.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
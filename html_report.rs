@@ -0,0 +1,257 @@
+//! Renders a `YearsStats` into one self-contained HTML page that opens
+//! offline, for users who want to keep or pass on their wrapped without
+//! uploading it. Headline numbers get cards; every other stat the run filled
+//! is rendered generically from its JSON form in stats.proto order, so new
+//! stats show up without touching this file.
+
+use chrono::Local;
+use serde_json::{Map, Value};
+
+use crate::schema_doc::{self, FieldDoc};
+use crate::stats::stats::{YearStats, YearsStats};
+use crate::AnalyzerResult;
+
+const TEMPLATE: &str = include_str!("html_report.html");
+/// Fields left out of the generic sections: the year and the headline
+/// numbers already have cards, avatars are raw image bytes and the rest is
+/// bookkeeping
+pub const HIDDEN_FIELDS: &[&str] = &[
+	"year",
+	"message_count",
+	"total_characters",
+	"average_per_day",
+	"avatar",
+	"build",
+	"skipped_stats",
+	"not_applicable"
+];
+
+/// The whole page.
+pub fn render(stats: &YearsStats) -> AnalyzerResult<String> {
+	let mut years: Vec<&YearStats> = stats.stats.iter().collect();
+	years.sort_by(|a, b| b.year.cmp(&a.year));
+
+	let title = match (years.last(), years.first()) {
+		(Some(first), Some(last)) if first.year != last.year => {
+			format!("Messages Wrapped {}–{}", first.year, last.year)
+		}
+		(Some(year), _) => format!("Messages Wrapped {}", year.year),
+		_ => String::from("Messages Wrapped")
+	};
+	let nav: String = years
+		.iter()
+		.map(|year| format!("<a href=\"#year-{0}\">{0}</a>", year.year))
+		.collect();
+	let schema = schema_doc::describe();
+	let fields: &[FieldDoc] = schema
+		.messages
+		.iter()
+		.find(|message| message.name == "YearStats")
+		.map_or(&[][..], |message| message.fields.as_slice());
+	let mut body = String::new();
+	for year in &years {
+		body.push_str(&render_year(year, fields)?);
+	}
+	if years.is_empty() {
+		body.push_str("<p class=\"muted\">No messages were found for any year.</p>");
+	}
+
+	Ok(TEMPLATE
+		.replace("{{title}}", &escape(&title))
+		.replace("{{generated}}", &Local::now().format("%B %-d, %Y").to_string())
+		.replace("{{version}}", env!("CARGO_PKG_VERSION"))
+		.replace("{{nav}}", &nav)
+		.replace("{{body}}", &body))
+}
+
+fn render_year(year: &YearStats, fields: &[FieldDoc]) -> AnalyzerResult<String> {
+	let mut html = format!("<section class=\"year\" id=\"year-{0}\">\n<h2>{0}</h2>\n", year.year);
+
+	html.push_str("<div class=\"cards\">\n");
+	if let Some(count) = &year.message_count {
+		html.push_str(&card("Messages sent", &number(count.sent.into()), "sent"));
+		html.push_str(&card("Messages received", &number(count.received.into()), "received"));
+	}
+	if let Some(average) = &year.average_per_day {
+		html.push_str(&card("Sent per day", &number(average.sent.into()), "sent"));
+		html.push_str(&card("Received per day", &number(average.received.into()), "received"));
+	}
+	if let Some(characters) = &year.total_characters {
+		html.push_str(&card("Characters typed", &number(characters.sent.into()), "sent"));
+	}
+	if let Some(most_sent) = year.most_sent.as_ref().filter(|item| !item.key.is_empty()) {
+		html.push_str(&card("Most sent", &escape(&most_sent.key), ""));
+	}
+	html.push_str("</div>\n");
+
+	let Value::Object(values) = serde_json::to_value(year)? else {
+		return Ok(html + "</section>\n");
+	};
+	for field in fields.iter().filter(|field| !HIDDEN_FIELDS.contains(&field.name.as_str())) {
+		let Some(value) = values.get(&field.name).filter(|value| !is_empty(value)) else {
+			continue;
+		};
+		let description = field
+			.description
+			.as_ref()
+			.map(|description| format!("<p class=\"muted\">{}</p>", escape(description)))
+			.unwrap_or_default();
+		html.push_str(&format!(
+			"<details>\n<summary>{}</summary>\n<div>{}{}</div>\n</details>\n",
+			label(&field.name),
+			description,
+			render_value(&field.name, value)
+		));
+	}
+
+	html.push_str("</section>\n");
+	Ok(html)
+}
+
+fn card(label: &str, value: &str, class: &str) -> String {
+	format!(
+		"<div class=\"card\"><div class=\"value {}\">{}</div><div class=\"label\">{}</div></div>\n",
+		class, value, label
+	)
+}
+
+/// Objects become definition lists, lists of objects tables and lists of
+/// plain values comma-separated text.
+fn render_value(key: &str, value: &Value) -> String {
+	match value {
+		Value::Object(fields) => render_object(fields),
+		Value::Array(items) if items.iter().all(Value::is_object) => render_table(items),
+		Value::Array(items) => {
+			items.iter().map(|item| render_value(key, item)).collect::<Vec<_>>().join(", ")
+		}
+		_ => scalar(key, value)
+	}
+}
+
+fn render_object(fields: &Map<String, Value>) -> String {
+	let mut html = String::from("<dl>");
+	for (key, value) in fields {
+		if HIDDEN_FIELDS.contains(&key.as_str()) || is_empty(value) {
+			continue;
+		}
+		html.push_str(&format!("<dt>{}</dt><dd>{}</dd>", label(key), render_value(key, value)));
+	}
+	html.push_str("</dl>");
+	html
+}
+
+/// One row per object, one column per field any of them has.
+fn render_table(items: &[Value]) -> String {
+	let mut columns: Vec<&str> = Vec::new();
+	for fields in items.iter().filter_map(Value::as_object) {
+		for key in fields.keys() {
+			if !HIDDEN_FIELDS.contains(&key.as_str()) && !columns.contains(&key.as_str()) {
+				columns.push(key);
+			}
+		}
+	}
+
+	let mut html = String::from("<table><thead><tr>");
+	for column in &columns {
+		html.push_str(&format!("<th>{}</th>", label(column)));
+	}
+	html.push_str("</tr></thead><tbody>");
+	for fields in items.iter().filter_map(Value::as_object) {
+		html.push_str("<tr>");
+		for column in &columns {
+			let cell = fields.get(*column).map(|value| render_value(column, value));
+			html.push_str(&format!("<td>{}</td>", cell.unwrap_or_default()));
+		}
+		html.push_str("</tr>");
+	}
+	html.push_str("</tbody></table>");
+	html
+}
+
+fn scalar(key: &str, value: &Value) -> String {
+	match value {
+		Value::String(s) => escape(s),
+		_ => plain_scalar(key, value)
+	}
+}
+
+/// A number, flag or string as text, numbers formatted by the unit their
+/// field name ends in.
+pub fn plain_scalar(key: &str, value: &Value) -> String {
+	match value {
+		Value::Number(n) if key.ends_with("_seconds") => {
+			duration(n.as_f64().unwrap_or_default() as i64)
+		}
+		Value::Number(n) if key.ends_with("_share") || key.ends_with("_rate") => {
+			format!("{:.0}%", n.as_f64().unwrap_or_default() * 100.0)
+		}
+		Value::Number(n) => number(n.as_f64().unwrap_or_default()),
+		Value::Bool(true) => String::from("Yes"),
+		Value::Bool(false) => String::from("No"),
+		Value::String(s) => s.clone(),
+		_ => String::new()
+	}
+}
+
+/// Whole numbers with thousands separators, others to one decimal.
+pub fn number(value: f64) -> String {
+	if value.fract() != 0.0 {
+		return format!("{:.1}", value);
+	}
+	let digits = (value.abs() as u64).to_string();
+	let mut grouped = String::new();
+	for (index, digit) in digits.chars().enumerate() {
+		if index > 0 && (digits.len() - index) % 3 == 0 {
+			grouped.push(',');
+		}
+		grouped.push(digit);
+	}
+	if value < 0.0 { format!("-{}", grouped) } else { grouped }
+}
+
+pub fn duration(seconds: i64) -> String {
+	match seconds {
+		s if s < 60 => format!("{}s", s),
+		s if s < 3600 => format!("{}m", s / 60),
+		s if s < 86_400 => format!("{}h {}m", s / 3600, s % 3600 / 60),
+		s => format!("{}d {}h", s / 86_400, s % 86_400 / 3600)
+	}
+}
+
+fn label(key: &str) -> String {
+	escape(&humanize(key))
+}
+
+/// `top_individual_chats` as "Top individual chats".
+pub fn humanize(key: &str) -> String {
+	let words = key.replace('_', " ");
+	let mut chars = words.chars();
+	match chars.next() {
+		Some(first) => first.to_uppercase().chain(chars).collect(),
+		None => String::new()
+	}
+}
+
+pub fn is_empty(value: &Value) -> bool {
+	match value {
+		Value::Null => true,
+		Value::Array(items) => items.is_empty(),
+		Value::Object(fields) => fields.is_empty(),
+		_ => false
+	}
+}
+
+fn escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			'\'' => escaped.push_str("&#39;"),
+			_ => escaped.push(c)
+		}
+	}
+	escaped
+}
@@ -0,0 +1,180 @@
+//! Looks for signs that most of the history lives in Messages in iCloud and
+//! only part of it was ever downloaded to this Mac: chats without a single
+//! local message, reactions and replies to messages that aren't there, and
+//! attachments whose files were never fetched. The wrapped is computed from
+//! what is local, so the app warns that it may be incomplete.
+
+use std::path::PathBuf;
+
+use chrono::Utc;
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::insights::APPLE_EPOCH_OFFSET;
+use crate::schema::Schema;
+use crate::warnings::Warning;
+use crate::{paths, readonly, AnalyzerResult};
+
+/// Share of chats without local messages above which history looks missing
+const EMPTY_CHATS_THRESHOLD: f64 = 0.2;
+/// Share of reactions and replies to missing messages above which history
+/// looks cut off
+const ORPHANS_THRESHOLD: f64 = 0.05;
+/// Share of recent attachments missing on disk above which they look
+/// offloaded to iCloud
+const MISSING_ATTACHMENTS_THRESHOLD: f64 = 0.5;
+/// Recent attachments whose files are looked for
+const CHECKED_ATTACHMENTS: u32 = 2000;
+/// A history younger than this with heavy daily traffic looks truncated
+/// rather than like a new account
+const SHORT_HISTORY_DAYS: i64 = 365;
+const BUSY_MESSAGES_PER_DAY: f64 = 20.0;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnosis {
+	/// History looks partly left in iCloud
+	pub likely_incomplete: bool,
+	/// Why, any of "empty_chats", "orphaned_references", "attachments_not_downloaded"
+	/// and "short_history"
+	pub reasons: Vec<&'static str>,
+	/// Messages carry iCloud sync state, so Messages in iCloud is on
+	pub icloud_sync: bool,
+	pub messages: i64,
+	/// Unix milliseconds of the oldest local message
+	pub first_message: Option<i64>,
+	pub history_days: i64,
+	pub chats: i64,
+	pub empty_chats: i64,
+	/// Reactions and replies, and how many point at messages that aren't local
+	pub references: i64,
+	pub orphaned_references: i64,
+	pub checked_attachments: i64,
+	pub missing_attachments: i64,
+	pub warning: Option<Warning>
+}
+
+pub fn diagnose(db: &Connection, schema: &Schema) -> AnalyzerResult<Diagnosis> {
+	let icloud_sync = schema.has_column("message", "ck_sync_state") &&
+		scalar(db, "SELECT EXISTS (SELECT 1 FROM message WHERE ck_sync_state != 0)")? == 1;
+	let messages = scalar(db, "SELECT COUNT(*) FROM message")?;
+	let first_message = readonly::prepare(
+		db,
+		"SELECT MIN(CASE WHEN date > 1000000000000 THEN date / 1000000000 ELSE date END)
+		FROM message WHERE date > 0"
+	)?
+	.query_row([], |row| row.get::<_, Option<i64>>(0))?
+	.map(|apple_seconds| (apple_seconds + APPLE_EPOCH_OFFSET) * 1000);
+	let history_days =
+		first_message.map_or(0, |first| (Utc::now().timestamp_millis() - first) / 86_400_000);
+
+	let chats = scalar(db, "SELECT COUNT(*) FROM chat")?;
+	let empty_chats = scalar(
+		db,
+		"SELECT COUNT(*) FROM chat c
+		WHERE NOT EXISTS (SELECT 1 FROM chat_message_join j WHERE j.chat_id = c.ROWID)"
+	)?;
+	let (references, orphaned_references) = references(db, schema)?;
+	let (checked_attachments, missing_attachments) = missing_attachments(db)?;
+
+	let share = |part: i64, whole: i64| if whole > 0 { part as f64 / whole as f64 } else { 0.0 };
+	let messages_per_day = messages as f64 / history_days.max(1) as f64;
+	let mut reasons = Vec::new();
+	if share(empty_chats, chats) > EMPTY_CHATS_THRESHOLD {
+		reasons.push("empty_chats");
+	}
+	if share(orphaned_references, references) > ORPHANS_THRESHOLD {
+		reasons.push("orphaned_references");
+	}
+	if share(missing_attachments, checked_attachments) > MISSING_ATTACHMENTS_THRESHOLD {
+		reasons.push("attachments_not_downloaded");
+	}
+	if history_days < SHORT_HISTORY_DAYS && messages_per_day >= BUSY_MESSAGES_PER_DAY {
+		reasons.push("short_history");
+	}
+
+	// Without iCloud sync the same signs mean deleted messages, not missing ones
+	let likely_incomplete = icloud_sync && !reasons.is_empty();
+	let warning = likely_incomplete.then(|| {
+		Warning::new(
+			"icloud_history_incomplete",
+			"Part of your history seems to be in iCloud only, so your wrapped may be incomplete. \
+			 Keep Messages open until it finishes downloading, or turn off Optimize Mac Storage"
+		)
+	});
+
+	Ok(Diagnosis {
+		likely_incomplete,
+		reasons,
+		icloud_sync,
+		messages,
+		first_message,
+		history_days,
+		chats,
+		empty_chats,
+		references,
+		orphaned_references,
+		checked_attachments,
+		missing_attachments,
+		warning
+	})
+}
+
+/// Reactions and, where chat.db has them, inline replies, and how many of
+/// them point at a message that isn't in the database. Reactions name their
+/// message as `p:0/GUID` or `bp:GUID`.
+fn references(db: &Connection, schema: &Schema) -> AnalyzerResult<(i64, i64)> {
+	let mut sources = vec![
+		"SELECT CASE WHEN instr(associated_message_guid, '/') > 0
+			THEN substr(associated_message_guid, instr(associated_message_guid, '/') + 1)
+			ELSE substr(associated_message_guid, instr(associated_message_guid, ':') + 1)
+			END AS guid
+		FROM message
+		WHERE associated_message_type BETWEEN 2000 AND 3007 AND associated_message_guid IS NOT NULL"
+	];
+	if schema.has_column("message", "thread_originator_guid") {
+		sources.push(
+			"SELECT thread_originator_guid AS guid FROM message
+			WHERE thread_originator_guid IS NOT NULL AND thread_originator_guid != ''"
+		);
+	}
+
+	let counts = readonly::prepare(
+		db,
+		&format!(
+			"SELECT COUNT(*),
+				COALESCE(SUM(NOT EXISTS (SELECT 1 FROM message m WHERE m.guid = r.guid)), 0)
+			FROM ({}) r",
+			sources.join(" UNION ALL ")
+		)
+	)?
+	.query_row([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+	Ok(counts)
+}
+
+/// How many of the most recent attachments were checked, and how many of
+/// their files aren't on disk.
+fn missing_attachments(db: &Connection) -> AnalyzerResult<(i64, i64)> {
+	let mut statement = readonly::prepare(
+		db,
+		"SELECT filename FROM attachment WHERE filename IS NOT NULL ORDER BY ROWID DESC LIMIT ?1"
+	)?;
+	let filenames = statement
+		.query_map([CHECKED_ATTACHMENTS], |row| row.get::<_, String>(0))?
+		.collect::<Result<Vec<_>, _>>()?;
+
+	// chat.db stores paths under the home folder as ~/Library/Messages/...
+	let missing = filenames
+		.iter()
+		.map(|filename| match filename.strip_prefix("~/") {
+			Some(relative) => paths::home_dir().join(relative),
+			None => PathBuf::from(filename)
+		})
+		.filter(|path| !path.exists())
+		.count();
+	Ok((filenames.len() as i64, missing as i64))
+}
+
+fn scalar(db: &Connection, sql: &str) -> AnalyzerResult<i64> {
+	Ok(readonly::prepare(db, sql)?.query_row([], |row| row.get(0))?)
+}
@@ -0,0 +1,104 @@
+//! Finds the handles that belong to me (phone number and Apple ID emails), so
+//! messages synced from my other addresses aren't counted as received.
+
+use std::collections::{HashMap, HashSet};
+
+use imessage_database::tables::messages::Message;
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::schema::Schema;
+use crate::{readonly, AnalyzerResult};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Identity {
+	pub handle: String,
+	/// Messages I sent from this handle
+	pub messages: i64
+}
+
+/// Handles I have sent messages from, most used first. chat.db records them
+/// on sent messages as `destination_caller_id` and as `account`, the latter
+/// prefixed with `P:` or `E:`. Older databases may only have one of them.
+pub fn detect(db: &Connection, schema: &Schema) -> AnalyzerResult<Vec<Identity>> {
+	let sources: Vec<String> = ["destination_caller_id", "account"]
+		.into_iter()
+		.filter(|column| schema.has_column("message", column))
+		.map(|column| format!("SELECT {} AS id FROM message WHERE is_from_me = 1", column))
+		.collect();
+	if sources.is_empty() {
+		return Ok(Vec::new());
+	}
+
+	let mut statement = readonly::prepare(
+		db,
+		&format!(
+			"SELECT id, COUNT(*) FROM ({})
+			WHERE id IS NOT NULL AND id != ''
+			GROUP BY id",
+			sources.join(" UNION ALL ")
+		)
+	)?;
+	let rows = statement
+		.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?;
+
+	let mut counts: HashMap<String, i64> = HashMap::new();
+	for row in rows {
+		let (id, count) = row?;
+		// Both columns are set on most sent messages, keep the larger count
+		let entry = counts.entry(normalize(&id)).or_default();
+		*entry = (*entry).max(count);
+	}
+
+	let mut identities: Vec<Identity> = counts
+		.into_iter()
+		.filter(|(handle, _)| !handle.is_empty())
+		.map(|(handle, messages)| Identity { handle, messages })
+		.collect();
+	identities.sort_by(|a, b| b.messages.cmp(&a.messages).then_with(|| a.handle.cmp(&b.handle)));
+
+	Ok(identities)
+}
+
+/// ROWIDs in the handle table for any of `identities`.
+pub fn handle_rowids(db: &Connection, identities: &[String]) -> AnalyzerResult<HashSet<i32>> {
+	let wanted: HashSet<String> = identities.iter().map(|id| normalize(id)).collect();
+
+	let mut statement = readonly::prepare(db, "SELECT ROWID, id FROM handle")?;
+	let rows = statement
+		.query_map([], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?)))?;
+
+	let mut rowids = HashSet::new();
+	for row in rows {
+		let (rowid, id) = row?;
+		if wanted.contains(&normalize(&id)) {
+			rowids.insert(rowid);
+		}
+	}
+	Ok(rowids)
+}
+
+/// Marks messages that arrived from one of my own handles as sent by me.
+/// Returns how many were changed.
+pub fn claim_messages(messages: &mut [Message], mine: &HashSet<i32>) -> usize {
+	let mut claimed = 0;
+	for message in messages.iter_mut().filter(|m| !m.is_from_me) {
+		if message.handle_id.is_some_and(|handle| mine.contains(&handle)) {
+			message.is_from_me = true;
+			claimed += 1;
+		}
+	}
+	claimed
+}
+
+fn normalize(id: &str) -> String {
+	let id = id.trim();
+	let id = match id.get(..2) {
+		Some(prefix) if prefix.eq_ignore_ascii_case("p:") || prefix.eq_ignore_ascii_case("e:") => {
+			&id[2..]
+		}
+		_ => id
+	};
+	id.to_lowercase()
+}
@@ -0,0 +1,530 @@
+//! Backups from SMS Backup & Restore on Android (`sms-YYYYMMDD.xml`). Every
+//! `<sms>` element is one text; an `<mms>` element holds its text and media
+//! in `<part>` children and, for group messages, everyone involved in
+//! `<addr>` children. Dates are unix milliseconds. The file is read one tag at
+//! a time since MMS media is inlined as base64 and backups get large.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use rusqlite::{params, Connection, Transaction};
+
+use super::{Import, ADDRESS_BOOK_SCHEMA};
+use crate::insights::APPLE_EPOCH_OFFSET;
+use crate::{demo, AnalyzerResult};
+
+/// `type` of an `<sms>` I sent. Received is 1; drafts, outbox and failed
+/// messages never reached anyone and are skipped
+const SMS_SENT: &str = "2";
+const SMS_RECEIVED: &str = "1";
+/// `msg_box` of an `<mms>`, same meaning as `type` above
+const MMS_SENT: &str = "2";
+const MMS_RECEIVED: &str = "1";
+/// `type` of the `<addr>` that sent an MMS
+const ADDR_FROM: &str = "137";
+/// `address` of an MMS recipient the phone didn't know
+const PLACEHOLDER_ADDRESS: &str = "insert-address-token";
+/// Names the app writes when a number isn't in the phone's contacts
+const UNKNOWN_NAMES: &[&str] = &["(Unknown)", "null", ""];
+
+/// Imports the backup at `xml_path` into `out_dir`, replacing an earlier
+/// import there.
+pub fn import(xml_path: &Path, out_dir: &Path) -> AnalyzerResult<Import> {
+	let chat_db_path = out_dir.join("chat.db");
+	let address_book_path = out_dir.join("AddressBook");
+	let address_book_db = address_book_path.join("Sources/android/AddressBook-v22.abcddb");
+	let _ = fs::remove_file(&chat_db_path);
+	let _ = fs::remove_dir_all(&address_book_path);
+	fs::create_dir_all(address_book_db.parent().unwrap_or(out_dir))?;
+
+	let mut chat_db = Connection::open(&chat_db_path)?;
+	chat_db.execute_batch(demo::SCHEMA)?;
+	let mut writer = Writer::new(chat_db.transaction()?);
+
+	let mut tags = Tags::new(BufReader::new(File::open(xml_path)?));
+	let mut mms: Option<Mms> = None;
+	while let Some(mut tag) = tags.next_tag()? {
+		let name = std::mem::take(&mut tag.name);
+		match (name.as_str(), tag.closing) {
+			("sms", false) => writer.sms(&tag)?,
+			("mms", false) => mms = Some(Mms { tag, parts: Vec::new(), addrs: Vec::new() }),
+			("mms", true) => {
+				if let Some(mms) = mms.take() {
+					writer.mms(&mms)?;
+				}
+			}
+			("part", false) => {
+				if let Some(mms) = &mut mms {
+					mms.parts.push(tag);
+				}
+			}
+			("addr", false) => {
+				if let Some(mms) = &mut mms {
+					mms.addrs.push(tag);
+				}
+			}
+			_ => {}
+		}
+	}
+
+	let (names, skipped) = (writer.names, writer.skipped);
+	let (messages, chats) = (writer.next_message as usize - 1, writer.chats.len());
+	writer.tx.commit()?;
+	let _ = chat_db.close();
+
+	let contacts = write_address_book(&address_book_db, &names)?;
+
+	Ok(Import {
+		chat_db_path,
+		address_book_path,
+		messages,
+		chats,
+		contacts,
+		skipped,
+		decrypted: false
+	})
+}
+
+struct Mms {
+	tag: Tag,
+	parts: Vec<Tag>,
+	addrs: Vec<Tag>
+}
+
+/// Inserts records into the chat.db being built.
+struct Writer<'a> {
+	tx: Transaction<'a>,
+	handles: HashMap<String, i64>,
+	/// Chat ROWIDs keyed by the sorted addresses in them
+	chats: HashMap<BTreeSet<String>, i64>,
+	/// Contact name for each address, as the backup had it
+	names: HashMap<String, String>,
+	/// My own numbers, learned from the sender of MMS I sent
+	mine: BTreeSet<String>,
+	next_message: i64,
+	next_attachment: i64,
+	skipped: usize
+}
+
+impl<'a> Writer<'a> {
+	fn new(tx: Transaction<'a>) -> Self {
+		Self {
+			tx,
+			handles: HashMap::new(),
+			chats: HashMap::new(),
+			names: HashMap::new(),
+			mine: BTreeSet::new(),
+			next_message: 1,
+			next_attachment: 1,
+			skipped: 0
+		}
+	}
+
+	fn sms(&mut self, tag: &Tag) -> AnalyzerResult<()> {
+		let from_me = match tag.get("type") {
+			Some(SMS_SENT) => true,
+			Some(SMS_RECEIVED) => false,
+			_ => {
+				self.skipped += 1;
+				return Ok(());
+			}
+		};
+		let address = tag.get("address").map(normalize).filter(|address| !address.is_empty());
+		let (Some(address), Some(date)) = (address, date(tag)) else {
+			self.skipped += 1;
+			return Ok(());
+		};
+		self.remember_names(&[address.clone()], tag.get("contact_name"));
+
+		let chat_id = self.chat(&[address.clone()])?;
+		let handle_id = self.handle(&address)?;
+		self.message(chat_id, handle_id, from_me, date, tag.get("body"), &[])
+	}
+
+	fn mms(&mut self, mms: &Mms) -> AnalyzerResult<()> {
+		let from_me = match mms.tag.get("msg_box") {
+			Some(MMS_SENT) => true,
+			Some(MMS_RECEIVED) => false,
+			_ => {
+				self.skipped += 1;
+				return Ok(());
+			}
+		};
+		let Some(date) = date(&mms.tag) else {
+			self.skipped += 1;
+			return Ok(());
+		};
+
+		// Older backups have no <addr> children, only `address` joined by ~
+		let mut addresses: Vec<String> = mms
+			.tag
+			.get("address")
+			.map(|joined| joined.split('~').map(normalize).collect())
+			.unwrap_or_default();
+		self.remember_names(&addresses, mms.tag.get("contact_name"));
+		let mut sender = None;
+		for addr in &mms.addrs {
+			let Some(address) = addr.get("address").map(normalize) else { continue };
+			if addr.get("type") == Some(ADDR_FROM) {
+				sender = Some(address.clone());
+			}
+			if !addresses.contains(&address) {
+				addresses.push(address);
+			}
+		}
+		// Placeholders normalize to nothing
+		addresses.retain(|address| !address.is_empty());
+		if addresses.is_empty() {
+			self.skipped += 1;
+			return Ok(());
+		}
+
+		// My own number shows up as the sender of my messages and among the
+		// recipients of others', it's not a member of the chat
+		if let Some(sender) = sender.as_ref().filter(|_| from_me) {
+			self.mine.insert(sender.clone());
+		}
+		if addresses.iter().any(|address| !self.mine.contains(address)) {
+			addresses.retain(|address| !self.mine.contains(address));
+		}
+		let chat_id = self.chat(&addresses)?;
+		let handle_id = match (&sender, from_me) {
+			(Some(sender), false) => self.handle(sender)?,
+			(_, true) if addresses.len() == 1 => self.handle(&addresses[0])?,
+			_ => 0
+		};
+
+		let text: Vec<&str> = mms
+			.parts
+			.iter()
+			.filter(|part| part.get("ct") == Some("text/plain"))
+			.filter_map(|part| part.get("text"))
+			.collect();
+		let media: Vec<&str> = mms
+			.parts
+			.iter()
+			.filter_map(|part| part.get("ct"))
+			.filter(|mime| !matches!(*mime, "text/plain" | "application/smil"))
+			.collect();
+		let text = (!text.is_empty()).then(|| text.join("\n"));
+		self.message(chat_id, handle_id, from_me, date, text.as_deref(), &media)
+	}
+
+	fn message(
+		&mut self, chat_id: i64, handle_id: i64, from_me: bool, date: i64, text: Option<&str>,
+		media: &[&str]
+	) -> AnalyzerResult<()> {
+		let rowid = self.next_message;
+		self.next_message += 1;
+
+		self.tx.execute(
+			"INSERT INTO message (
+				ROWID, guid, text, handle_id, service, date, date_read, date_delivered,
+				is_from_me, is_read, is_sent, is_delivered, is_finished, cache_has_attachments
+			) VALUES (?1, ?2, ?3, ?4, 'SMS', ?5, ?5, ?5, ?6, 1, ?6, 1, 1, ?7)",
+			params![
+				rowid,
+				format!("ANDROID-{:08}", rowid),
+				text,
+				handle_id,
+				date,
+				from_me,
+				!media.is_empty()
+			]
+		)?;
+		self.tx.execute(
+			"INSERT INTO chat_message_join VALUES (?1, ?2, ?3)",
+			params![chat_id, rowid, date]
+		)?;
+
+		for mime in media {
+			let attachment = self.next_attachment;
+			self.next_attachment += 1;
+			self.tx.execute(
+				"INSERT INTO attachment (ROWID, guid, created_date, mime_type, is_outgoing)
+				VALUES (?1, ?2, ?3, ?4, ?5)",
+				params![attachment, format!("ANDROID-A{:08}", attachment), date, mime, from_me]
+			)?;
+			self.tx.execute(
+				"INSERT INTO message_attachment_join VALUES (?1, ?2)",
+				params![rowid, attachment]
+			)?;
+		}
+		Ok(())
+	}
+
+	fn handle(&mut self, address: &str) -> AnalyzerResult<i64> {
+		if let Some(&rowid) = self.handles.get(address) {
+			return Ok(rowid);
+		}
+		let rowid = self.handles.len() as i64 + 1;
+		self.tx.execute(
+			"INSERT INTO handle (ROWID, id, service, uncanonicalized_id)
+			VALUES (?1, ?2, 'SMS', ?2)",
+			params![rowid, address]
+		)?;
+		self.handles.insert(address.to_string(), rowid);
+		Ok(rowid)
+	}
+
+	fn chat(&mut self, addresses: &[String]) -> AnalyzerResult<i64> {
+		let key: BTreeSet<String> = addresses.iter().cloned().collect();
+		if let Some(&rowid) = self.chats.get(&key) {
+			return Ok(rowid);
+		}
+
+		let rowid = self.chats.len() as i64 + 1;
+		let (guid, identifier, style) = match addresses {
+			[address] => (format!("SMS;-;{}", address), address.clone(), 45),
+			_ => {
+				let identifier = format!("chat{}", 1000 + rowid);
+				(format!("SMS;+;{}", identifier), identifier, 43)
+			}
+		};
+		self.tx.execute(
+			"INSERT INTO chat (ROWID, guid, style, chat_identifier, service_name)
+			VALUES (?1, ?2, ?3, ?4, 'SMS')",
+			params![rowid, guid, style, identifier]
+		)?;
+		for address in &key {
+			let handle = self.handle(address)?;
+			self.tx.execute(
+				"INSERT INTO chat_handle_join VALUES (?1, ?2)",
+				params![rowid, handle]
+			)?;
+		}
+		self.chats.insert(key, rowid);
+		Ok(rowid)
+	}
+
+	/// `contact_name` lists names in the order of the addresses, separated by
+	/// commas for group messages. Names are only taken when the counts match.
+	fn remember_names(&mut self, addresses: &[String], contact_name: Option<&str>) {
+		let Some(contact_name) = contact_name else { return };
+		let names: Vec<&str> = contact_name.split(", ").collect();
+		if names.len() != addresses.len() {
+			return;
+		}
+		for (address, name) in addresses.iter().zip(names) {
+			if !UNKNOWN_NAMES.contains(&name) {
+				self.names.entry(address.clone()).or_insert_with(|| name.to_string());
+			}
+		}
+	}
+}
+
+/// Writes one AddressBook record per distinct name. Returns how many.
+fn write_address_book(path: &Path, names: &HashMap<String, String>) -> AnalyzerResult<usize> {
+	let mut db = Connection::open(path)?;
+	db.execute_batch(ADDRESS_BOOK_SCHEMA)?;
+	db.execute("INSERT INTO ZABCDCONTAINER (ZNAME) VALUES ('Android')", [])?;
+
+	let mut records: HashMap<&str, i64> = HashMap::new();
+	let tx = db.transaction()?;
+	for (address, name) in names {
+		let next = records.len() as i64 + 1;
+		let record = *records.entry(name.as_str()).or_insert(next);
+		if record == next {
+			let (first, last) = name.split_once(' ').unwrap_or((name.as_str(), ""));
+			tx.execute(
+				"INSERT INTO ZABCDRECORD (Z_PK, ZFIRSTNAME, ZLASTNAME) VALUES (?1, ?2, ?3)",
+				params![record, first, (!last.is_empty()).then_some(last)]
+			)?;
+		}
+		let table = if address.contains('@') {
+			"INSERT INTO ZABCDEMAILADDRESS (ZOWNER, ZADDRESS) VALUES (?1, ?2)"
+		} else {
+			"INSERT INTO ZABCDPHONENUMBER (ZOWNER, ZFULLNUMBER) VALUES (?1, ?2)"
+		};
+		tx.execute(table, params![record, address])?;
+	}
+	tx.commit()?;
+	let _ = db.close();
+
+	Ok(records.len())
+}
+
+/// chat.db date (Apple epoch nanoseconds) of a record's `date`.
+fn date(tag: &Tag) -> Option<i64> {
+	let millis: i64 = tag.get("date")?.parse().ok()?;
+	let seconds = millis / 1000 - APPLE_EPOCH_OFFSET;
+	(millis > 0).then(|| seconds * 1_000_000_000 + millis % 1000 * 1_000_000)
+}
+
+/// Drops the formatting phones add to numbers, so "(555) 123-4567" and
+/// "555-123-4567" are one person. Email addresses and alphanumeric senders
+/// like "AMAZON" are kept, lowercased; the placeholder MMS uses for unknown
+/// addresses becomes empty.
+fn normalize(address: &str) -> String {
+	let address = address.trim();
+	if address == PLACEHOLDER_ADDRESS {
+		return String::new();
+	}
+	if address.contains('@') || address.chars().any(char::is_alphabetic) {
+		return address.to_lowercase();
+	}
+	address.chars().filter(|c| c.is_ascii_digit() || *c == '+').collect()
+}
+
+/// A start or end tag with its attributes.
+struct Tag {
+	name: String,
+	closing: bool,
+	attributes: Vec<(String, String)>
+}
+
+impl Tag {
+	fn get(&self, name: &str) -> Option<&str> {
+		self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+	}
+}
+
+/// Minimal pull reader for the backup format: elements with double-quoted
+/// attributes and no text content worth keeping. The `data` attribute of MMS
+/// parts (base64 media) is skipped rather than kept.
+struct Tags<R> {
+	reader: R,
+	buffer: Vec<u8>
+}
+
+impl<R: BufRead> Tags<R> {
+	fn new(reader: R) -> Self {
+		Self { reader, buffer: Vec::new() }
+	}
+
+	fn next_tag(&mut self) -> io::Result<Option<Tag>> {
+		loop {
+			self.buffer.clear();
+			if self.reader.read_until(b'<', &mut self.buffer)? == 0 {
+				return Ok(None);
+			}
+			self.buffer.clear();
+			// '>' may appear inside an attribute value, keep reading until the
+			// quotes are balanced
+			loop {
+				if self.reader.read_until(b'>', &mut self.buffer)? == 0 {
+					return Ok(None);
+				}
+				if self.buffer.iter().filter(|&&b| b == b'"').count() % 2 == 0 {
+					break;
+				}
+			}
+			if matches!(self.buffer.first(), Some(b'?' | b'!')) {
+				continue;
+			}
+			return Ok(Some(parse_tag(&String::from_utf8_lossy(&self.buffer))));
+		}
+	}
+}
+
+fn parse_tag(raw: &str) -> Tag {
+	let raw = raw.trim_end_matches('>').trim_end_matches('/');
+	let (closing, raw) = match raw.strip_prefix('/') {
+		Some(rest) => (true, rest),
+		None => (false, raw)
+	};
+	let name_end = raw.find(char::is_whitespace).unwrap_or(raw.len());
+	let mut attributes = Vec::new();
+
+	let mut rest = &raw[name_end..];
+	while let Some((key, after)) = rest.split_once("=\"") {
+		let Some((value, after)) = after.split_once('"') else { break };
+		let key = key.trim();
+		if key != "data" {
+			attributes.push((key.to_string(), unescape(value)));
+		}
+		rest = after;
+	}
+
+	Tag { name: raw[..name_end].to_string(), closing, attributes }
+}
+
+/// Resolves XML entities. Numeric ones are UTF-16 code units: the app writes
+/// emoji as surrogate pairs like `&#55357;&#56832;`, which only make a
+/// character together.
+fn unescape(value: &str) -> String {
+	let mut out = String::with_capacity(value.len());
+	let mut units: Vec<u16> = Vec::new();
+	let mut rest = value;
+
+	while let Some(start) = rest.find('&') {
+		let Some(end) = rest[start..].find(';').map(|end| start + end) else { break };
+		let entity = &rest[start + 1..end];
+		let unit = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+			Some(hex) => u32::from_str_radix(hex, 16).ok(),
+			None => entity.strip_prefix('#').and_then(|decimal| decimal.parse().ok())
+		};
+
+		let code_unit = unit.filter(|&unit| unit <= 0xFFFF);
+		if start > 0 || code_unit.is_none() {
+			out.push_str(&String::from_utf16_lossy(&units));
+			units.clear();
+		}
+		out.push_str(&rest[..start]);
+		match (code_unit, unit) {
+			(Some(code_unit), _) => units.push(code_unit as u16),
+			(None, Some(unit)) => out.extend(char::from_u32(unit)),
+			(None, None) => match entity {
+				"amp" => out.push('&'),
+				"lt" => out.push('<'),
+				"gt" => out.push('>'),
+				"quot" => out.push('"'),
+				"apos" => out.push('\''),
+				_ => out.push_str(&rest[start..=end])
+			}
+		}
+		rest = &rest[end + 1..];
+	}
+	out.push_str(&String::from_utf16_lossy(&units));
+	out.push_str(rest);
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn unescapes_surrogate_pairs() {
+		assert_eq!(unescape("hi &#55357;&#56832;!"), "hi 😀!");
+		assert_eq!(unescape("&#xD83D;&#xDE00;&#55357;&#56832;"), "😀😀");
+	}
+
+	#[test]
+	fn unescapes_named_and_unknown_entities() {
+		assert_eq!(unescape("a &lt;3 &amp; &quot;b&quot; &#233;"), "a <3 & \"b\" é");
+		assert_eq!(unescape("&nbsp; & 5"), "&nbsp; & 5");
+	}
+
+	#[test]
+	fn parses_attributes_containing_angle_brackets() {
+		let tag = parse_tag(r#"sms address="555" body="2 > 1 &gt; 0" type="1" />"#);
+		assert_eq!(tag.name, "sms");
+		assert!(!tag.closing);
+		assert_eq!(tag.get("body"), Some("2 > 1 > 0"));
+		assert_eq!(tag.get("type"), Some("1"));
+	}
+
+	#[test]
+	fn reads_tags_past_angle_brackets_in_values() {
+		let xml = r#"<?xml version="1.0"?><smses><sms body="a > b" address="555" /></smses>"#;
+		let mut tags = Tags::new(xml.as_bytes());
+		assert_eq!(tags.next_tag().unwrap().map(|tag| tag.name), Some("smses".to_string()));
+		let sms = tags.next_tag().unwrap().unwrap();
+		assert_eq!(sms.get("body"), Some("a > b"));
+		assert_eq!(sms.get("address"), Some("555"));
+		let end = tags.next_tag().unwrap().unwrap();
+		assert!(end.closing && end.name == "smses");
+		assert!(tags.next_tag().unwrap().is_none());
+	}
+
+	#[test]
+	fn normalizes_addresses() {
+		assert_eq!(normalize("(555) 123-4567"), "5551234567");
+		assert_eq!(normalize("+1 555 123 4567"), "+15551234567");
+		assert_eq!(normalize("AMAZON"), "amazon");
+		assert_eq!(normalize(PLACEHOLDER_ADDRESS), "");
+	}
+}
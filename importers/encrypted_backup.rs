@@ -0,0 +1,294 @@
+//! Encrypted iPhone backups. The keybag in Manifest.plist holds one wrapped
+//! key per protection class, which the backup password unwraps once it is
+//! stretched with PBKDF2 (SHA-256, then SHA-1). Manifest.db and every backed
+//! up file are encrypted with AES-256-CBC under their own key, which is
+//! wrapped (RFC 3394) with the key of their protection class; a file's key
+//! and size are in its `Files` row in Manifest.db.
+
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+
+use aes::cipher::block_padding::NoPadding;
+use aes::cipher::{BlockDecryptMut, KeyIvInit};
+use aes::Aes256;
+use aes_kw::KekAes256;
+use pbkdf2::pbkdf2_hmac;
+use rusqlite::{Connection, OptionalExtension};
+use sha1::Sha1;
+use sha2::Sha256;
+
+use super::ios_backup::locate;
+use crate::{readonly, AnalyzerResult};
+
+/// Class keys with this `WRAP` bit are wrapped with the password key
+const WRAP_PASSCODE: u32 = 2;
+const KEY_LEN: usize = 32;
+/// A wrapped key is the key plus the 8-byte integrity check
+const WRAPPED_KEY_LEN: usize = KEY_LEN + 8;
+
+/// An unlocked backup whose files can be decrypted.
+pub struct EncryptedBackup {
+	dir: PathBuf,
+	keybag: Keybag,
+	manifest: Connection,
+	/// Decrypted Manifest.db, removed again on drop
+	manifest_path: PathBuf
+}
+
+impl EncryptedBackup {
+	/// Unlocks the backup in `dir` with its password and decrypts Manifest.db
+	/// into `scratch_dir`. Stretching the password takes several seconds by
+	/// design.
+	pub fn unlock(dir: &Path, password: &str, scratch_dir: &Path) -> AnalyzerResult<Self> {
+		let manifest = plist::Value::from_file(dir.join("Manifest.plist"))
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+		let manifest = manifest.as_dictionary().ok_or_else(|| invalid("Manifest.plist"))?;
+		let data = |key: &str| {
+			manifest.get(key).and_then(plist::Value::as_data).ok_or_else(|| invalid(key))
+		};
+
+		let mut keybag = Keybag::parse(data("BackupKeyBag")?)?;
+		keybag.unlock(password)?;
+
+		// Protection class as 4 little-endian bytes, then the wrapped key
+		let manifest_key = data("ManifestKey")?;
+		let (class, wrapped) = manifest_key.split_at(4.min(manifest_key.len()));
+		let class = u32::from_le_bytes(class.try_into().map_err(|_| invalid("ManifestKey"))?);
+		let key = keybag.unwrap_key(class, wrapped)?;
+
+		let manifest_path = scratch_dir.join("Manifest.db");
+		fs::write(&manifest_path, decrypt(&key, &fs::read(dir.join("Manifest.db"))?)?)?;
+		let manifest = readonly::open(&manifest_path)?;
+
+		Ok(Self { dir: dir.to_path_buf(), keybag, manifest, manifest_path })
+	}
+
+	/// Decrypts the file stored under `hash` to `target`. Returns false when
+	/// the backup doesn't have it.
+	pub fn extract(&self, hash: &str, target: &Path) -> AnalyzerResult<bool> {
+		let record: Option<Vec<u8>> =
+			readonly::prepare(&self.manifest, "SELECT file FROM Files WHERE fileID = ?1")?
+				.query_row([hash], |row| row.get(0))
+				.optional()?;
+		let (Some(record), Some(path)) = (record, locate(&self.dir, hash)) else {
+			return Ok(false);
+		};
+
+		let (class, wrapped, size) = file_key(&record).ok_or_else(|| invalid(hash))?;
+		let key = self.keybag.unwrap_key(class, &wrapped)?;
+		let mut plaintext = decrypt(&key, &fs::read(path)?)?;
+		// The ciphertext is padded to the block size
+		plaintext.truncate(size as usize);
+		fs::write(target, plaintext)?;
+		Ok(true)
+	}
+}
+
+impl Drop for EncryptedBackup {
+	fn drop(&mut self) {
+		let _ = fs::remove_file(&self.manifest_path);
+	}
+}
+
+/// The backup keybag: how to stretch the password, and the class keys.
+struct Keybag {
+	salt: Vec<u8>,
+	iterations: u32,
+	/// Salt and rounds of the SHA-256 pass, since iOS 10.2
+	double_protection: Option<(Vec<u8>, u32)>,
+	class_keys: Vec<ClassKey>
+}
+
+#[derive(Default)]
+struct ClassKey {
+	class: u32,
+	wrap: u32,
+	wrapped: Vec<u8>,
+	key: Option<[u8; KEY_LEN]>
+}
+
+impl Keybag {
+	/// Reads the tag-length-value records of a keybag. Records after the
+	/// second `UUID` describe class keys, one `UUID` each.
+	fn parse(data: &[u8]) -> io::Result<Self> {
+		let mut salt = None;
+		let mut iterations = None;
+		let (mut dp_salt, mut dp_iterations) = (None, None);
+		let mut class_keys: Vec<ClassKey> = Vec::new();
+		let mut seen_uuid = false;
+
+		let mut rest = data;
+		while rest.len() >= 8 {
+			let tag = &rest[..4];
+			let length = u32::from_be_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+			let value = rest.get(8..8 + length).ok_or_else(|| invalid("keybag"))?;
+			rest = &rest[8 + length..];
+			let number = || value.try_into().map(u32::from_be_bytes).ok();
+
+			if tag == b"UUID" {
+				if seen_uuid {
+					class_keys.push(ClassKey::default());
+				}
+				seen_uuid = true;
+				continue;
+			}
+			match (tag, class_keys.last_mut()) {
+				(b"CLAS", Some(key)) => key.class = number().unwrap_or_default(),
+				(b"WRAP", Some(key)) => key.wrap = number().unwrap_or_default(),
+				(b"WPKY", Some(key)) => key.wrapped = value.to_vec(),
+				(b"SALT", None) => salt = Some(value.to_vec()),
+				(b"ITER", None) => iterations = number(),
+				(b"DPSL", None) => dp_salt = Some(value.to_vec()),
+				(b"DPIC", None) => dp_iterations = number(),
+				_ => {}
+			}
+		}
+
+		Ok(Self {
+			salt: salt.ok_or_else(|| invalid("keybag SALT"))?,
+			iterations: iterations.ok_or_else(|| invalid("keybag ITER"))?,
+			double_protection: dp_salt.zip(dp_iterations),
+			class_keys
+		})
+	}
+
+	/// Unwraps every class key protected by the password.
+	fn unlock(&mut self, password: &str) -> io::Result<()> {
+		let mut passcode = password.as_bytes().to_vec();
+		if let Some((salt, iterations)) = &self.double_protection {
+			let mut stretched = [0u8; KEY_LEN];
+			pbkdf2_hmac::<Sha256>(&passcode, salt, *iterations, &mut stretched);
+			passcode = stretched.to_vec();
+		}
+		let mut key = [0u8; KEY_LEN];
+		pbkdf2_hmac::<Sha1>(&passcode, &self.salt, self.iterations, &mut key);
+
+		for class_key in self.class_keys.iter_mut().filter(|k| k.wrap & WRAP_PASSCODE != 0) {
+			let unwrapped = aes_unwrap(&key, &class_key.wrapped).ok_or_else(|| {
+				io::Error::new(io::ErrorKind::PermissionDenied, "The backup password is wrong")
+			})?;
+			class_key.key = Some(unwrapped);
+		}
+		Ok(())
+	}
+
+	/// Unwraps a file or manifest key with its protection class key.
+	fn unwrap_key(&self, class: u32, wrapped: &[u8]) -> io::Result<[u8; KEY_LEN]> {
+		self.class_keys
+			.iter()
+			.find(|key| key.class == class)
+			.and_then(|key| key.key.as_ref())
+			.and_then(|class_key| aes_unwrap(class_key, wrapped))
+			.ok_or_else(|| invalid("file key"))
+	}
+}
+
+fn aes_unwrap(kek: &[u8; KEY_LEN], wrapped: &[u8]) -> Option<[u8; KEY_LEN]> {
+	if wrapped.len() != WRAPPED_KEY_LEN {
+		return None;
+	}
+	let mut key = [0u8; KEY_LEN];
+	KekAes256::from(*kek).unwrap(wrapped, &mut key).ok()?;
+	Some(key)
+}
+
+/// AES-256-CBC with a zero IV, as backups use it.
+fn decrypt(key: &[u8; KEY_LEN], ciphertext: &[u8]) -> io::Result<Vec<u8>> {
+	let mut buffer = ciphertext.to_vec();
+	let length = cbc::Decryptor::<Aes256>::new(key.into(), &[0u8; 16].into())
+		.decrypt_padded_mut::<NoPadding>(&mut buffer)
+		.map_err(|_| invalid("ciphertext length"))?
+		.len();
+	buffer.truncate(length);
+	Ok(buffer)
+}
+
+/// Protection class, wrapped key and plaintext size from a file's `MBFile`
+/// record, an NSKeyedArchiver plist. The key is stored behind the same
+/// 4-byte class prefix as the manifest key.
+fn file_key(record: &[u8]) -> Option<(u32, Vec<u8>, u64)> {
+	let archive = plist::Value::from_reader(Cursor::new(record)).ok()?;
+	let archive = archive.as_dictionary()?;
+	let objects = archive.get("$objects")?.as_array()?;
+	let object = |reference: &plist::Value| objects.get(reference.as_uid()?.get() as usize);
+
+	let root = object(archive.get("$top")?.as_dictionary()?.get("root")?)?.as_dictionary()?;
+	let class = root.get("ProtectionClass")?.as_unsigned_integer()? as u32;
+	let size = root.get("Size")?.as_unsigned_integer()?;
+	let key = object(root.get("EncryptionKey")?)?.as_dictionary()?.get("NS.data")?.as_data()?;
+	Some((class, key.get(4..)?.to_vec(), size))
+}
+
+fn invalid(what: &str) -> io::Error {
+	let message = format!("Unreadable {} in the encrypted backup", what);
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const PASSWORD: &str = "hunter2";
+	const CLASS_KEY: [u8; KEY_LEN] = [9; KEY_LEN];
+
+	fn record(tag: &[u8; 4], value: &[u8]) -> Vec<u8> {
+		let mut record = tag.to_vec();
+		record.extend((value.len() as u32).to_be_bytes());
+		record.extend(value);
+		record
+	}
+
+	/// A keybag with one class key wrapped with the key of `PASSWORD`.
+	fn keybag() -> Vec<u8> {
+		let salt = b"salt";
+		let mut password_key = [0u8; KEY_LEN];
+		pbkdf2_hmac::<Sha1>(PASSWORD.as_bytes(), salt, 1, &mut password_key);
+		let mut wrapped = [0u8; WRAPPED_KEY_LEN];
+		KekAes256::from(password_key).wrap(&CLASS_KEY, &mut wrapped).unwrap();
+
+		[
+			record(b"VERS", &3u32.to_be_bytes()),
+			record(b"UUID", &[1; 16]),
+			record(b"SALT", salt),
+			record(b"ITER", &1u32.to_be_bytes()),
+			record(b"UUID", &[2; 16]),
+			record(b"CLAS", &3u32.to_be_bytes()),
+			record(b"WRAP", &WRAP_PASSCODE.to_be_bytes()),
+			record(b"WPKY", &wrapped)
+		]
+		.concat()
+	}
+
+	#[test]
+	fn parses_keybag_records() {
+		let keybag = Keybag::parse(&keybag()).unwrap();
+		assert_eq!(keybag.salt, b"salt");
+		assert_eq!(keybag.iterations, 1);
+		assert!(keybag.double_protection.is_none());
+		assert_eq!(keybag.class_keys.len(), 1);
+		assert_eq!(keybag.class_keys[0].class, 3);
+		assert_eq!(keybag.class_keys[0].wrapped.len(), WRAPPED_KEY_LEN);
+	}
+
+	#[test]
+	fn rejects_truncated_keybag() {
+		let mut data = keybag();
+		data.truncate(data.len() - 1);
+		assert!(Keybag::parse(&data).is_err());
+	}
+
+	#[test]
+	fn unlocks_with_the_password() {
+		let mut keybag = Keybag::parse(&keybag()).unwrap();
+		keybag.unlock(PASSWORD).unwrap();
+		assert_eq!(keybag.class_keys[0].key, Some(CLASS_KEY));
+	}
+
+	#[test]
+	fn wrong_password_is_an_error() {
+		let mut keybag = Keybag::parse(&keybag()).unwrap();
+		let error = keybag.unlock("hunter3").unwrap_err();
+		assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+	}
+}
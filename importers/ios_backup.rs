@@ -0,0 +1,229 @@
+//! Local iPhone backups made by Finder, or iTunes on Windows. A backup keeps
+//! every file under the SHA-1 of its domain and path, in a folder named after
+//! the hash's first two hex digits (older backups keep them all at the top).
+//! The iPhone's Messages database has the same tables as chat.db and is
+//! copied as is; its AddressBook predates the macOS one and is converted.
+//! Encrypted backups are decrypted with their password in `encrypted_backup`.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use super::encrypted_backup::EncryptedBackup;
+use super::{Import, ADDRESS_BOOK_SCHEMA};
+use crate::{paths, readonly, AnalyzerResult};
+
+/// SHA-1 of `HomeDomain-Library/SMS/sms.db`
+pub const SMS_DB: &str = "3d0d7e5fb2ce288813306e4d4636395e047a3d28";
+/// SHA-1 of `HomeDomain-Library/AddressBook/AddressBook.sqlitedb`
+pub const ADDRESS_BOOK: &str = "31bb7ba8914766d4ba40d6dfb6113c8b614be442";
+/// `property` of phone numbers and email addresses in `ABMultiValue`
+const PHONE_PROPERTY: i64 = 3;
+const EMAIL_PROPERTY: i64 = 4;
+
+/// One device's backup.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Backup {
+	/// Folder name, the device's UDID
+	pub id: String,
+	pub device_name: Option<String>,
+	/// iOS version the backup was made with
+	pub product_version: Option<String>,
+	/// Unix milliseconds
+	pub last_backup: Option<u64>,
+	/// Encrypted backups need their password to be imported
+	pub encrypted: bool,
+	pub has_messages: bool
+}
+
+/// Every backup in the backup folder, most recent first.
+pub fn list() -> AnalyzerResult<Vec<Backup>> {
+	let entries = match fs::read_dir(paths::ios_backups()) {
+		Ok(entries) => entries,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(e.into())
+	};
+	let mut backups: Vec<Backup> =
+		entries.filter_map(Result::ok).filter_map(|entry| read_backup(&entry.path())).collect();
+	backups.sort_by(|a, b| b.last_backup.cmp(&a.last_backup));
+	Ok(backups)
+}
+
+/// Copies Messages out of backup `id` into `out_dir` and converts its
+/// AddressBook, replacing an earlier import there. Encrypted backups need
+/// their `password`. Without `id` the most recent backup that can be
+/// imported is used.
+pub fn import(id: Option<&str>, password: Option<&str>, out_dir: &Path) -> AnalyzerResult<Import> {
+	let backup = list()?.into_iter().find(|backup| match id {
+		Some(id) => backup.id == id,
+		None => backup.has_messages && (!backup.encrypted || password.is_some())
+	});
+	let Some(backup) = backup else {
+		return Err(io::Error::new(io::ErrorKind::NotFound, "No iPhone backup with Messages found")
+			.into());
+	};
+
+	let dir = paths::ios_backups().join(&backup.id);
+	let chat_db_path = out_dir.join("chat.db");
+	let address_book_path = out_dir.join("AddressBook");
+	let address_book_db = address_book_path.join("Sources/iphone/AddressBook-v22.abcddb");
+	let _ = fs::remove_file(&chat_db_path);
+	let _ = fs::remove_dir_all(&address_book_path);
+	fs::create_dir_all(address_book_db.parent().unwrap_or(out_dir))?;
+
+	// Decrypted files are written next to the import and removed once read
+	let decrypted_address_book = out_dir.join("AddressBook.sqlitedb");
+	let _cleanup = scopeguard::guard(decrypted_address_book.clone(), |path| {
+		let _ = fs::remove_file(path);
+	});
+	let (has_messages, address_book_source) = if backup.encrypted {
+		let message = "The iPhone backup is encrypted, enter its password";
+		let password =
+			password.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, message))?;
+		let encrypted = EncryptedBackup::unlock(&dir, password, out_dir)?;
+		let has_address_book = encrypted.extract(ADDRESS_BOOK, &decrypted_address_book)?;
+		(
+			encrypted.extract(SMS_DB, &chat_db_path)?,
+			has_address_book.then_some(decrypted_address_book.clone())
+		)
+	} else {
+		let sms_db = locate(&dir, SMS_DB);
+		if let Some(sms_db) = &sms_db {
+			fs::copy(sms_db, &chat_db_path)?;
+		}
+		(sms_db.is_some(), locate(&dir, ADDRESS_BOOK))
+	};
+	if !has_messages {
+		return Err(io::Error::new(io::ErrorKind::NotFound, "The backup has no Messages").into());
+	}
+
+	let chat_db = readonly::open(&chat_db_path)?;
+	let (messages, chats) = (count(&chat_db, "message")?, count(&chat_db, "chat")?);
+	let _ = chat_db.close();
+
+	let contacts = match address_book_source {
+		Some(source) => convert_address_book(&source, &address_book_db)?,
+		None => 0
+	};
+
+	Ok(Import {
+		chat_db_path,
+		address_book_path,
+		messages,
+		chats,
+		contacts,
+		skipped: 0,
+		decrypted: backup.encrypted
+	})
+}
+
+/// Deletes an import from `out_dir`. Returns whether there was one.
+pub fn remove(out_dir: &Path) -> AnalyzerResult<bool> {
+	match fs::remove_dir_all(out_dir) {
+		Ok(()) => Ok(true),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+		Err(e) => Err(e.into())
+	}
+}
+
+/// Path of a backed up file by its hash.
+pub fn locate(dir: &Path, hash: &str) -> Option<PathBuf> {
+	[dir.join(&hash[..2]).join(hash), dir.join(hash)].into_iter().find(|path| path.is_file())
+}
+
+/// Reads a backup's Info.plist and Manifest.plist. `None` for folders that
+/// aren't backups.
+fn read_backup(dir: &Path) -> Option<Backup> {
+	let info = plist::Value::from_file(dir.join("Info.plist")).ok()?;
+	let info = info.as_dictionary()?;
+	let string = |key: &str| info.get(key).and_then(plist::Value::as_string).map(str::to_string);
+	let last_backup = info
+		.get("Last Backup Date")
+		.and_then(plist::Value::as_date)
+		.and_then(|date| SystemTime::from(date).duration_since(UNIX_EPOCH).ok())
+		.map(|since| since.as_millis() as u64);
+	let encrypted = plist::Value::from_file(dir.join("Manifest.plist"))
+		.ok()
+		.and_then(|manifest| manifest.as_dictionary()?.get("IsEncrypted")?.as_boolean())
+		.unwrap_or(false);
+
+	Some(Backup {
+		id: dir.file_name()?.to_string_lossy().into_owned(),
+		device_name: string("Device Name"),
+		product_version: string("Product Version"),
+		last_backup,
+		encrypted,
+		has_messages: locate(dir, SMS_DB).is_some()
+	})
+}
+
+fn count(db: &Connection, table: &str) -> AnalyzerResult<usize> {
+	let count: i64 = readonly::prepare(db, &format!("SELECT COUNT(*) FROM {}", table))?
+		.query_row([], |row| row.get(0))?;
+	Ok(count as usize)
+}
+
+/// Writes the people in an iPhone `AddressBook.sqlitedb` into a macOS
+/// AddressBook source database. Returns how many there were.
+fn convert_address_book(source: &Path, target: &Path) -> AnalyzerResult<usize> {
+	let source = readonly::open(source)?;
+	let mut target = Connection::open(target)?;
+	target.execute_batch(ADDRESS_BOOK_SCHEMA)?;
+	target.execute("INSERT INTO ZABCDCONTAINER (ZNAME) VALUES ('iPhone')", [])?;
+	let tx = target.transaction()?;
+
+	// Birthdays are seconds since 2001 on both sides, stored as text here
+	let mut people = readonly::prepare(
+		&source,
+		"SELECT ROWID, First, Last, Middle, Nickname, Organization, \
+		 CAST(NULLIF(Birthday, '') AS REAL) FROM ABPerson"
+	)?;
+	let mut rows = people.query([])?;
+	let mut contacts = 0;
+	while let Some(row) = rows.next()? {
+		tx.execute(
+			"INSERT INTO ZABCDRECORD (Z_PK, ZFIRSTNAME, ZLASTNAME, ZMIDDLENAME, ZNICKNAME, \
+			 ZORGANIZATION, ZBIRTHDAY) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+			params![
+				row.get::<_, i64>(0)?,
+				row.get::<_, Option<String>>(1)?,
+				row.get::<_, Option<String>>(2)?,
+				row.get::<_, Option<String>>(3)?,
+				row.get::<_, Option<String>>(4)?,
+				row.get::<_, Option<String>>(5)?,
+				row.get::<_, Option<f64>>(6)?
+			]
+		)?;
+		contacts += 1;
+	}
+	drop(rows);
+	drop(people);
+
+	let mut values = readonly::prepare(
+		&source,
+		"SELECT record_id, property, value FROM ABMultiValue
+		 WHERE property IN (?1, ?2) AND value IS NOT NULL"
+	)?;
+	let mut rows = values.query([PHONE_PROPERTY, EMAIL_PROPERTY])?;
+	while let Some(row) = rows.next()? {
+		let (owner, property, value): (i64, i64, String) = (row.get(0)?, row.get(1)?, row.get(2)?);
+		let insert = if property == EMAIL_PROPERTY {
+			"INSERT INTO ZABCDEMAILADDRESS (ZOWNER, ZADDRESS) VALUES (?1, ?2)"
+		} else {
+			"INSERT INTO ZABCDPHONENUMBER (ZOWNER, ZFULLNUMBER) VALUES (?1, ?2)"
+		};
+		tx.execute(insert, params![owner, value])?;
+	}
+	drop(rows);
+	drop(values);
+
+	tx.commit()?;
+	let _ = target.close();
+	let _ = source.close();
+	Ok(contacts)
+}
@@ -0,0 +1,41 @@
+//! Message history from outside Messages on this Mac. Importers write what
+//! they read into a chat.db-shaped database, plus an AddressBook for the
+//! names they carry, so the analysis reads imported history exactly like the
+//! real thing: pass the paths as `chatDbPath` and `addressBookPath`.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+pub mod android_xml;
+mod encrypted_backup;
+pub mod ios_backup;
+
+/// The tables of a macOS AddressBook source database that contacts are read
+/// from
+const ADDRESS_BOOK_SCHEMA: &str = "
+CREATE TABLE ZABCDRECORD (
+	Z_PK INTEGER PRIMARY KEY, ZFIRSTNAME TEXT, ZLASTNAME TEXT, ZMIDDLENAME TEXT, ZNICKNAME TEXT,
+	ZORGANIZATION TEXT, ZBIRTHDAY TIMESTAMP
+);
+CREATE TABLE ZABCDPHONENUMBER (Z_PK INTEGER PRIMARY KEY, ZOWNER INTEGER, ZFULLNUMBER TEXT);
+CREATE TABLE ZABCDEMAILADDRESS (Z_PK INTEGER PRIMARY KEY, ZOWNER INTEGER, ZADDRESS TEXT);
+CREATE TABLE ZABCDRELATEDNAME (Z_PK INTEGER PRIMARY KEY, ZOWNER INTEGER, ZLABEL TEXT, ZNAME TEXT);
+CREATE TABLE ZABCDCONTAINER (Z_PK INTEGER PRIMARY KEY, ZNAME TEXT);
+";
+
+/// Where an import was written and what it contained.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Import {
+	pub chat_db_path: PathBuf,
+	pub address_book_path: PathBuf,
+	pub messages: usize,
+	pub chats: usize,
+	pub contacts: usize,
+	/// Records that couldn't be imported, e.g. drafts and failed sends
+	pub skipped: usize,
+	/// Read out of an encrypted backup. The decrypted copy stays readable in
+	/// the data directory until the import is removed or `purge_all_data`
+	pub decrypted: bool
+}
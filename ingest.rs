@@ -0,0 +1,52 @@
+//! Streaming reads of the message table. Rows are handed to a sink one at a
+//! time as they come out of SQLite, so callers decide what to keep instead of
+//! materializing the whole table first. Memory is only bounded if the sink
+//! keeps it so: the analysis still collects every message in range, because
+//! the stats and insight passes work on slices.
+
+use imessage_database::tables::messages::Message;
+use imessage_database::tables::table::Table;
+use rusqlite::Connection;
+
+use crate::options::DateRange;
+use crate::progress::Reporter;
+use crate::{low_impact, readonly, AnalyzerResult};
+
+const HEARTBEAT_ROWS: usize = 50_000;
+const START_PERCENT: f64 = 5.0;
+const END_PERCENT: f64 = 40.0;
+
+/// Sends every message in `range` to `sink`, with a heartbeat every
+/// `HEARTBEAT_ROWS` rows so the UI can show how far the query has got.
+/// Returns the number of rows read, including ones outside the range.
+pub fn stream<F>(
+	chat_db: &Connection, range: &DateRange, paced: bool, progress: &Reporter, mut sink: F
+) -> AnalyzerResult<usize>
+where
+	F: FnMut(Message)
+{
+	let total: i64 = readonly::prepare(chat_db, "SELECT COUNT(*) FROM message")?
+		.query_row([], |row| row.get(0))?;
+
+	let mut statement = Message::get(chat_db)?;
+	let rows = statement.query_map([], |row| Ok(Message::from_row(row)))?;
+	let mut read = 0;
+	for row in rows {
+		let message = Message::extract(row)?;
+		if range.contains(message.date) {
+			sink(message);
+		}
+		read += 1;
+		if paced && read % low_impact::ROWS_PER_BATCH == 0 {
+			low_impact::pause();
+		}
+		if read % HEARTBEAT_ROWS == 0 {
+			let done = read as f64 / total.max(1) as f64;
+			let percent = START_PERCENT + (END_PERCENT - START_PERCENT) * done.min(1.0);
+			progress.heartbeat("loadingMessages", percent, read as i64);
+		}
+	}
+	progress.heartbeat("loadingMessages", END_PERCENT, read as i64);
+
+	Ok(read)
+}
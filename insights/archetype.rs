@@ -0,0 +1,88 @@
+use imessage_database::tables::messages::Message;
+
+use super::words::{is_emoji, words};
+use super::{apple_seconds, is_tapback, replies, Sources};
+use crate::stats::stats::{ArchetypeFactor, TextingArchetype};
+
+const MIN_MESSAGES: usize = 50;
+/// A second message of mine after this long without an answer is a double text
+const DOUBLE_TEXT_SECONDS: i64 = 5 * 60;
+const MAX_REPLY_SECONDS: i64 = 24 * 60 * 60;
+
+const FACTORS: [&str; 5] =
+	["doubleTexting", "reactionUsage", "responseSpeed", "messageLength", "emojiDensity"];
+
+/// Each archetype is the factor profile it stands for, in `FACTORS` order.
+const ARCHETYPES: &[(&str, [f32; 5])] = &[
+	("The Hype Machine", [0.3, 0.9, 0.6, 0.3, 0.9]),
+	("The Novelist", [0.2, 0.2, 0.4, 0.9, 0.3]),
+	("The Rapid Fire", [0.9, 0.3, 0.8, 0.2, 0.5]),
+	("The Speed Demon", [0.3, 0.3, 0.95, 0.3, 0.3]),
+	("The Ghost", [0.1, 0.2, 0.1, 0.3, 0.2]),
+	("The Minimalist", [0.1, 0.1, 0.5, 0.1, 0.1])
+];
+
+/// Places me in the archetype whose factor profile is closest to mine. The
+/// factor scores are included so the viewer can draw the profile.
+pub fn texting_archetype(messages: &[Message], sources: &Sources) -> Option<TextingArchetype> {
+	let sent: Vec<&Message> = messages.iter().filter(|m| m.is_from_me).collect();
+	let typed: Vec<&Message> = sent.iter().copied().filter(|m| !is_tapback(m)).collect();
+	if typed.len() < MIN_MESSAGES {
+		return None;
+	}
+
+	let tapbacks = sent.len() - typed.len();
+	let mut word_count = 0;
+	let mut emoji_count = 0;
+	for text in typed.iter().filter_map(|m| m.text.as_deref()) {
+		word_count += words(text).count();
+		emoji_count += text.chars().filter(|&c| is_emoji(c)).count();
+	}
+
+	let mut reply_seconds = Vec::new();
+	let mut double_texts = 0;
+	for conversation in super::conversations_by_contact(messages, sources).values() {
+		reply_seconds.extend(
+			replies(conversation)
+				.into_iter()
+				.filter(|r| r.reply.is_from_me && r.seconds <= MAX_REPLY_SECONDS)
+				.map(|r| r.seconds)
+		);
+		double_texts += conversation
+			.windows(2)
+			.filter(|pair| {
+				pair[0].is_from_me &&
+					pair[1].is_from_me &&
+					apple_seconds(pair[1].date) - apple_seconds(pair[0].date) >= DOUBLE_TEXT_SECONDS
+			})
+			.count();
+	}
+	reply_seconds.sort_unstable();
+	let median_reply = reply_seconds.get(reply_seconds.len() / 2).copied().unwrap_or(MAX_REPLY_SECONDS);
+
+	let typed_count = typed.len() as f32;
+	let scores = [
+		(double_texts as f32 / typed_count / 0.3).min(1.0),
+		(tapbacks as f32 / typed_count / 0.2).min(1.0),
+		1.0 - ((1.0 + median_reply as f32 / 60.0).ln() / (1.0 + 1440f32).ln()).min(1.0),
+		(word_count as f32 / typed_count / 15.0).min(1.0),
+		(emoji_count as f32 / typed_count).min(1.0)
+	];
+
+	let (archetype, _) = ARCHETYPES
+		.iter()
+		.map(|(name, profile)| {
+			let distance: f32 = profile.iter().zip(&scores).map(|(p, s)| (p - s).powi(2)).sum();
+			(name, distance)
+		})
+		.min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+	Some(TextingArchetype {
+		archetype: archetype.to_string(),
+		factors: FACTORS
+			.iter()
+			.zip(scores)
+			.map(|(name, score)| ArchetypeFactor { name: name.to_string(), score })
+			.collect()
+	})
+}
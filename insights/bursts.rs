@@ -0,0 +1,49 @@
+use imessage_database::tables::messages::Message;
+
+use super::{apple_seconds, messages_by_chat, unix_seconds, Sources};
+use crate::stats::stats::{BurstStats, MessageBurst};
+
+/// Finds runs of my messages to one chat with no reply in between and short
+/// pauses between them, and reports how many there were and the biggest rant.
+pub fn burst_stats(messages: &[Message], sources: &Sources) -> BurstStats {
+	let min_messages = sources.options.burst_min_messages();
+	let max_gap = sources.options.burst_max_gap_seconds();
+
+	let mut burst_count = 0;
+	// Chat, message count, and first and last message of the biggest burst
+	let mut biggest: Option<(i32, usize, &Message, &Message)> = None;
+	for (chat_id, chat_messages) in messages_by_chat(messages) {
+		let mut start = 0;
+		for end in 1..=chat_messages.len() {
+			let continues = end < chat_messages.len() && {
+				let (previous, current) = (chat_messages[end - 1], chat_messages[end]);
+				previous.is_from_me &&
+					current.is_from_me &&
+					apple_seconds(current.date) - apple_seconds(previous.date) <= max_gap
+			};
+			if continues {
+				continue;
+			}
+
+			let run = &chat_messages[start..end];
+			if run[0].is_from_me && run.len() >= min_messages {
+				burst_count += 1;
+				if biggest.map_or(true, |(_, count, _, _)| run.len() > count) {
+					biggest = Some((chat_id, run.len(), run[0], run[run.len() - 1]));
+				}
+			}
+			start = end;
+		}
+	}
+
+	BurstStats {
+		burst_count,
+		biggest: biggest.map(|(chat_id, count, first, last)| MessageBurst {
+			chat_name: sources.chat_name(chat_id),
+			chat_id: Some(chat_id),
+			message_count: count as i32,
+			duration_seconds: apple_seconds(last.date) - apple_seconds(first.date),
+			date: unix_seconds(first.date)
+		})
+	}
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+
+use chrono::Timelike;
+use imessage_database::tables::messages::Message;
+
+use super::{is_tapback, local_time, top_contacts, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{ChronotypeStats, ScheduleTwin};
+
+const MIN_MESSAGES: f64 = 50.0;
+
+/// Classifies my texting chronotype from the circular mean of my sending
+/// hours. The confidence is the mean resultant length: 1 when every message
+/// is sent at the same hour, 0 when sending is spread evenly over the day.
+pub fn chronotype_stats(messages: &[Message], sources: &Sources) -> Option<ChronotypeStats> {
+	let mut mine = [0f64; 24];
+	let mut theirs: HashMap<i32, [f64; 24]> = HashMap::new();
+	for message in messages.iter().filter(|m| !is_tapback(m)) {
+		let Some(hour) = local_time(message.date).map(|t| t.hour() as usize) else { continue };
+		if message.is_from_me {
+			mine[hour] += 1.0;
+		} else if let Some(handle) = message.handle_id {
+			theirs.entry(handle).or_insert([0.0; 24])[hour] += 1.0;
+		}
+	}
+
+	let total: f64 = mine.iter().sum();
+	if total < MIN_MESSAGES {
+		return None;
+	}
+
+	let (x, y) = mine.iter().enumerate().fold((0.0, 0.0), |(x, y), (hour, count)| {
+		let angle = hour as f64 / 24.0 * TAU;
+		(x + count * angle.cos(), y + count * angle.sin())
+	});
+	let peak_hour = (y.atan2(x).rem_euclid(TAU) / TAU * 24.0) as f32;
+	let confidence = ((x * x + y * y).sqrt() / total) as f32;
+
+	let conversations = super::conversations_by_contact(messages, sources);
+	let candidates = sources.options.top(TopList::ScheduleTwinCandidates);
+	let schedule_twin = top_contacts(&conversations, candidates)
+		.into_iter()
+		.filter_map(|handle| {
+			let hours = theirs.get(&handle)?;
+			if hours.iter().sum::<f64>() < MIN_MESSAGES {
+				return None;
+			}
+			Some((handle, cosine_similarity(&mine, hours)))
+		})
+		.max_by(|a, b| a.1.total_cmp(&b.1))
+		.map(|(handle, similarity)| {
+			let (name, handle_id) = sources.person(handle);
+			ScheduleTwin { name, handle_id, similarity: similarity as f32, avatar: None }
+		});
+
+	Some(ChronotypeStats {
+		persona: persona(peak_hour).to_string(),
+		peak_hour,
+		confidence,
+		schedule_twin
+	})
+}
+
+fn persona(peak_hour: f32) -> &'static str {
+	match peak_hour {
+		h if (5.0..11.0).contains(&h) => "Early Bird",
+		h if (11.0..18.0).contains(&h) => "Daytime Texter",
+		h if (18.0..22.0).contains(&h) => "Evening Chatter",
+		_ => "Night Owl"
+	}
+}
+
+fn cosine_similarity(a: &[f64; 24], b: &[f64; 24]) -> f64 {
+	let dot: f64 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+	let norm = |v: &[f64; 24]| v.iter().map(|x| x * x).sum::<f64>().sqrt();
+	dot / (norm(a) * norm(b)).max(f64::EPSILON)
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::{top_items, Sources};
+use crate::options::{PrivacyLevel, TopList};
+use crate::stats::stats::{ContactCardStats, PhraseStats};
+
+/// Counts vCards exchanged. The transfer name of a shared card is the name of
+/// the contact on it, which gives us "most shared contact". Those are people
+/// outside the conversation, so their names are only included when the
+/// privacy level is permissive.
+pub fn contact_card_stats(messages: &[Message], sources: &Sources) -> ContactCardStats {
+	let mut sent = 0;
+	let mut received = 0;
+	let mut shared_contacts: HashMap<String, i32> = HashMap::new();
+	let mut senders: HashMap<i32, i32> = HashMap::new();
+
+	for message in messages.iter().filter(|m| m.num_attachments > 0) {
+		for card in sources.attachments.for_message(message.rowid).iter().filter(|a| a.is_vcard()) {
+			if message.is_from_me {
+				sent += 1;
+			} else {
+				received += 1;
+				if let Some(handle) = message.handle_id {
+					*senders.entry(handle).or_default() += 1;
+				}
+			}
+
+			if let Some(name) = card.transfer_name.as_deref() {
+				let name = name.trim_end_matches(".vcf").trim_end_matches(".VCF").trim();
+				if !name.is_empty() {
+					*shared_contacts.entry(name.to_string()).or_default() += 1;
+				}
+			}
+		}
+	}
+
+	let top_card_sender = senders
+		.into_iter()
+		.max_by_key(|&(handle, count)| (count, -handle))
+		.map(|(handle, count)| {
+			let (name, handle_id) = sources.person(handle);
+			PhraseStats { name, handle_id, count, avatar: None }
+		});
+
+	let most_shared_contacts = if sources.options.privacy_level() == PrivacyLevel::Permissive {
+		top_items(shared_contacts, sources.options.top(TopList::People))
+	} else {
+		Vec::new()
+	};
+
+	ContactCardStats { sent, received, most_shared_contacts, top_card_sender }
+}
@@ -0,0 +1,63 @@
+use imessage_database::tables::messages::Message;
+
+use super::is_tapback;
+use super::words::{is_emoji, words};
+use crate::stats::stats::DictationStats;
+
+/// Spoken punctuation that dictation sometimes transcribes literally
+const LITERAL_ARTIFACTS: &[&str] =
+	&[" period", " comma", " question mark", " exclamation point", " new line"];
+/// Dictated messages tend to be long, full sentences
+const MIN_DICTATED_WORDS: usize = 8;
+/// Share of my messages starting lowercase for tidy sentences to look unusual
+const LOWERCASE_HABIT: f32 = 0.6;
+const MIN_MESSAGES: usize = 50;
+
+/// Estimates how many of my messages were dictated. Literal spoken punctuation
+/// is a giveaway; otherwise, if I usually type in lowercase, long messages
+/// with sentence capitalization, a final period, and no emoji look dictated.
+pub fn dictation_stats(messages: &[Message]) -> Option<DictationStats> {
+	let texts: Vec<&str> = messages
+		.iter()
+		.filter(|m| m.is_from_me && !is_tapback(m))
+		.filter_map(|m| m.text.as_deref())
+		.filter(|text| !text.trim().is_empty())
+		.collect();
+	if texts.len() < MIN_MESSAGES {
+		return None;
+	}
+
+	let lowercase_starts = texts
+		.iter()
+		.filter(|text| text.trim_start().starts_with(|c: char| c.is_lowercase()))
+		.count();
+	let types_lowercase = lowercase_starts as f32 / texts.len() as f32 >= LOWERCASE_HABIT;
+
+	let mut likely_dictated = 0;
+	let mut literal_artifacts = 0;
+	for text in &texts {
+		let lowercase = text.to_lowercase();
+		if LITERAL_ARTIFACTS.iter().any(|artifact| lowercase.trim_end().ends_with(artifact)) {
+			literal_artifacts += 1;
+			likely_dictated += 1;
+			continue;
+		}
+
+		let trimmed = text.trim();
+		if types_lowercase &&
+			words(trimmed).count() >= MIN_DICTATED_WORDS &&
+			trimmed.starts_with(|c: char| c.is_uppercase()) &&
+			trimmed.ends_with('.') &&
+			!trimmed.chars().any(is_emoji)
+		{
+			likely_dictated += 1;
+		}
+	}
+
+	Some(DictationStats {
+		analyzed: texts.len() as i32,
+		likely_dictated,
+		literal_artifacts,
+		dictated_share: likely_dictated as f32 / texts.len() as f32
+	})
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::words::{is_dry_reply, is_emoji, words};
+use super::{direct_chats, is_tapback, top_contacts, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{DrynessScore, DrynessStats};
+
+/// Contacts need this many messages to me before they get a score
+const MIN_MESSAGES: i32 = 20;
+/// Averages at or past these values count as fully juicy (or fully dry)
+const JUICY_WORDS: f32 = 12.0;
+const JUICY_EMOJI_RATE: f32 = 0.3;
+const DRY_TAPBACK_RATE: f32 = 0.3;
+const DRY_REPLY_RATE: f32 = 0.3;
+
+#[derive(Default)]
+struct Tally {
+	messages: i32,
+	words: i32,
+	with_emoji: i32,
+	dry_replies: i32,
+	tapbacks: i32
+}
+
+impl Tally {
+	fn add(&mut self, message: &Message) {
+		if is_tapback(message) {
+			self.tapbacks += 1;
+			return;
+		}
+		let Some(text) = message.text.as_deref() else { return };
+
+		self.messages += 1;
+		self.words += words(text).count() as i32;
+		if text.chars().any(is_emoji) {
+			self.with_emoji += 1;
+		}
+		if is_dry_reply(text) {
+			self.dry_replies += 1;
+		}
+	}
+
+	/// Scores from 0 (juicy) to 100 (bone dry).
+	fn score(&self, name: String, handle_id: String) -> DrynessScore {
+		let messages = self.messages.max(1) as f32;
+		let average_words = self.words as f32 / messages;
+		let emoji_rate = self.with_emoji as f32 / messages;
+		let tapback_rate = self.tapbacks as f32 / (self.tapbacks + self.messages).max(1) as f32;
+		let dry_reply_rate = self.dry_replies as f32 / messages;
+
+		let score = 0.35 * (1.0 - (average_words / JUICY_WORDS).min(1.0)) +
+			0.2 * (1.0 - (emoji_rate / JUICY_EMOJI_RATE).min(1.0)) +
+			0.2 * (tapback_rate / DRY_TAPBACK_RATE).min(1.0) +
+			0.25 * (dry_reply_rate / DRY_REPLY_RATE).min(1.0);
+
+		DrynessScore {
+			name,
+			handle_id,
+			score: score * 100.0,
+			average_words,
+			emoji_rate,
+			tapback_rate,
+			dry_reply_rate,
+			avatar: None
+		}
+	}
+}
+
+/// Scores how dry my texting is, and that of my top contacts towards me, from
+/// message length, emoji use, tapping back instead of replying, and bare
+/// "k"/"lol" replies in one-on-one chats.
+pub fn dryness_stats(messages: &[Message], sources: &Sources) -> DrynessStats {
+	let direct_chats = direct_chats(messages, sources);
+
+	let mut mine = Tally::default();
+	let mut theirs: HashMap<i32, Tally> = HashMap::new();
+	for message in messages {
+		let Some(&handle) = message.chat_id.and_then(|chat_id| direct_chats.get(&chat_id)) else {
+			continue;
+		};
+		if message.is_from_me {
+			mine.add(message);
+		} else {
+			theirs.entry(handle).or_default().add(message);
+		}
+	}
+
+	let conversations = super::conversations_by_contact(messages, sources);
+	let limit = sources.options.top(TopList::Contacts);
+	let contacts: Vec<DrynessScore> = top_contacts(&conversations, limit)
+		.into_iter()
+		.filter_map(|handle| {
+			let tally = theirs.get(&handle).filter(|t| t.messages >= MIN_MESSAGES)?;
+			let (name, handle_id) = sources.person(handle);
+			Some(tally.score(name, handle_id))
+		})
+		.collect();
+
+	let driest = contacts.iter().max_by(|a, b| a.score.total_cmp(&b.score)).cloned();
+	let juiciest = contacts.iter().min_by(|a, b| a.score.total_cmp(&b.score)).cloned();
+
+	DrynessStats {
+		me: Some(mine.score(String::from("You"), String::new())),
+		contacts,
+		driest,
+		juiciest
+	}
+}
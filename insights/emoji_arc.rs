@@ -0,0 +1,83 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::emoji_months::EmojiMonths;
+use crate::stats::stats::{EmojiArc, EmojiArcPoint};
+
+/// Scored emoji a month needs to be the happiest or gloomiest
+const MIN_SCORED: i32 = 5;
+
+/// The emotional arc of my year, one point per month, from the monthly emoji
+/// counts emoji of the month already gathers.
+pub fn emoji_arc(messages: &[Message]) -> EmojiArc {
+	let mut months = EmojiMonths::default();
+	for message in messages {
+		months.add(message);
+	}
+	months.arc()
+}
+
+/// Scores each month's emoji mix on `valence`.
+pub fn arc(months: &[HashMap<String, i32>; 12]) -> EmojiArc {
+	let mut total = 0.0;
+	let mut total_scored = 0;
+	let points: Vec<EmojiArcPoint> = months
+		.iter()
+		.enumerate()
+		.map(|(index, counts)| {
+			let scores: Vec<(&String, i32, f32)> = counts
+				.iter()
+				.filter_map(|(emoji, &count)| Some((emoji, count, valence(emoji.chars().next()?)?)))
+				.collect();
+			let sum: f32 = scores.iter().map(|&(_, count, valence)| valence * count as f32).sum();
+			let scored: i32 = scores.iter().map(|&(_, count, _)| count).sum();
+			let top = scores.iter().max_by_key(|&&(emoji, count, _)| (count, Reverse(emoji)));
+			total += sum;
+			total_scored += scored;
+			EmojiArcPoint {
+				month: index as i32 + 1,
+				valence: (scored > 0).then(|| sum / scored as f32),
+				scored,
+				emoji: top.map(|(emoji, _, _)| emoji.to_string())
+			}
+		})
+		.collect();
+
+	let ranked = || {
+		points.iter().filter(|point| point.scored >= MIN_SCORED).filter_map(|point| {
+			point.valence.map(|valence| (point.month, valence))
+		})
+	};
+	EmojiArc {
+		average: (total_scored > 0).then(|| total / total_scored as f32),
+		happiest_month: ranked().max_by(|a, b| a.1.total_cmp(&b.1)).map(|(month, _)| month),
+		gloomiest_month: ranked().min_by(|a, b| a.1.total_cmp(&b.1)).map(|(month, _)| month),
+		points
+	}
+}
+
+/// Rough mood of an emoji, from -1 (gloomy) to 1 (happy). Emoji that are
+/// neutral or read both ways, like 😭 and 💀 which mostly mean laughing,
+/// aren't scored.
+fn valence(emoji: char) -> Option<f32> {
+	Some(match emoji {
+		'🥰' | '😍' | '🤩' | '🥳' | '❤' | '💕' | '💖' | '💗' | '💞' => 0.9,
+		'😀' | '😃' | '😄' | '😁' | '😆' | '🤣' | '😊' | '😘' | '🎉' | '🎊' => 0.8,
+		'😂' | '☺' | '🤗' | '🙌' | '💓' | '💘' | '💝' | '💛' | '💙' | '💜' | '💚' | '🧡' => 0.7,
+		'😎' | '😇' | '😋' | '😚' | '👏' | '💯' => 0.6,
+		'👍' | '🔥' | '✨' | '😗' | '😙' | '🌞' => 0.5,
+		'🙂' | '😉' | '😌' | '🙏' => 0.4,
+		'😅' => 0.2,
+		'😑' => -0.2,
+		'😬' | '😪' => -0.3,
+		'😕' | '🙄' | '😓' | '😷' => -0.4,
+		'😟' | '🙁' | '😣' | '😩' | '😤' | '😥' | '😱' | '😒' | '👎' | '🤒' | '🤕' => -0.5,
+		'😔' | '☹' | '😖' | '😫' | '😰' | '😨' | '🤢' => -0.6,
+		'😢' | '😞' | '🤮' => -0.7,
+		'😠' => -0.8,
+		'😡' | '🤬' | '💔' => -0.9,
+		_ => return None
+	})
+}
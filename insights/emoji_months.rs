@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+use chrono::Datelike;
+use imessage_database::tables::messages::Message;
+
+use super::words::emojis;
+use super::{is_tapback, local_time, top_items};
+use crate::stats::stats::{EmojiArc, Item};
+
+/// The emoji I sent most in each month, always 12 entries. Months without
+/// any emoji get an empty key and a zero count.
+pub fn emoji_of_the_month(messages: &[Message]) -> Vec<Item> {
+	let mut months = EmojiMonths::default();
+	for message in messages {
+		months.add(message);
+	}
+	months.finish()
+}
+
+/// Emoji counts by month, filled one message at a time so the fused engine
+/// can share its scan.
+#[derive(Default)]
+pub struct EmojiMonths {
+	months: [HashMap<String, i32>; 12]
+}
+
+impl EmojiMonths {
+	pub fn add(&mut self, message: &Message) {
+		if !message.is_from_me || is_tapback(message) {
+			return;
+		}
+		let (Some(text), Some(time)) = (message.text.as_deref(), local_time(message.date)) else {
+			return;
+		};
+		for emoji in emojis(text) {
+			*self.months[time.month0() as usize].entry(emoji.to_string()).or_default() += 1;
+		}
+	}
+
+	/// The emotional arc of the year, scored from the same counts.
+	pub fn arc(&self) -> EmojiArc {
+		super::emoji_arc::arc(&self.months)
+	}
+
+	pub fn finish(self) -> Vec<Item> {
+		self.months
+			.into_iter()
+			.map(|counts| {
+				top_items(counts, 1).pop().unwrap_or(Item { key: String::new(), count: 0 })
+			})
+			.collect()
+	}
+}
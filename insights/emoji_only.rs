@@ -0,0 +1,60 @@
+use imessage_database::tables::messages::Message;
+
+use super::words::is_emoji_only;
+use super::{is_tapback, Sources};
+use crate::stats::stats::{EmojiOnlyStats, PhraseStats};
+
+/// Counts messages made of nothing but emoji, the contact I trade them with
+/// most, and my longest back-and-forth of emoji-only messages with someone.
+pub fn emoji_only_stats(messages: &[Message], sources: &Sources) -> EmojiOnlyStats {
+	let mut sent = 0;
+	let mut received = 0;
+	for message in messages.iter().filter(|m| !is_tapback(m) && is_emoji_message(m)) {
+		if message.is_from_me {
+			sent += 1;
+		} else {
+			received += 1;
+		}
+	}
+
+	let mut top_contact: Option<(i32, i32)> = None;
+	let mut longest_streak: Option<(i32, i32)> = None;
+	for (&handle, conversation) in &super::conversations_by_contact(messages, sources) {
+		let count = conversation.iter().filter(|m| is_emoji_message(m)).count() as i32;
+		if count > 0 && top_contact.map_or(true, |(_, best)| count > best) {
+			top_contact = Some((handle, count));
+		}
+
+		// A streak only counts as an exchange once both of us are in it
+		let mut streak = 0;
+		let mut both_sides = false;
+		for (index, message) in conversation.iter().enumerate() {
+			if !is_emoji_message(message) {
+				streak = 0;
+				both_sides = false;
+				continue;
+			}
+			streak += 1;
+			both_sides |= streak > 1 && conversation[index - 1].is_from_me != message.is_from_me;
+			if both_sides && longest_streak.map_or(true, |(_, best)| streak > best) {
+				longest_streak = Some((handle, streak));
+			}
+		}
+	}
+
+	let to_stats = |(handle, count): (i32, i32)| {
+		let (name, handle_id) = sources.person(handle);
+		PhraseStats { name, handle_id, count, avatar: None }
+	};
+
+	EmojiOnlyStats {
+		sent,
+		received,
+		top_contact: top_contact.map(to_stats),
+		longest_streak: longest_streak.map(to_stats)
+	}
+}
+
+fn is_emoji_message(message: &Message) -> bool {
+	message.text.as_deref().is_some_and(is_emoji_only)
+}
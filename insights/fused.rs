@@ -0,0 +1,84 @@
+//! The fused engine: instead of every pass scanning the year on its own, one
+//! iteration over the messages feeds the accumulators of the passes in
+//! `PASSES`, the ones that look at each message once and keep counts. Passes
+//! that pair messages up or walk conversations, like reply times, sessions
+//! and topics, keep their own scans. Selected with `engine: "fused"`.
+
+use imessage_database::tables::messages::Message;
+
+use super::emoji_months::EmojiMonths;
+use super::group_split::GroupSplit;
+use super::reaction_speed::ReactionSpeeds;
+use super::shared_links::SharedLinks;
+use super::shared_with_you::SharedWithYou;
+use super::word_cloud::WordCounts;
+use super::Sources;
+use crate::stats::stats::YearStats;
+
+/// Passes the fused scan computes. The engine skips them in `PASSES`.
+pub const PASSES: &[&str] = &[
+	"emojiOfTheMonth",
+	"emojiArc",
+	"sharedLinks",
+	"groupDirectSplit",
+	"sharedWithYou",
+	"wordCloud",
+	"reactionSpeed"
+];
+
+/// Fills the fields of every fused pass with one iteration over `messages`,
+/// leaving out the passes in `not_applicable`.
+pub fn run(year: &mut YearStats, messages: &[Message], sources: &Sources, not_applicable: &[&str]) {
+	let wanted = |names: &[&str]| names.iter().any(|name| !not_applicable.contains(name));
+	let mut emoji_months = wanted(&["emojiOfTheMonth", "emojiArc"]).then(EmojiMonths::default);
+	let mut shared_links = wanted(&["sharedLinks"]).then(SharedLinks::default);
+	let mut group_split = wanted(&["groupDirectSplit"]).then(GroupSplit::default);
+	let mut shared_with_you = wanted(&["sharedWithYou"]).then(SharedWithYou::default);
+	let mut word_counts = wanted(&["wordCloud"]).then(WordCounts::default);
+	let mut reaction_speeds = wanted(&["reactionSpeed"]).then(|| ReactionSpeeds::new(sources));
+
+	for message in messages {
+		if let Some(emoji_months) = &mut emoji_months {
+			emoji_months.add(message);
+		}
+		if let Some(shared_links) = &mut shared_links {
+			shared_links.add(message, sources);
+		}
+		if let Some(group_split) = &mut group_split {
+			group_split.add(message, sources);
+		}
+		if let Some(shared_with_you) = &mut shared_with_you {
+			shared_with_you.add(message, sources);
+		}
+		if let Some(word_counts) = &mut word_counts {
+			word_counts.add(message);
+		}
+		if let Some(reaction_speeds) = &mut reaction_speeds {
+			reaction_speeds.add(message);
+		}
+	}
+
+	if let Some(emoji_months) = emoji_months {
+		if !not_applicable.contains(&"emojiArc") {
+			year.emoji_arc = Some(emoji_months.arc());
+		}
+		if !not_applicable.contains(&"emojiOfTheMonth") {
+			year.emoji_of_the_month = emoji_months.finish();
+		}
+	}
+	if let Some(shared_links) = shared_links {
+		year.top_shared_links = shared_links.finish(sources);
+	}
+	if let Some(group_split) = group_split {
+		year.group_direct_split = group_split.finish();
+	}
+	if let Some(shared_with_you) = shared_with_you {
+		year.shared_with_you = shared_with_you.finish(sources);
+	}
+	if let Some(word_counts) = word_counts {
+		year.word_cloud = Some(word_counts.finish(sources));
+	}
+	if let Some(reaction_speeds) = reaction_speeds {
+		year.reaction_speed = Some(reaction_speeds.finish(sources));
+	}
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::{associated_guid, is_added_tapback, is_tapback, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{GroupChatRanking, PhraseStats};
+
+#[derive(Default)]
+struct ChatTally {
+	total: i32,
+	/// Messages per sender, with 0 standing for me
+	by_sender: HashMap<i32, i32>,
+	/// Tapbacks received per member
+	reacted_to: HashMap<i32, i32>
+}
+
+/// My busiest group chats with my share of the messages, who carried each
+/// conversation, whose messages got the most tapbacks, and how much I lurk.
+pub fn group_chat_rankings(messages: &[Message], sources: &Sources) -> Vec<GroupChatRanking> {
+	let is_group = |chat_id: i32| sources.chats.get(chat_id).is_some_and(|chat| chat.is_group());
+	let sender = |message: &Message| {
+		if message.is_from_me { 0 } else { message.handle_id.unwrap_or(0) }
+	};
+
+	let mut tallies: HashMap<i32, ChatTally> = HashMap::new();
+	let mut senders: HashMap<&str, i32> = HashMap::new();
+	for message in messages.iter().filter(|m| !is_tapback(m)) {
+		let Some(chat_id) = message.chat_id.filter(|&id| is_group(id)) else { continue };
+		let tally = tallies.entry(chat_id).or_default();
+		tally.total += 1;
+		*tally.by_sender.entry(sender(message)).or_default() += 1;
+		senders.insert(message.guid.as_str(), sender(message));
+	}
+	for message in messages.iter().filter(|m| is_added_tapback(m)) {
+		let (Some(chat_id), Some(target)) = (message.chat_id, associated_guid(message)) else {
+			continue;
+		};
+		let Some(&author) = senders.get(target).filter(|&&author| author != 0) else { continue };
+		if let Some(tally) = tallies.get_mut(&chat_id) {
+			*tally.reacted_to.entry(author).or_default() += 1;
+		}
+	}
+
+	let mut chats: Vec<(i32, ChatTally)> = tallies.into_iter().collect();
+	chats.sort_by(|a, b| b.1.total.cmp(&a.1.total).then_with(|| a.0.cmp(&b.0)));
+
+	chats
+		.into_iter()
+		.take(sources.options.top(TopList::GroupChats))
+		.map(|(chat_id, tally)| {
+			let my_messages = tally.by_sender.get(&0).copied().unwrap_or(0);
+			let my_share = my_messages as f32 / tally.total.max(1) as f32;
+			let participants = sources.chats.get(chat_id).map_or(2, |chat| chat.members.len() + 1);
+			let fair_share = 1.0 / participants as f32;
+
+			let top_member = |counts: &HashMap<i32, i32>| {
+				counts
+					.iter()
+					.filter(|&(&handle, _)| handle != 0)
+					.max_by_key(|&(&handle, &count)| (count, -handle))
+					.map(|(&handle, &count)| {
+						let (name, handle_id) = sources.person(handle);
+						PhraseStats { name, handle_id, count, avatar: None }
+					})
+			};
+			let carrier = top_member(&tally.by_sender);
+
+			GroupChatRanking {
+				chat_id,
+				name: sources.chat_name(chat_id),
+				total_messages: tally.total,
+				my_messages,
+				my_share,
+				i_carried: carrier.as_ref().map_or(my_messages > 0, |c| my_messages > c.count),
+				carrier,
+				most_reacted_to: top_member(&tally.reacted_to),
+				lurker_score: (1.0 - my_share / fair_share).clamp(0.0, 1.0)
+			}
+		})
+		.collect()
+}
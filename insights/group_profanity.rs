@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::words::{WordList, PROFANITY};
+use super::{top_items, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{GroupChatProfanity, PhraseStats};
+
+#[derive(Default)]
+struct ChatTally {
+	messages: usize,
+	/// Profanity count per sender, with 0 standing for me
+	by_sender: HashMap<i32, i32>,
+	words: HashMap<String, i32>
+}
+
+/// Profanity leaderboard for my busiest group chats: who curses most, the
+/// group's favorite expletive, and where I rank.
+pub fn group_chat_profanity(messages: &[Message], sources: &Sources) -> Vec<GroupChatProfanity> {
+	let extra = sources.options.extra_profanity.clone().unwrap_or_default();
+	let profanity = WordList::new(PROFANITY, &extra);
+
+	let mut tallies: HashMap<i32, ChatTally> = HashMap::new();
+	for message in messages {
+		let Some(chat_id) = message.chat_id else { continue };
+		if !sources.chats.get(chat_id).is_some_and(|chat| chat.is_group()) {
+			continue;
+		}
+
+		let tally = tallies.entry(chat_id).or_default();
+		tally.messages += 1;
+
+		let Some(text) = message.text.as_deref() else { continue };
+		let sender = if message.is_from_me { 0 } else { message.handle_id.unwrap_or(0) };
+		for word in profanity.matches(text) {
+			*tally.by_sender.entry(sender).or_default() += 1;
+			*tally.words.entry(word).or_default() += 1;
+		}
+	}
+
+	let mut chats: Vec<(i32, ChatTally)> = tallies.into_iter().collect();
+	chats.sort_by(|a, b| b.1.messages.cmp(&a.1.messages).then_with(|| a.0.cmp(&b.0)));
+
+	chats
+		.into_iter()
+		.take(sources.options.top(TopList::GroupChats))
+		.filter(|(_, tally)| !tally.by_sender.is_empty())
+		.map(|(chat_id, tally)| {
+			let mut ranking: Vec<(i32, i32)> = tally.by_sender.into_iter().collect();
+			ranking.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+			let my_position = ranking.iter().position(|&(sender, _)| sender == 0);
+			let my_count = my_position.map_or(0, |i| ranking[i].1);
+
+			let members = ranking
+				.iter()
+				.filter(|&&(sender, _)| sender != 0)
+				.take(sources.options.top(TopList::GroupMembers))
+				.map(|&(handle, count)| {
+					let (name, handle_id) = sources.person(handle);
+					PhraseStats { name, handle_id, count, avatar: None }
+				})
+				.collect();
+
+			GroupChatProfanity {
+				chat_id,
+				name: sources.chat_name(chat_id),
+				members,
+				favorite_expletive: top_items(tally.words, 1).pop(),
+				my_count,
+				my_rank: my_position.map(|i| i as i32 + 1)
+			}
+		})
+		.collect()
+}
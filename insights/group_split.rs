@@ -0,0 +1,73 @@
+use chrono::Datelike;
+use imessage_database::tables::messages::Message;
+
+use super::{is_tapback, local_time, Sources};
+use crate::stats::stats::{GroupDirectSplit, MessageCount};
+
+/// How my messaging divides between group chats and one-on-one chats, with
+/// the group share for each month. `group_dependence` is the share of the
+/// messages I sent that went to group chats.
+pub fn group_direct_split(messages: &[Message], sources: &Sources) -> Option<GroupDirectSplit> {
+	let mut split = GroupSplit::default();
+	for message in messages {
+		split.add(message, sources);
+	}
+	split.finish()
+}
+
+/// Group and one-on-one counts, filled one message at a time so the fused
+/// engine can share its scan.
+pub struct GroupSplit {
+	group: MessageCount,
+	direct: MessageCount,
+	/// Group messages and all messages, by month
+	monthly: [(i32, i32); 12]
+}
+
+impl Default for GroupSplit {
+	fn default() -> Self {
+		Self {
+			group: MessageCount { sent: 0, received: 0 },
+			direct: MessageCount { sent: 0, received: 0 },
+			monthly: [(0, 0); 12]
+		}
+	}
+}
+
+impl GroupSplit {
+	pub fn add(&mut self, message: &Message, sources: &Sources) {
+		if is_tapback(message) {
+			return;
+		}
+		let Some(chat) = message.chat_id.and_then(|id| sources.chats.get(id)) else { return };
+		let is_group = chat.is_group();
+		let counts = if is_group { &mut self.group } else { &mut self.direct };
+		if message.is_from_me {
+			counts.sent += 1;
+		} else {
+			counts.received += 1;
+		}
+		if let Some(month) = local_time(message.date).map(|t| t.month0() as usize) {
+			self.monthly[month].0 += is_group as i32;
+			self.monthly[month].1 += 1;
+		}
+	}
+
+	pub fn finish(self) -> Option<GroupDirectSplit> {
+		let Self { group, direct, monthly } = self;
+		let sent = group.sent + direct.sent;
+		if sent + group.received + direct.received == 0 {
+			return None;
+		}
+
+		Some(GroupDirectSplit {
+			monthly_group_share: monthly
+				.iter()
+				.map(|&(group, total)| if total == 0 { 0.0 } else { group as f64 / total as f64 })
+				.collect(),
+			group_dependence: if sent == 0 { 0.0 } else { group.sent as f64 / sent as f64 },
+			group,
+			direct
+		})
+	}
+}
@@ -0,0 +1,158 @@
+//! A wrapped for one group chat instead of for me: who talked the most, the
+//! chat's emoji, its busiest day and its running jokes. It is shared as its
+//! own payload so everyone in the chat can open it.
+
+use std::collections::{HashMap, HashSet};
+
+use imessage_database::tables::messages::Message;
+
+use super::words::{emojis, is_stop_word, words};
+use super::{is_tapback, local_time, top_items, year_messages, Sources};
+use crate::options::{PrivacyLevel, TopList};
+use crate::stats::stats::{GroupWrapped, Item, PhraseStats};
+
+/// Times a phrase has to come up before it counts as a running joke
+const MIN_JOKE_COUNT: i32 = 3;
+/// How many times more often per message the chat uses a phrase than my
+/// other chats do
+const JOKE_AFFINITY: f64 = 5.0;
+/// Sender id standing for me
+const ME: i32 = 0;
+
+/// The wrapped of group chat `chat_id` for `year`. `None` when the chat isn't
+/// a group chat or had no messages that year.
+pub fn group_wrapped(sources: &Sources, chat_id: i32, year: i32) -> Option<GroupWrapped> {
+	let chat = sources.chats.get(chat_id).filter(|chat| chat.is_group())?;
+	let (in_chat, elsewhere): (Vec<&Message>, Vec<&Message>) = year_messages(sources.messages, year)
+		.iter()
+		.filter(|m| !is_tapback(m))
+		.partition(|m| m.chat_id == Some(chat_id));
+	if in_chat.is_empty() {
+		return None;
+	}
+
+	let mut by_sender: HashMap<i32, i32> = HashMap::new();
+	let mut emoji_counts: HashMap<String, i32> = HashMap::new();
+	let mut days: HashMap<String, i32> = HashMap::new();
+	for &message in &in_chat {
+		*by_sender.entry(sender(message)).or_default() += 1;
+		for emoji in message.text.as_deref().into_iter().flat_map(emojis) {
+			*emoji_counts.entry(emoji.to_string()).or_default() += 1;
+		}
+		if let Some(time) = local_time(message.date) {
+			*days.entry(time.format("%Y-%m-%d").to_string()).or_default() += 1;
+		}
+	}
+
+	let mut leaderboard: Vec<PhraseStats> = by_sender
+		.into_iter()
+		.map(|(handle, count)| {
+			let (name, handle_id) = if handle == ME { me(sources) } else { sources.person(handle) };
+			PhraseStats { name, handle_id, count, avatar: None }
+		})
+		.collect();
+	leaderboard.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+
+	// Earliest day on ties
+	let busiest_day = days
+		.into_iter()
+		.max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+		.map(|(key, count)| Item { key, count });
+
+	let inside_jokes = if sources.options.privacy_level() == PrivacyLevel::Strict {
+		Vec::new()
+	} else {
+		inside_jokes(&in_chat, &elsewhere, sources.options.top(TopList::GroupWrappedItems))
+	};
+
+	Some(GroupWrapped {
+		year,
+		name: sources.chat_name(chat_id),
+		member_count: chat.members.len() as i32 + 1,
+		total_messages: in_chat.len() as i32,
+		leaderboard,
+		top_emojis: top_items(emoji_counts, sources.options.top(TopList::GroupWrappedItems)),
+		busiest_day,
+		inside_jokes,
+		build: None
+	})
+}
+
+/// Up to `limit` phrases at least two members use in the chat, much more
+/// often than they come up in my other chats. A phrase inside one already
+/// picked, or the other way around, is the same joke and left out.
+fn inside_jokes(in_chat: &[&Message], elsewhere: &[&Message], limit: usize) -> Vec<Item> {
+	let mut counts: HashMap<String, i32> = HashMap::new();
+	let mut speakers: HashMap<String, HashSet<i32>> = HashMap::new();
+	for &message in in_chat {
+		for phrase in phrases(message) {
+			*counts.entry(phrase.clone()).or_default() += 1;
+			speakers.entry(phrase).or_default().insert(sender(message));
+		}
+	}
+
+	let mut outside: HashMap<String, i32> = HashMap::new();
+	for &message in elsewhere {
+		for phrase in phrases(message).into_iter().filter(|phrase| counts.contains_key(phrase)) {
+			*outside.entry(phrase).or_default() += 1;
+		}
+	}
+
+	let chat_rate = |count: i32| count as f64 / in_chat.len() as f64;
+	let other_rate = |phrase: &str| {
+		outside.get(phrase).copied().unwrap_or(0) as f64 / elsewhere.len().max(1) as f64
+	};
+	let mut candidates: Vec<Item> = counts
+		.into_iter()
+		.filter(|(phrase, count)| {
+			*count >= MIN_JOKE_COUNT &&
+				speakers[phrase].len() > 1 &&
+				chat_rate(*count) >= JOKE_AFFINITY * other_rate(phrase)
+		})
+		.map(|(key, count)| Item { key, count })
+		.collect();
+	candidates.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| b.key.len().cmp(&a.key.len())));
+
+	let mut jokes: Vec<Item> = Vec::new();
+	for candidate in candidates {
+		let overlaps = jokes
+			.iter()
+			.any(|joke| joke.key.contains(&candidate.key) || candidate.key.contains(&joke.key));
+		if !overlaps {
+			jokes.push(candidate);
+		}
+		if jokes.len() == limit {
+			break;
+		}
+	}
+	jokes
+}
+
+/// Two and three word phrases in the message, each once, leaving out those
+/// made only of stop words.
+fn phrases(message: &Message) -> HashSet<String> {
+	let tokens: Vec<String> =
+		message.text.as_deref().map(|text| words(text).collect()).unwrap_or_default();
+	(2..=3)
+		.flat_map(|length| tokens.windows(length))
+		.filter(|window| !window.iter().all(|word| is_stop_word(word)))
+		.map(|window| window.join(" "))
+		.collect()
+}
+
+fn sender(message: &Message) -> i32 {
+	if message.is_from_me { ME } else { message.handle_id.unwrap_or(ME) }
+}
+
+/// My name as the AddressBook has it for my first handle, "Me" otherwise.
+fn me(sources: &Sources) -> (String, String) {
+	let handle_id = sources
+		.options
+		.my_handles
+		.as_ref()
+		.and_then(|handles| handles.first())
+		.cloned()
+		.unwrap_or_default();
+	let name = sources.contacts.get_name(&handle_id).unwrap_or_else(|| String::from("Me"));
+	(name, handle_id)
+}
@@ -0,0 +1,60 @@
+use imessage_database::tables::messages::Message;
+
+use super::{apple_seconds, top_contacts, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{ConversationHalfLife, HalfLifeStats};
+
+/// Quiet that ends an exchange
+const QUIET_SECONDS: i64 = 60 * 60;
+/// Exchanges needed before a contact can be the slow burn or flash flood
+const MIN_EXCHANGES: i32 = 5;
+
+/// For each top one-on-one conversation, splits the year into exchanges at
+/// every hour of quiet and takes the median time from an exchange's first
+/// message to its last. Exchanges both sides didn't take part in never got
+/// going and aren't counted.
+pub fn half_life_stats(messages: &[Message], sources: &Sources) -> HalfLifeStats {
+	let conversations = super::conversations_by_contact(messages, sources);
+
+	let limit = sources.options.top(TopList::Contacts);
+	let contacts: Vec<ConversationHalfLife> = top_contacts(&conversations, limit)
+		.into_iter()
+		.filter_map(|handle| {
+			let mut durations = Vec::new();
+			let mut lengths = Vec::new();
+			let conversation = &conversations[&handle];
+			let same_exchange = |a: &&Message, b: &&Message| {
+				apple_seconds(b.date) - apple_seconds(a.date) < QUIET_SECONDS
+			};
+			for exchange in conversation.chunk_by(same_exchange) {
+				if exchange.iter().all(|m| m.is_from_me == exchange[0].is_from_me) {
+					continue;
+				}
+				let (first, last) = (exchange[0], exchange[exchange.len() - 1]);
+				durations.push(apple_seconds(last.date) - apple_seconds(first.date));
+				lengths.push(exchange.len() as i32);
+			}
+			if durations.is_empty() {
+				return None;
+			}
+			durations.sort_unstable();
+			lengths.sort_unstable();
+
+			let (name, handle_id) = sources.person(handle);
+			Some(ConversationHalfLife {
+				name,
+				handle_id,
+				exchanges: durations.len() as i32,
+				half_life_seconds: durations[durations.len() / 2],
+				median_messages: lengths[lengths.len() / 2],
+				avatar: None
+			})
+		})
+		.collect();
+
+	let ranked = || contacts.iter().filter(|c| c.exchanges >= MIN_EXCHANGES);
+	let slow_burn = ranked().max_by_key(|c| c.half_life_seconds).cloned();
+	let flash_flood = ranked().min_by_key(|c| c.half_life_seconds).cloned();
+
+	HalfLifeStats { contacts, slow_burn, flash_flood }
+}
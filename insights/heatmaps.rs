@@ -0,0 +1,39 @@
+use chrono::Timelike;
+use imessage_database::tables::messages::Message;
+
+use super::{local_time, top_contacts, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{ContactHeatmap, MessageCount};
+
+/// Sent/received counts by hour of day for each top one-on-one contact, plus
+/// the hour we text the most.
+pub fn contact_hourly(messages: &[Message], sources: &Sources) -> Vec<ContactHeatmap> {
+	let conversations = super::conversations_by_contact(messages, sources);
+
+	top_contacts(&conversations, sources.options.top(TopList::Contacts))
+		.into_iter()
+		.map(|handle| {
+			let mut hourly = vec![MessageCount { sent: 0, received: 0 }; 24];
+			for message in &conversations[&handle] {
+				let Some(hour) = local_time(message.date).map(|t| t.hour() as usize) else {
+					continue;
+				};
+				if message.is_from_me {
+					hourly[hour].sent += 1;
+				} else {
+					hourly[hour].received += 1;
+				}
+			}
+
+			let peak_hour = (0..24).max_by_key(|&hour| hourly[hour].sent + hourly[hour].received);
+			let (name, handle_id) = sources.person(handle);
+			ContactHeatmap {
+				name,
+				handle_id,
+				hourly,
+				peak_hour: peak_hour.unwrap_or(0) as i32,
+				avatar: None
+			}
+		})
+		.collect()
+}
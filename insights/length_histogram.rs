@@ -0,0 +1,54 @@
+use imessage_database::tables::messages::Message;
+
+use super::words::words;
+use super::{is_tapback, top_contacts, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{ContactLengthHistogram, LengthBucket, LengthHistogramStats};
+
+/// Lower word count of each bucket: quick replies, regular texts and essays.
+/// Each bucket ends where the next one starts.
+const BUCKETS: [i32; 3] = [1, 6, 21];
+
+/// How long the messages I send are, bucketed by word count, overall and
+/// towards each top contact in one-on-one chats. Messages without words, such
+/// as bare attachments or emoji, aren't counted.
+pub fn length_histogram(messages: &[Message], sources: &Sources) -> LengthHistogramStats {
+	let overall = histogram(messages.iter().filter(|m| m.is_from_me && !is_tapback(m)));
+
+	let conversations = super::conversations_by_contact(messages, sources);
+	let contacts = top_contacts(&conversations, sources.options.top(TopList::Contacts))
+		.into_iter()
+		.filter_map(|handle| {
+			let sent = conversations[&handle].iter().copied().filter(|m| m.is_from_me);
+			let buckets = histogram(sent);
+			if buckets.iter().all(|bucket| bucket.count == 0) {
+				return None;
+			}
+			let (name, handle_id) = sources.person(handle);
+			Some(ContactLengthHistogram { name, handle_id, buckets, avatar: None })
+		})
+		.collect();
+
+	LengthHistogramStats { overall, contacts }
+}
+
+fn histogram<'a>(messages: impl Iterator<Item = &'a Message>) -> Vec<LengthBucket> {
+	let mut buckets: Vec<LengthBucket> = BUCKETS
+		.iter()
+		.enumerate()
+		.map(|(index, &min_words)| LengthBucket {
+			min_words,
+			max_words: BUCKETS.get(index + 1).map(|next| next - 1),
+			count: 0
+		})
+		.collect();
+
+	for message in messages {
+		let Some(text) = message.text.as_deref() else { continue };
+		let count = words(text).count() as i32;
+		if let Some(bucket) = buckets.iter_mut().rev().find(|bucket| count >= bucket.min_words) {
+			bucket.count += 1;
+		}
+	}
+	buckets
+}
@@ -0,0 +1,48 @@
+use imessage_database::tables::messages::Message;
+
+use super::words::words;
+use super::{direct_chats, is_tapback, unix_seconds, Sources};
+use crate::options::PrivacyLevel;
+use crate::stats::stats::{LongestMessages, MessageSuperlative};
+
+/// The longest message I sent and the longest I received, by characters.
+/// The text itself is only included at the permissive privacy level.
+pub fn longest_messages(messages: &[Message], sources: &Sources) -> LongestMessages {
+	let include_text = sources.options.privacy_level() == PrivacyLevel::Permissive;
+	let direct_chats = direct_chats(messages, sources);
+
+	let longest = |from_me: bool| {
+		messages
+			.iter()
+			.filter(|m| m.is_from_me == from_me && !is_tapback(m))
+			.filter_map(|m| Some((m, m.text.as_deref()?.chars().count())))
+			.max_by_key(|&(m, length)| (length, -m.rowid))
+			.map(|(message, character_count)| {
+				let text = message.text.as_deref().unwrap_or_default();
+
+				// Received messages are credited to the sender, sent ones to
+				// the person or group chat they went to
+				let (name, handle_id) = match (from_me, message.chat_id) {
+					(false, _) => sources.person(message.handle_id.unwrap_or(0)),
+					(true, Some(chat_id)) => match direct_chats.get(&chat_id) {
+						Some(&handle) => sources.person(handle),
+						None => (sources.chat_name(chat_id), String::new())
+					},
+					(true, None) => (String::new(), String::new())
+				};
+
+				MessageSuperlative {
+					name,
+					handle_id,
+					character_count: character_count as i32,
+					word_count: words(text).count() as i32,
+					date: unix_seconds(message.date),
+					text: include_text.then(|| text.to_string()),
+					chat_id: message.chat_id,
+					avatar: None
+				}
+			})
+	};
+
+	LongestMessages { sent: longest(true), received: longest(false) }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::{direct_chats, Sources};
+use crate::attachments::Attachment;
+use crate::stats::stats::{AttachmentStats, MediaPartner, MessageCount};
+
+const BYTES_PER_MEGABYTE: f64 = 1_000_000.0;
+
+/// Photos, videos, voice memos and GIFs sent and received, megabytes of
+/// attachments exchanged, and the person I swap the most media with in
+/// one-on-one chats. `None` when the year has no attachments.
+pub fn attachment_stats(messages: &[Message], sources: &Sources) -> Option<AttachmentStats> {
+	let direct_chats = direct_chats(messages, sources);
+	let count = || MessageCount { sent: 0, received: 0 };
+	let (mut photos, mut videos, mut voice_memos, mut gifs) = (count(), count(), count(), count());
+	let (mut bytes_sent, mut bytes_received) = (0i64, 0i64);
+	let mut partners: HashMap<i32, (i32, i32)> = HashMap::new();
+	let mut any = false;
+
+	for message in messages.iter().filter(|m| m.num_attachments > 0) {
+		let attachments = sources.attachments.for_message(message.rowid);
+		if attachments.is_empty() {
+			continue;
+		}
+		any = true;
+
+		for attachment in attachments {
+			let bucket = if attachment.is_gif() {
+				Some(&mut gifs)
+			} else if attachment.is_photo() {
+				Some(&mut photos)
+			} else if attachment.is_video() {
+				Some(&mut videos)
+			} else if attachment.is_voice_memo() {
+				Some(&mut voice_memos)
+			} else {
+				None
+			};
+			if let Some(bucket) = bucket {
+				if message.is_from_me { bucket.sent += 1 } else { bucket.received += 1 }
+			}
+			if message.is_from_me {
+				bytes_sent += attachment.total_bytes;
+			} else {
+				bytes_received += attachment.total_bytes;
+			}
+		}
+
+		let media = attachments.iter().filter(|a| is_media(a)).count() as i32;
+		if let Some(&handle) = message.chat_id.and_then(|id| direct_chats.get(&id)) {
+			let entry = partners.entry(handle).or_default();
+			if message.is_from_me { entry.0 += media } else { entry.1 += media }
+		}
+	}
+	if !any {
+		return None;
+	}
+
+	let top_media_partner = partners
+		.into_iter()
+		.filter(|(_, (sent, received))| sent + received > 0)
+		.max_by_key(|&(handle, (sent, received))| (sent + received, -handle))
+		.map(|(handle, (sent, received))| {
+			let (name, handle_id) = sources.person(handle);
+			MediaPartner { name, handle_id, sent, received, avatar: None }
+		});
+
+	Some(AttachmentStats {
+		photos: Some(photos),
+		videos: Some(videos),
+		voice_memos: Some(voice_memos),
+		gifs: Some(gifs),
+		megabytes_sent: (bytes_sent as f64 / BYTES_PER_MEGABYTE) as f32,
+		megabytes_received: (bytes_received as f64 / BYTES_PER_MEGABYTE) as f32,
+		top_media_partner
+	})
+}
+
+fn is_media(attachment: &Attachment) -> bool {
+	attachment.is_photo() ||
+		attachment.is_gif() ||
+		attachment.is_video() ||
+		attachment.is_voice_memo()
+}
@@ -0,0 +1,457 @@
+//! Per-year stats computed after `stats::get_all_yearly_stats`, filled into
+//! the optional fields of each `YearStats`.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Datelike, Local, TimeZone};
+use imessage_database::tables::messages::Message;
+use prost::Message as ProstMessage;
+use rayon::prelude::*;
+
+use crate::attachments::Attachments;
+use crate::chats::Chats;
+use crate::contact_details::ContactDetails;
+use crate::contacts::Contacts;
+use crate::handles::Handles;
+use crate::link_previews::LinkPreviews;
+use crate::low_impact;
+use crate::options::FetchOptions;
+use crate::progress::Reporter;
+use crate::stats::stats::{Item, YearStats, YearsStats};
+use crate::syndication::Syndicated;
+
+mod archetype;
+mod bursts;
+mod chronotype;
+mod contact_cards;
+mod dictation;
+mod dryness;
+mod emoji_arc;
+mod emoji_months;
+mod emoji_only;
+mod fused;
+mod group_chats;
+mod group_profanity;
+mod group_split;
+mod group_wrapped;
+mod half_life;
+mod heatmaps;
+mod length_histogram;
+mod longest_messages;
+mod media;
+mod nicknames;
+mod photo_dumps;
+mod preflight;
+mod questions;
+mod ratio_trend;
+mod reaction_balance;
+mod reaction_speed;
+mod relationships;
+mod response_times;
+mod revivals;
+mod robots;
+mod sentiment;
+mod shared_links;
+mod shared_with_you;
+mod short_replies;
+mod silences;
+mod social_graph;
+mod standout_messages;
+mod system_counts;
+mod topics;
+mod word_balance;
+mod word_cloud;
+pub mod words;
+mod year_over_year;
+
+pub use group_wrapped::group_wrapped;
+
+/// Seconds between the Unix epoch and the Apple epoch (2001-01-01 UTC)
+pub const APPLE_EPOCH_OFFSET: i64 = 978_307_200;
+
+/// Everything loaded from chat.db and the AddressBook that insights can use.
+pub struct Sources<'a> {
+	pub messages: &'a [Message],
+	/// Messages from automated senders, left out of `messages`
+	pub automated: &'a [Message],
+	/// Group events and other system messages, left out of `messages`
+	pub system: &'a [Message],
+	pub contacts: &'a Contacts,
+	pub contact_details: &'a ContactDetails,
+	pub handles: &'a Handles,
+	pub attachments: &'a Attachments,
+	pub chats: &'a Chats,
+	pub link_previews: &'a LinkPreviews,
+	pub syndicated: &'a Syndicated,
+	pub options: &'a FetchOptions
+}
+
+impl Sources<'_> {
+	/// Resolves a handle ROWID to the display name and raw handle id.
+	pub fn person(&self, handle_rowid: i32) -> (String, String) {
+		let handle_id = self.handles.get(handle_rowid).cloned().unwrap_or_default();
+		let name = self.contacts.get_name(&handle_id).unwrap_or_else(|| handle_id.clone());
+		(name, handle_id)
+	}
+
+	/// The chat's display name, falling back to its members' names.
+	pub fn chat_name(&self, chat_id: i32) -> String {
+		let Some(chat) = self.chats.get(chat_id) else {
+			return String::new();
+		};
+		if let Some(name) = &chat.display_name {
+			return name.clone();
+		}
+		if chat.members.is_empty() {
+			return chat.chat_identifier.clone();
+		}
+		chat.members
+			.iter()
+			.map(|&handle| self.person(handle).0)
+			.collect::<Vec<_>>()
+			.join(", ")
+	}
+}
+
+type Pass = fn(&mut YearStats, &[Message], &Sources);
+
+/// Insight passes in priority order. When the time budget runs out the
+/// remaining passes are skipped.
+const PASSES: &[(&str, Pass)] = &[
+	("responseTimeLeaderboard", |year, messages, sources| {
+		year.response_time_leaderboard =
+			Some(response_times::response_time_leaderboard(messages, sources))
+	}),
+	("reactionBalance", |year, messages, sources| {
+		year.reaction_balance = Some(reaction_balance::reaction_balance(messages, sources))
+	}),
+	("reactionSpeed", |year, messages, sources| {
+		year.reaction_speed = Some(reaction_speed::reaction_speed(messages, sources))
+	}),
+	("longestMessages", |year, messages, sources| {
+		year.longest_messages = Some(longest_messages::longest_messages(messages, sources))
+	}),
+	("sendReceivedTrend", |year, messages, sources| {
+		year.send_received_trend = Some(ratio_trend::send_received_trend(messages, sources))
+	}),
+	("standoutMessages", |year, messages, sources| {
+		year.standout_messages = Some(standout_messages::standout_messages(messages, sources))
+	}),
+	("shortReplies", |year, messages, sources| {
+		year.short_replies = Some(short_replies::short_reply_stats(messages, sources))
+	}),
+	("bursts", |year, messages, sources| {
+		year.bursts = Some(bursts::burst_stats(messages, sources))
+	}),
+	("silenceBreaks", |year, messages, sources| {
+		year.silence_breaks = Some(silences::silence_stats(messages, sources))
+	}),
+	("halfLife", |year, messages, sources| {
+		year.half_life = Some(half_life::half_life_stats(messages, sources))
+	}),
+	("contactHourly", |year, messages, sources| {
+		year.contact_hourly = heatmaps::contact_hourly(messages, sources)
+	}),
+	("socialGraph", |year, messages, sources| {
+		year.social_graph = Some(social_graph::social_graph_stats(messages, sources))
+	}),
+	("emojiOnly", |year, messages, sources| {
+		year.emoji_only = Some(emoji_only::emoji_only_stats(messages, sources))
+	}),
+	("questionLatency", |year, messages, sources| {
+		year.question_latency = Some(questions::question_stats(messages, sources))
+	}),
+	("revivals", |year, messages, sources| {
+		year.revivals = Some(revivals::revival_stats(messages, sources))
+	}),
+	("emojiOfTheMonth", |year, messages, _| {
+		year.emoji_of_the_month = emoji_months::emoji_of_the_month(messages)
+	}),
+	("emojiArc", |year, messages, _| year.emoji_arc = Some(emoji_arc::emoji_arc(messages))),
+	("nicknames", |year, messages, sources| {
+		year.nicknames = nicknames::nickname_usage(messages, sources)
+	}),
+	("photoDumps", |year, messages, sources| {
+		year.photo_dumps = Some(photo_dumps::photo_dump_stats(messages, sources))
+	}),
+	("sharedLinks", |year, messages, sources| {
+		year.top_shared_links = shared_links::top_shared_links(messages, sources)
+	}),
+	("wordBalance", |year, messages, sources| {
+		year.word_balance = Some(word_balance::word_balance(messages, sources))
+	}),
+	("wordCloud", |year, messages, sources| {
+		year.word_cloud = Some(word_cloud::word_cloud(messages, sources))
+	}),
+	("sentiment", |year, messages, sources| {
+		year.sentiment = Some(sentiment::sentiment_stats(messages, sources))
+	}),
+	("lengthHistogram", |year, messages, sources| {
+		year.length_histogram = Some(length_histogram::length_histogram(messages, sources))
+	}),
+	("groupDirectSplit", |year, messages, sources| {
+		year.group_direct_split = group_split::group_direct_split(messages, sources)
+	}),
+	("relationships", |year, messages, sources| {
+		year.relationships = relationships::relationships(messages, sources)
+	}),
+	("sharedWithYou", |year, messages, sources| {
+		year.shared_with_you = shared_with_you::shared_with_you_stats(messages, sources)
+	}),
+	("robots", |year, _, sources| {
+		year.robots = robots::robot_stats(year_messages(sources.automated, year.year), sources)
+	}),
+	("systemMessages", |year, _, sources| {
+		year.system_messages =
+			system_counts::system_message_counts(year_messages(sources.system, year.year))
+	}),
+	("groupChats", |year, messages, sources| {
+		year.group_chats = group_chats::group_chat_rankings(messages, sources)
+	}),
+	("attachments", |year, messages, sources| {
+		year.attachments = media::attachment_stats(messages, sources)
+	}),
+	("contactCards", |year, messages, sources| {
+		year.contact_cards = Some(contact_cards::contact_card_stats(messages, sources))
+	}),
+	("chronotype", |year, messages, sources| {
+		year.chronotype = chronotype::chronotype_stats(messages, sources)
+	}),
+	("archetype", |year, messages, sources| {
+		year.archetype = archetype::texting_archetype(messages, sources)
+	}),
+	("groupChatProfanity", |year, messages, sources| {
+		year.group_chat_profanity = group_profanity::group_chat_profanity(messages, sources)
+	}),
+	("dictation", |year, messages, sources| {
+		if sources.options.dictation_stats.unwrap_or(false) {
+			year.dictation = dictation::dictation_stats(messages)
+		}
+	}),
+	("dryness", |year, messages, sources| {
+		year.dryness = Some(dryness::dryness_stats(messages, sources))
+	}),
+	("topics", |year, messages, sources| year.topics = topics::contact_topics(messages, sources))
+];
+
+/// Runs every insight pass over each year, then compares each year with the
+/// one before. Passes run in parallel on a rayon pool (one at a time in
+/// low-impact mode), each filling a scratch `YearStats` that is merged back in
+/// pass order. A preflight scan first records the passes that can't apply to
+/// the year in `not_applicable`, and those aren't run. With the fused engine,
+/// passes that only need one forward scan share a single iteration over the
+/// year. Passes that would start after `deadline` are recorded in
+/// `skipped_stats` instead, so the wrapped is still valid when the budget runs
+/// out, and so are passes whose results can't be merged. Returns how long
+/// each pass took, summed over the years.
+pub fn apply(
+	stats: &mut YearsStats, sources: &Sources, deadline: Option<Instant>, progress: &Reporter
+) -> Vec<(&'static str, Duration)> {
+	let fused = sources.options.fused_engine();
+	let passes: Vec<&(&'static str, Pass)> =
+		PASSES.iter().filter(|(name, _)| !(fused && fused::PASSES.contains(name))).collect();
+	let year_ranges: Vec<&[Message]> =
+		stats.stats.iter().map(|s| year_messages(sources.messages, s.year)).collect();
+	let total = ((passes.len() + usize::from(fused)) * stats.stats.len()).max(1);
+	let finished = AtomicUsize::new(0);
+	let mut timings: Vec<(&'static str, Duration)> =
+		passes.iter().map(|&&(name, _)| (name, Duration::ZERO)).collect();
+	let mut fused_timing = Duration::ZERO;
+
+	for (year_stats, messages) in stats.stats.iter_mut().zip(&year_ranges) {
+		let year = year_stats.year;
+		let not_applicable = preflight::not_applicable(messages, sources);
+		year_stats.not_applicable = not_applicable.iter().map(|name| name.to_string()).collect();
+		if fused {
+			if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+				year_stats.skipped_stats.extend(fused::PASSES.iter().map(|name| name.to_string()));
+			} else {
+				let start = Instant::now();
+				fused::run(year_stats, messages, sources, &not_applicable);
+				fused_timing += start.elapsed();
+			}
+			let done = finished.fetch_add(1, Ordering::Relaxed) + 1;
+			let percent = 70.0 + 25.0 * done as f64 / total as f64;
+			progress.report_detail("computingInsights", percent, "fused");
+		}
+
+		let run = |&&(name, pass): &&(&'static str, Pass)| {
+			if not_applicable.contains(&name) {
+				return Some((YearStats { year, ..Default::default() }, Duration::ZERO));
+			}
+			if sources.options.low_impact() {
+				low_impact::pause();
+			}
+			if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+				return None;
+			}
+
+			let start = Instant::now();
+			let mut scratch = YearStats { year, ..Default::default() };
+			pass(&mut scratch, messages, sources);
+			let done = finished.fetch_add(1, Ordering::Relaxed) + 1;
+			let percent = 70.0 + 25.0 * done as f64 / total as f64;
+			progress.report_detail("computingInsights", percent, name);
+			Some((scratch, start.elapsed()))
+		};
+		let results: Vec<Option<(YearStats, Duration)>> = if sources.options.low_impact() {
+			passes.iter().map(run).collect()
+		} else {
+			passes.par_iter().map(run).collect()
+		};
+
+		for ((name, timing), result) in timings.iter_mut().zip(results) {
+			let Some((scratch, elapsed)) = result else {
+				year_stats.skipped_stats.push(name.to_string());
+				continue;
+			};
+			*timing += elapsed;
+			// Only the fields the pass set are encoded, so merging leaves the
+			// rest of the year untouched. A pass that fails to merge is left
+			// out like one the budget skipped.
+			if year_stats.merge(scratch.encode_to_vec().as_slice()).is_err() {
+				year_stats.skipped_stats.push(name.to_string());
+			}
+		}
+	}
+	year_over_year::apply(stats, sources.options);
+
+	if fused {
+		timings.insert(0, ("fused", fused_timing));
+	}
+	timings
+}
+
+/// Fills `year_over_year` again, e.g. after cached years were added back.
+pub fn compare_years(stats: &mut YearsStats, options: &FetchOptions) {
+	year_over_year::apply(stats, options);
+}
+
+/// Converts a chat.db date to seconds since the Apple epoch. Modern databases
+/// store nanoseconds, older ones seconds.
+pub fn apple_seconds(date: i64) -> i64 {
+	if date > 1_000_000_000_000 {
+		date / 1_000_000_000
+	} else {
+		date
+	}
+}
+
+pub fn unix_seconds(date: i64) -> i64 {
+	apple_seconds(date) + APPLE_EPOCH_OFFSET
+}
+
+pub fn local_time(date: i64) -> Option<DateTime<Local>> {
+	Local.timestamp_opt(unix_seconds(date), 0).single()
+}
+
+/// Returns the messages sent in `year`. Relies on messages being sorted by date.
+pub fn year_messages(messages: &[Message], year: i32) -> &[Message] {
+	let year_of = |m: &Message| local_time(m.date).map_or(i32::MIN, |t| t.year());
+	let start = messages.partition_point(|m| year_of(m) < year);
+	let end = messages.partition_point(|m| year_of(m) <= year);
+	&messages[start..end]
+}
+
+/// Whether the message is a tapback (added or removed) rather than a message
+/// someone typed.
+pub fn is_tapback(message: &Message) -> bool {
+	matches!(message.associated_message_type, Some(2000..=2007 | 3000..=3007))
+}
+
+/// Whether the message adds a tapback, as opposed to removing one.
+pub fn is_added_tapback(message: &Message) -> bool {
+	matches!(message.associated_message_type, Some(2000..=2007))
+}
+
+/// GUID of the message a tapback or reply points at. chat.db prefixes it
+/// with the part index (`p:0/GUID`) or `bp:` for balloon messages.
+pub fn associated_guid(message: &Message) -> Option<&str> {
+	let guid = message.associated_message_guid.as_deref()?;
+	Some(match guid.split_once('/') {
+		Some((_, guid)) => guid,
+		None => guid.strip_prefix("bp:").unwrap_or(guid)
+	})
+}
+
+/// Groups messages by chat, in date order. Tapbacks are left out.
+fn messages_by_chat(messages: &[Message]) -> HashMap<i32, Vec<&Message>> {
+	let mut chats: HashMap<i32, Vec<&Message>> = HashMap::new();
+	for message in messages.iter().filter(|m| !is_tapback(m)) {
+		if let Some(chat_id) = message.chat_id {
+			chats.entry(chat_id).or_default().push(message);
+		}
+	}
+	chats
+}
+
+/// Groups the messages of one-on-one conversations by the other person's
+/// handle ROWID. Tapbacks are left out.
+fn conversations_by_contact<'a>(
+	messages: &'a [Message], sources: &Sources
+) -> HashMap<i32, Vec<&'a Message>> {
+	let direct_chats = direct_chats(messages, sources);
+
+	let mut conversations: HashMap<i32, Vec<&Message>> = HashMap::new();
+	for message in messages.iter().filter(|m| !is_tapback(m)) {
+		if let Some(handle) = message.chat_id.and_then(|chat_id| direct_chats.get(&chat_id)) {
+			conversations.entry(*handle).or_default().push(message);
+		}
+	}
+
+	conversations
+}
+
+/// Maps the chat id of every one-on-one chat in `messages` to the other
+/// person's handle, going by the chat's members. A group where only one
+/// person has spoken is still a group.
+fn direct_chats(messages: &[Message], sources: &Sources) -> HashMap<i32, i32> {
+	let chat_ids: HashSet<i32> = messages.iter().filter_map(|m| m.chat_id).collect();
+
+	chat_ids
+		.into_iter()
+		.filter_map(|chat_id| {
+			let chat = sources.chats.get(chat_id).filter(|chat| !chat.is_group())?;
+			Some((chat_id, *chat.members.first()?))
+		})
+		.collect()
+}
+
+/// A message answering the other side of a conversation.
+pub struct Reply<'a> {
+	/// Last message of the other side before the reply
+	pub prompt: &'a Message,
+	pub reply: &'a Message,
+	pub seconds: i64
+}
+
+/// Finds every point in a conversation where the sender switches.
+fn replies<'a>(conversation: &[&'a Message]) -> Vec<Reply<'a>> {
+	conversation
+		.windows(2)
+		.filter(|pair| pair[0].is_from_me != pair[1].is_from_me)
+		.map(|pair| Reply {
+			prompt: pair[0],
+			reply: pair[1],
+			seconds: (apple_seconds(pair[1].date) - apple_seconds(pair[0].date)).max(0)
+		})
+		.collect()
+}
+
+/// Handles of the contacts with the most one-on-one messages, busiest first.
+fn top_contacts(conversations: &HashMap<i32, Vec<&Message>>, limit: usize) -> Vec<i32> {
+	let mut handles: Vec<(i32, usize)> =
+		conversations.iter().map(|(&handle, messages)| (handle, messages.len())).collect();
+	handles.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+	handles.into_iter().take(limit).map(|(handle, _)| handle).collect()
+}
+
+/// Turns a count map into `Item`s sorted by count, keeping the top `limit`.
+fn top_items(counts: HashMap<String, i32>, limit: usize) -> Vec<Item> {
+	let mut items: Vec<Item> = counts.into_iter().map(|(key, count)| Item { key, count }).collect();
+	items.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+	items.truncate(limit);
+	items
+}
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::words::words;
+use super::{top_contacts, Sources};
+use crate::options::TopList;
+use crate::stats::stats::NicknameUsage;
+
+/// Times a term has to open my messages to become my way of addressing someone
+const MIN_USES: i32 = 3;
+
+const ADDRESS_TERMS: &[&str] = &[
+	"babe", "baby", "bestie", "bro", "brother", "buddy", "dude", "fam", "girl", "homie", "king",
+	"love", "man", "mom", "queen", "sis", "sister", "bruh"
+];
+/// Greetings skipped when looking for the address term, as in "hey bro"
+const GREETINGS: &[&str] = &["hey", "hi", "hello", "yo", "sup", "ok", "okay", "omg"];
+
+/// My signature way of addressing each top contact: the term (a common
+/// nickname, or their first name) I most often open messages to them with.
+pub fn nickname_usage(messages: &[Message], sources: &Sources) -> Vec<NicknameUsage> {
+	let conversations = super::conversations_by_contact(messages, sources);
+
+	top_contacts(&conversations, sources.options.top(TopList::Contacts))
+		.into_iter()
+		.filter_map(|handle| {
+			let (name, handle_id) = sources.person(handle);
+			let first_name = name.split_whitespace().next().unwrap_or_default().to_lowercase();
+
+			let mut counts: HashMap<String, i32> = HashMap::new();
+			for text in conversations[&handle]
+				.iter()
+				.filter(|m| m.is_from_me)
+				.filter_map(|m| m.text.as_deref())
+			{
+				let Some(term) =
+					words(text).take(2).find(|word| !GREETINGS.contains(&word.as_str()))
+				else {
+					continue;
+				};
+				if ADDRESS_TERMS.contains(&term.as_str()) || (!first_name.is_empty() && term == first_name) {
+					*counts.entry(term).or_default() += 1;
+				}
+			}
+
+			let (nickname, count) = counts
+				.into_iter()
+				.filter(|&(_, count)| count >= MIN_USES)
+				.max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))?;
+
+			Some(NicknameUsage { name, handle_id, nickname, count, avatar: None })
+		})
+		.collect()
+}
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::{apple_seconds, unix_seconds, Sources};
+use crate::stats::stats::{PhotoDump, PhotoDumpStats};
+
+/// Longest pause between photo messages that still belong to one dump
+const MAX_GAP_SECONDS: i64 = 5 * 60;
+const MIN_PHOTOS: i32 = 5;
+
+/// Finds moments where I sent a pile of photos to one chat within minutes,
+/// and reports how many there were and the biggest one.
+pub fn photo_dump_stats(messages: &[Message], sources: &Sources) -> PhotoDumpStats {
+	let mut by_chat: HashMap<i32, Vec<(&Message, i32)>> = HashMap::new();
+	for message in messages.iter().filter(|m| m.is_from_me && m.num_attachments > 0) {
+		let photos =
+			sources.attachments.for_message(message.rowid).iter().filter(|a| a.is_photo()).count();
+		if let (Some(chat_id), true) = (message.chat_id, photos > 0) {
+			by_chat.entry(chat_id).or_default().push((message, photos as i32));
+		}
+	}
+
+	let mut dump_count = 0;
+	let mut biggest: Option<PhotoDump> = None;
+	for (chat_id, sends) in by_chat {
+		let mut start = 0;
+		for end in 1..=sends.len() {
+			if end < sends.len() &&
+				apple_seconds(sends[end].0.date) - apple_seconds(sends[end - 1].0.date) <=
+					MAX_GAP_SECONDS
+			{
+				continue;
+			}
+
+			let run = &sends[start..end];
+			start = end;
+			let photo_count: i32 = run.iter().map(|(_, photos)| photos).sum();
+			if photo_count < MIN_PHOTOS {
+				continue;
+			}
+
+			dump_count += 1;
+			if biggest.as_ref().map_or(true, |b| photo_count > b.photo_count) {
+				let (first, last) = (run[0].0, run[run.len() - 1].0);
+				biggest = Some(PhotoDump {
+					chat_name: sources.chat_name(chat_id),
+					chat_id: Some(chat_id),
+					photo_count,
+					date: unix_seconds(first.date),
+					duration_seconds: apple_seconds(last.date) - apple_seconds(first.date)
+				});
+			}
+		}
+	}
+
+	PhotoDumpStats { dump_count, biggest }
+}
@@ -0,0 +1,36 @@
+use imessage_database::tables::messages::Message;
+
+use super::{is_tapback, Sources};
+
+/// Passes that need tapbacks, which chat.db only has from iOS 10 (2016) on
+const REACTION_PASSES: &[&str] = &["reactionBalance", "reactionSpeed"];
+const GROUP_PASSES: &[&str] = &["groupChats", "groupChatProfanity"];
+const ATTACHMENT_PASSES: &[&str] = &["attachments", "photoDumps"];
+
+/// Insight passes that would only produce zeros for the year: no tapbacks,
+/// no group chats or no attachments. One scan that stops as soon as all three
+/// have been seen, so it's cheap to run before the passes.
+pub fn not_applicable(messages: &[Message], sources: &Sources) -> Vec<&'static str> {
+	let mut tapbacks = false;
+	let mut groups = false;
+	let mut attachments = false;
+	for message in messages {
+		tapbacks |= is_tapback(message);
+		let chat = message.chat_id.and_then(|id| sources.chats.get(id));
+		groups |= chat.is_some_and(|chat| chat.is_group());
+		attachments |= !sources.attachments.for_message(message.rowid).is_empty();
+		if tapbacks && groups && attachments {
+			break;
+		}
+	}
+
+	let mut passes = Vec::new();
+	for (found, ruled_out) in
+		[(tapbacks, REACTION_PASSES), (groups, GROUP_PASSES), (attachments, ATTACHMENT_PASSES)]
+	{
+		if !found {
+			passes.extend_from_slice(ruled_out);
+		}
+	}
+	passes
+}
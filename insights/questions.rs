@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::{apple_seconds, top_contacts, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{QuestionLatency, QuestionStats};
+
+/// Questions without an answer within this long count as unanswered
+const UNANSWERED_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+#[derive(Default)]
+struct Side {
+	answered: i64,
+	total_seconds: i64,
+	unanswered: i32
+}
+
+impl Side {
+	fn average(&self) -> i64 {
+		if self.answered == 0 {
+			0
+		} else {
+			self.total_seconds / self.answered
+		}
+	}
+}
+
+/// How long questions (messages ending in "?") wait for the other side's next
+/// message in one-on-one chats, and how many go unanswered.
+pub fn question_stats(messages: &[Message], sources: &Sources) -> QuestionStats {
+	let conversations = super::conversations_by_contact(messages, sources);
+
+	let mut stats = QuestionStats {
+		unanswered_by_me: 0,
+		unanswered_by_them: 0,
+		contacts: Vec::new(),
+		my_worst_backlog: None
+	};
+	let mut by_contact: HashMap<i32, QuestionLatency> = HashMap::new();
+
+	for (&handle, conversation) in &conversations {
+		// `mine` is me answering their questions, `theirs` them answering mine
+		let mut mine = Side::default();
+		let mut theirs = Side::default();
+
+		for (index, question) in conversation.iter().enumerate() {
+			if !question.text.as_deref().is_some_and(|t| t.trim_end().ends_with('?')) {
+				continue;
+			}
+			let side = if question.is_from_me { &mut theirs } else { &mut mine };
+			let answer = conversation[index + 1..]
+				.iter()
+				.find(|m| m.is_from_me != question.is_from_me)
+				.map(|answer| apple_seconds(answer.date) - apple_seconds(question.date))
+				.filter(|&seconds| seconds <= UNANSWERED_SECONDS);
+			match answer {
+				Some(seconds) => {
+					side.answered += 1;
+					side.total_seconds += seconds;
+				}
+				None => side.unanswered += 1
+			}
+		}
+
+		stats.unanswered_by_me += mine.unanswered;
+		stats.unanswered_by_them += theirs.unanswered;
+
+		let (name, handle_id) = sources.person(handle);
+		let latency = QuestionLatency {
+			name,
+			handle_id,
+			their_average_seconds: theirs.average(),
+			my_average_seconds: mine.average(),
+			unanswered_by_me: mine.unanswered,
+			unanswered_by_them: theirs.unanswered,
+			avatar: None
+		};
+		if latency.unanswered_by_me > 0 &&
+			stats
+				.my_worst_backlog
+				.as_ref()
+				.map_or(true, |worst| latency.unanswered_by_me > worst.unanswered_by_me)
+		{
+			stats.my_worst_backlog = Some(latency.clone());
+		}
+		by_contact.insert(handle, latency);
+	}
+
+	stats.contacts = top_contacts(&conversations, sources.options.top(TopList::Contacts))
+		.into_iter()
+		.filter_map(|handle| by_contact.remove(&handle))
+		.collect();
+
+	stats
+}
@@ -0,0 +1,59 @@
+use chrono::Datelike;
+use imessage_database::tables::messages::Message;
+
+use super::{local_time, top_contacts, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{MessageCount, RatioTrend, RatioTrendStats};
+
+/// Messages needed in both the first and last quarter to measure a shift
+const MIN_QUARTER_MESSAGES: i32 = 20;
+
+/// Monthly sent/received counts for each top contact. `shift` is how much my
+/// share of the conversation changed from the first quarter with messages to
+/// the last: positive means I ended up carrying it, negative that they did.
+pub fn send_received_trend(messages: &[Message], sources: &Sources) -> RatioTrendStats {
+	let conversations = super::conversations_by_contact(messages, sources);
+
+	let limit = sources.options.top(TopList::Contacts);
+	let contacts: Vec<RatioTrend> = top_contacts(&conversations, limit)
+		.into_iter()
+		.map(|handle| {
+			let mut monthly = vec![MessageCount { sent: 0, received: 0 }; 12];
+			for message in &conversations[&handle] {
+				let Some(month) = local_time(message.date).map(|t| t.month0() as usize) else {
+					continue;
+				};
+				if message.is_from_me {
+					monthly[month].sent += 1;
+				} else {
+					monthly[month].received += 1;
+				}
+			}
+
+			let (name, handle_id) = sources.person(handle);
+			RatioTrend { name, handle_id, shift: shift(&monthly), monthly, avatar: None }
+		})
+		.collect();
+
+	let biggest_shift = contacts.iter().max_by(|a, b| a.shift.abs().total_cmp(&b.shift.abs())).cloned();
+
+	RatioTrendStats { contacts, biggest_shift }
+}
+
+fn shift(monthly: &[MessageCount]) -> f32 {
+	let active: Vec<&MessageCount> = monthly.iter().filter(|m| m.sent + m.received > 0).collect();
+	if active.len() < 6 {
+		return 0.0;
+	}
+
+	let share = |months: &[&MessageCount]| {
+		let sent: i32 = months.iter().map(|m| m.sent).sum();
+		let total: i32 = months.iter().map(|m| m.sent + m.received).sum();
+		(total >= MIN_QUARTER_MESSAGES).then(|| sent as f32 / total as f32)
+	};
+
+	match (share(&active[..3]), share(&active[active.len() - 3..])) {
+		(Some(first), Some(last)) => last - first,
+		_ => 0.0
+	}
+}
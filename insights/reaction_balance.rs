@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::{associated_guid, is_added_tapback, is_tapback, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{ReactionBalance, ReactionBalanceStats};
+
+/// Contacts need this many messages to me to be called out for getting no reactions
+const MIN_MESSAGES: i32 = 50;
+
+#[derive(Default, Clone, Copy)]
+struct Tally {
+	given: i32,
+	received: i32,
+	their_messages: i32
+}
+
+/// Tapbacks I give to each contact's messages versus tapbacks they give to
+/// mine, across every chat.
+pub fn reaction_balance(messages: &[Message], sources: &Sources) -> ReactionBalanceStats {
+	// Tapbacks can target messages from before the year started
+	let authors: HashMap<&str, Option<i32>> = sources
+		.messages
+		.iter()
+		.map(|m| (m.guid.as_str(), if m.is_from_me { None } else { m.handle_id }))
+		.collect();
+
+	let mut tallies: HashMap<i32, Tally> = HashMap::new();
+	for message in messages {
+		if !is_tapback(message) {
+			if let (false, Some(handle)) = (message.is_from_me, message.handle_id) {
+				tallies.entry(handle).or_default().their_messages += 1;
+			}
+			continue;
+		}
+		if !is_added_tapback(message) {
+			continue;
+		}
+		let Some(author) = associated_guid(message).and_then(|guid| authors.get(guid)) else {
+			continue;
+		};
+
+		match (message.is_from_me, *author, message.handle_id) {
+			// I reacted to their message
+			(true, Some(handle), _) => tallies.entry(handle).or_default().given += 1,
+			// They reacted to my message
+			(false, None, Some(handle)) => tallies.entry(handle).or_default().received += 1,
+			_ => {}
+		}
+	}
+
+	let to_balance = |(handle, tally): (i32, Tally)| {
+		let (name, handle_id) = sources.person(handle);
+		ReactionBalance {
+			name,
+			handle_id,
+			given: tally.given,
+			received: tally.received,
+			their_messages: tally.their_messages,
+			avatar: None
+		}
+	};
+
+	let biggest_hype = tallies
+		.iter()
+		.filter(|(_, tally)| tally.received > 0)
+		.max_by_key(|(&handle, tally)| (tally.received, -handle))
+		.map(|(&handle, &tally)| to_balance((handle, tally)));
+	let least_reacted_to = tallies
+		.iter()
+		.filter(|(_, tally)| tally.their_messages >= MIN_MESSAGES)
+		.min_by(|(a_handle, a), (b_handle, b)| {
+			let a_rate = a.given as f64 / a.their_messages as f64;
+			let b_rate = b.given as f64 / b.their_messages as f64;
+			a_rate.total_cmp(&b_rate).then_with(|| a_handle.cmp(b_handle))
+		})
+		.map(|(&handle, &tally)| to_balance((handle, tally)));
+
+	let mut ranked: Vec<(i32, Tally)> = tallies
+		.into_iter()
+		.filter(|(_, tally)| tally.given + tally.received > 0)
+		.collect();
+	ranked.sort_by(|a, b| {
+		(b.1.given + b.1.received).cmp(&(a.1.given + a.1.received)).then_with(|| a.0.cmp(&b.0))
+	});
+
+	ReactionBalanceStats {
+		contacts: ranked
+			.into_iter()
+			.take(sources.options.top(TopList::Contacts))
+			.map(to_balance)
+			.collect(),
+		biggest_hype,
+		least_reacted_to
+	}
+}
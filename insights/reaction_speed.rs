@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::{apple_seconds, associated_guid, is_added_tapback, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{ReactionSpeed, ReactionSpeedStats};
+
+/// Tapbacks later than this are catching up on old messages, not reacting
+const MAX_REACTION_SECONDS: i64 = 24 * 60 * 60;
+/// Tapbacks a contact needs before their median means anything
+const MIN_REACTIONS: usize = 5;
+
+/// How long tapbacks take to arrive after the message they react to: each
+/// contact's median on my messages, the fastest of them, and my own median on
+/// everyone else's, across every chat. The companion to reply speed.
+pub fn reaction_speed(messages: &[Message], sources: &Sources) -> ReactionSpeedStats {
+	let mut speeds = ReactionSpeeds::new(sources);
+	for message in messages {
+		speeds.add(message);
+	}
+	speeds.finish(sources)
+}
+
+/// Tapback delays, filled one message at a time so the fused engine can
+/// share its scan.
+pub struct ReactionSpeeds<'a> {
+	/// Tapbacks can target messages from before the year started
+	targets: HashMap<&'a str, (bool, i64)>,
+	mine: Vec<i64>,
+	theirs: HashMap<i32, Vec<i64>>
+}
+
+impl<'a> ReactionSpeeds<'a> {
+	pub fn new(sources: &Sources<'a>) -> Self {
+		let targets = sources
+			.messages
+			.iter()
+			.map(|m| (m.guid.as_str(), (m.is_from_me, apple_seconds(m.date))))
+			.collect();
+		Self { targets, mine: Vec::new(), theirs: HashMap::new() }
+	}
+
+	pub fn add(&mut self, message: &Message) {
+		if !is_added_tapback(message) {
+			return;
+		}
+		let Some(&(target_from_me, sent)) =
+			associated_guid(message).and_then(|guid| self.targets.get(guid))
+		else {
+			return;
+		};
+		let seconds = apple_seconds(message.date) - sent;
+		if !(0..=MAX_REACTION_SECONDS).contains(&seconds) {
+			return;
+		}
+		match (message.is_from_me, target_from_me, message.handle_id) {
+			// I reacted to someone else's message
+			(true, false, _) => self.mine.push(seconds),
+			// They reacted to my message
+			(false, true, Some(handle)) if handle != 0 => {
+				self.theirs.entry(handle).or_default().push(seconds)
+			}
+			_ => {}
+		}
+	}
+
+	pub fn finish(self, sources: &Sources) -> ReactionSpeedStats {
+		let mut mine = self.mine;
+		let mut ranked: Vec<(i32, usize, i64)> = self
+			.theirs
+			.into_iter()
+			.filter(|(_, seconds)| seconds.len() >= MIN_REACTIONS)
+			.map(|(handle, mut seconds)| (handle, seconds.len(), median(&mut seconds)))
+			.collect();
+		ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+		let to_speed = |&(handle, reactions, median_seconds): &(i32, usize, i64)| {
+			let (name, handle_id) = sources.person(handle);
+			let reactions = reactions as i32;
+			ReactionSpeed { name, handle_id, reactions, median_seconds, avatar: None }
+		};
+		let fastest_reactor = ranked.iter().min_by_key(|&&(handle, _, seconds)| (seconds, handle));
+		let limit = sources.options.top(TopList::Contacts);
+
+		ReactionSpeedStats {
+			fastest_reactor: fastest_reactor.map(to_speed),
+			contacts: ranked.iter().take(limit).map(to_speed).collect(),
+			my_reactions: mine.len() as i32,
+			my_median_seconds: (!mine.is_empty()).then(|| median(&mut mine))
+		}
+	}
+}
+
+fn median(seconds: &mut [i64]) -> i64 {
+	seconds.sort_unstable();
+	seconds[seconds.len() / 2]
+}
@@ -0,0 +1,52 @@
+use chrono::Datelike;
+use imessage_database::tables::messages::Message;
+
+use super::{local_time, top_contacts, Sources};
+use crate::options::{PrivacyLevel, TopList};
+use crate::stats::stats::ContactRelationship;
+
+/// Relationship labels, companies and birthday texts for my top contacts
+/// ("your sister was your #2"). Nothing at the strict privacy level, and
+/// companies only at the permissive one.
+pub fn relationships(messages: &[Message], sources: &Sources) -> Vec<ContactRelationship> {
+	let privacy = sources.options.privacy_level();
+	if privacy == PrivacyLevel::Strict {
+		return Vec::new();
+	}
+	let conversations = super::conversations_by_contact(messages, sources);
+
+	top_contacts(&conversations, sources.options.top(TopList::Contacts))
+		.into_iter()
+		.enumerate()
+		.filter_map(|(index, handle)| {
+			let (name, handle_id) = sources.person(handle);
+			let detail = sources.contact_details.get(&handle_id)?;
+
+			let birthday_messages = detail.birthday.map_or(0, |birthday| {
+				conversations[&handle]
+					.iter()
+					.filter(|m| m.is_from_me)
+					.filter_map(|m| local_time(m.date))
+					.filter(|t| (t.month(), t.day()) == birthday)
+					.count() as i32
+			});
+			let organization = detail
+				.organization
+				.clone()
+				.filter(|_| privacy == PrivacyLevel::Permissive);
+			if detail.relationship.is_none() && organization.is_none() && birthday_messages == 0 {
+				return None;
+			}
+
+			Some(ContactRelationship {
+				name,
+				handle_id,
+				rank: index as i32 + 1,
+				relationship: detail.relationship.clone(),
+				organization,
+				birthday_messages,
+				avatar: None
+			})
+		})
+		.collect()
+}
@@ -0,0 +1,85 @@
+use imessage_database::tables::messages::Message;
+
+use super::{replies, top_contacts, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{InstantReplyCount, ResponseTimeLeaderboard, ResponseTimePair};
+
+/// Gaps longer than this start a new conversation rather than answer one
+const MAX_REPLY_SECONDS: i64 = 24 * 60 * 60;
+/// Both sides need this many replies before an average means anything
+const MIN_REPLIES: i32 = 5;
+
+/// Average reply time in both directions for my top one-on-one contacts,
+/// plus how often I answered each of them within the instant reply thresholds.
+pub fn response_time_leaderboard(messages: &[Message], sources: &Sources) -> ResponseTimeLeaderboard {
+	let conversations = super::conversations_by_contact(messages, sources);
+	let thresholds = sources.options.instant_reply_thresholds();
+
+	let limit = sources.options.top(TopList::Contacts);
+	let contacts: Vec<ResponseTimePair> = top_contacts(&conversations, limit)
+		.into_iter()
+		.filter_map(|handle| {
+			let mut mine = (0i64, 0i32);
+			let mut theirs = (0i64, 0i32);
+			let mut instant = vec![0i32; thresholds.len()];
+			for reply in replies(&conversations[&handle])
+				.into_iter()
+				.filter(|r| r.seconds <= MAX_REPLY_SECONDS)
+			{
+				let side = if reply.reply.is_from_me { &mut mine } else { &mut theirs };
+				side.0 += reply.seconds;
+				side.1 += 1;
+				if reply.reply.is_from_me {
+					for (count, &threshold) in instant.iter_mut().zip(&thresholds) {
+						if reply.seconds <= threshold {
+							*count += 1;
+						}
+					}
+				}
+			}
+			if mine.1 < MIN_REPLIES || theirs.1 < MIN_REPLIES {
+				return None;
+			}
+
+			let (name, handle_id) = sources.person(handle);
+			Some(ResponseTimePair {
+				name,
+				handle_id,
+				their_average_seconds: theirs.0 / theirs.1 as i64,
+				my_average_seconds: mine.0 / mine.1 as i64,
+				their_replies: theirs.1,
+				my_replies: mine.1,
+				avatar: None,
+				instant_replies: thresholds
+					.iter()
+					.zip(instant)
+					.map(|(&threshold, count)| InstantReplyCount {
+						threshold_seconds: threshold as i32,
+						count
+					})
+					.collect()
+			})
+		})
+		.collect();
+
+	let most_ignored = contacts.iter().max_by_key(|pair| pair.my_average_seconds).cloned();
+	let most_lopsided = contacts
+		.iter()
+		.max_by(|a, b| asymmetry(a).total_cmp(&asymmetry(b)))
+		.cloned();
+	// Ranked by the tightest threshold
+	let quickest_draw = contacts
+		.iter()
+		.filter(|pair| pair.instant_replies.first().is_some_and(|instant| instant.count > 0))
+		.max_by_key(|pair| pair.instant_replies.first().map(|instant| instant.count))
+		.cloned();
+
+	ResponseTimeLeaderboard { contacts, most_ignored, most_lopsided, quickest_draw }
+}
+
+/// How many times slower the slower side replies.
+fn asymmetry(pair: &ResponseTimePair) -> f64 {
+	let mine = pair.my_average_seconds.max(1) as f64;
+	let theirs = pair.their_average_seconds.max(1) as f64;
+	mine.max(theirs) / mine.min(theirs)
+}
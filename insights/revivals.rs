@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::{apple_seconds, messages_by_chat, unix_seconds, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{Necropost, PhraseStats, RevivalStats};
+
+const DORMANT_SECONDS: i64 = 30 * 24 * 60 * 60;
+
+/// Counts messages that revive a chat, or reply in a thread, that had been
+/// dormant for 30+ days, who does it most, and my most extreme necropost.
+pub fn revival_stats(messages: &[Message], sources: &Sources) -> RevivalStats {
+	// Thread replies can point at messages from before the year
+	let thread_starts: HashMap<&str, i64> =
+		sources.messages.iter().map(|m| (m.guid.as_str(), apple_seconds(m.date))).collect();
+
+	let mut revived_by_me = 0;
+	let mut revivers: HashMap<i32, i32> = HashMap::new();
+	let mut biggest: Option<(i32, i64, &Message)> = None;
+
+	for (chat_id, chat_messages) in messages_by_chat(messages) {
+		for (index, &message) in chat_messages.iter().enumerate() {
+			let sent = apple_seconds(message.date);
+			let chat_gap = index.checked_sub(1).map(|i| sent - apple_seconds(chat_messages[i].date));
+			let thread_gap = message
+				.thread_originator_guid
+				.as_deref()
+				.and_then(|guid| thread_starts.get(guid))
+				.map(|&start| sent - start);
+
+			let Some(dormant) = chat_gap.max(thread_gap).filter(|&gap| gap >= DORMANT_SECONDS) else {
+				continue;
+			};
+
+			if message.is_from_me {
+				revived_by_me += 1;
+				if biggest.map_or(true, |(_, gap, _)| dormant > gap) {
+					biggest = Some((chat_id, dormant, message));
+				}
+			} else if let Some(handle) = message.handle_id {
+				*revivers.entry(handle).or_default() += 1;
+			}
+		}
+	}
+
+	let mut top_revivers: Vec<(i32, i32)> = revivers.into_iter().collect();
+	top_revivers.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+	RevivalStats {
+		revived_by_me,
+		top_revivers: top_revivers
+			.into_iter()
+			.take(sources.options.top(TopList::People))
+			.map(|(handle, count)| {
+				let (name, handle_id) = sources.person(handle);
+				PhraseStats { name, handle_id, count, avatar: None }
+			})
+			.collect(),
+		my_biggest_necropost: biggest.map(|(chat_id, dormant_seconds, message)| Necropost {
+			chat_name: sources.chat_name(chat_id),
+			chat_id: Some(chat_id),
+			dormant_seconds,
+			date: unix_seconds(message.date)
+		})
+	}
+}
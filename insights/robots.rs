@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::Sources;
+use crate::options::TopList;
+use crate::stats::stats::{RobotSender, RobotStats};
+
+/// Robots that texted me: messages received from automated senders, which
+/// are kept out of every other stat.
+pub fn robot_stats(automated: &[Message], sources: &Sources) -> Option<RobotStats> {
+	let mut by_handle: HashMap<i32, i32> = HashMap::new();
+	for message in automated.iter().filter(|m| !m.is_from_me) {
+		if let Some(handle) = message.handle_id {
+			*by_handle.entry(handle).or_default() += 1;
+		}
+	}
+	if by_handle.is_empty() {
+		return None;
+	}
+
+	let received = by_handle.values().sum();
+	let senders = by_handle.len() as i32;
+	let mut ranked: Vec<(i32, i32)> = by_handle.into_iter().collect();
+	ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+	Some(RobotStats {
+		received,
+		senders,
+		top_senders: ranked
+			.into_iter()
+			.take(sources.options.top(TopList::People))
+			.map(|(handle, received)| RobotSender {
+				handle_id: sources.handles.get(handle).cloned().unwrap_or_default(),
+				received
+			})
+			.collect()
+	})
+}
@@ -0,0 +1,174 @@
+use chrono::Datelike;
+use imessage_database::tables::messages::Message;
+
+use super::words::words;
+use super::{is_tapback, local_time, top_contacts, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{ContactSentiment, MonthTone, SentimentStats};
+
+/// Scored messages a month needs to count towards the gloomiest month and
+/// the tone shift
+const MIN_MONTH_MESSAGES: i32 = 10;
+/// Scored messages a contact needs in both directions together
+const MIN_CONTACT_MESSAGES: i32 = 20;
+/// Words that flip a scored word within the three before it
+const NEGATIONS: &[&str] =
+	&["aint", "cant", "didnt", "doesnt", "dont", "isnt", "never", "no", "not", "wasnt", "wont"];
+/// Words that strengthen the scored word right after them
+const BOOSTERS: &[&str] = &["extremely", "really", "so", "super", "totally", "very"];
+const BOOST: f32 = 0.3;
+/// How much of a word's valence is left, reversed, after a negation
+const NEGATION_SCALE: f32 = -0.74;
+/// Added per exclamation mark, up to four
+const EXCLAMATION_BOOST: f32 = 0.292;
+/// Spreads summed valence over -1 to 1; the larger, the flatter
+const NORMALIZATION: f32 = 15.0;
+
+/// VADER-style valence of common texting words, from -4 (most negative) to
+/// 4. Sorted for binary search. Words slang uses both ways, like "dead" and
+/// "insane", aren't scored.
+const LEXICON: &[(&str, f32)] = &[
+	("abandoned", -1.9), ("adore", 2.9), ("afraid", -2.2), ("agree", 1.5), ("amazing", 2.8),
+	("angry", -2.3), ("annoyed", -1.6), ("annoying", -1.7), ("anxious", -1.0), ("ashamed", -2.1),
+	("awesome", 3.1), ("awful", -2.0), ("awkward", -0.6), ("bad", -2.5), ("beautiful", 2.9),
+	("best", 3.2), ("better", 1.9), ("bitter", -1.8), ("blessed", 2.9), ("bored", -1.1),
+	("boring", -1.3), ("brilliant", 2.8), ("broke", -1.8), ("broken", -2.1), ("calm", 1.3),
+	("cancelled", -1.0), ("care", 2.2), ("cared", 1.8), ("celebrate", 2.7), ("cheers", 2.1),
+	("congrats", 2.4), ("congratulations", 2.9), ("cool", 1.3), ("cry", -2.1), ("crying", -2.1),
+	("cute", 2.0), ("damn", -1.7), ("delighted", 2.9), ("depressed", -2.3), ("disappointed", -1.9),
+	("disappointing", -2.2), ("disgusting", -2.4), ("dope", 1.3), ("dumb", -2.3), ("enjoy", 2.2),
+	("enjoyed", 2.3), ("excellent", 2.7), ("excite", 2.1), ("excited", 1.4), ("exciting", 2.2),
+	("exhausted", -1.5), ("fail", -2.5), ("failed", -2.3), ("fantastic", 2.6), ("fav", 2.0),
+	("favorite", 2.0), ("fear", -2.2), ("fight", -1.6), ("frustrated", -2.4), ("fuck", -2.5),
+	("fun", 2.3), ("funny", 1.9), ("furious", -2.7), ("glad", 2.0), ("glorious", 2.8),
+	("good", 1.9), ("gorgeous", 3.0), ("grateful", 2.0), ("great", 3.1), ("gross", -2.1),
+	("haha", 2.0), ("hahaha", 2.6), ("happy", 2.7), ("hate", -2.7), ("hated", -3.2),
+	("hates", -1.9), ("heaven", 2.3), ("helpful", 1.8), ("hilarious", 1.7), ("honored", 2.2),
+	("hope", 1.9), ("horrible", -2.5), ("hug", 2.1), ("hugs", 2.2), ("hurt", -2.4),
+	("hurts", -2.1), ("idiot", -2.3), ("impressed", 2.1), ("incredible", 2.0),
+	("interesting", 1.7), ("jealous", -2.0), ("joy", 2.8), ("laugh", 2.6), ("legend", 1.3),
+	("liked", 1.8), ("lmao", 2.0), ("lol", 1.8), ("lonely", -1.5), ("lost", -1.3), ("love", 3.2),
+	("loved", 2.9), ("lovely", 2.8), ("loves", 2.7), ("loving", 2.9), ("lucky", 1.8),
+	("mad", -2.2), ("mess", -1.5), ("miserable", -2.2), ("nervous", -1.1), ("nice", 1.8),
+	("nope", -1.2), ("pain", -2.3), ("panic", -2.3), ("peace", 2.5), ("perfect", 2.7),
+	("pissed", -3.2), ("pleased", 1.9), ("problem", -1.7), ("problems", -1.7), ("proud", 2.1),
+	("regret", -1.8), ("relaxed", 2.2), ("rude", -2.0), ("ruined", -2.4), ("sad", -2.1),
+	("sadly", -1.7), ("safe", 1.9), ("scared", -1.9), ("scary", -2.2), ("shit", -2.6),
+	("sick", -2.3), ("sigh", -0.9), ("smile", 1.5), ("smiling", 2.4), ("stress", -1.8),
+	("stressed", -1.4), ("stupid", -2.4), ("suck", -1.5), ("sucks", -1.5), ("support", 1.7),
+	("sweet", 2.0), ("terrible", -2.1), ("thank", 1.5), ("thanks", 1.9), ("thx", 1.5),
+	("tired", -1.9), ("tragic", -3.4), ("ty", 1.6), ("ugh", -1.8), ("ugly", -2.3),
+	("unfair", -2.1), ("unhappy", -1.8), ("upset", -1.6), ("useless", -1.8), ("wasted", -2.2),
+	("welcome", 2.0), ("win", 2.8), ("wonderful", 2.7), ("worried", -1.2), ("worry", -1.9),
+	("worse", -2.1), ("worst", -3.1), ("wow", 2.8), ("wrong", -2.1), ("wtf", -2.8), ("yay", 2.4),
+	("yum", 1.9)
+];
+
+/// How positive the messages in my one-on-one chats are, from the VADER-style
+/// lexicon: per top contact in both directions and the most positive of those
+/// friendships, plus the tone of everything I sent month by month, its
+/// gloomiest month and how it shifted from the start of the year to the end.
+/// Messages without any scored word are left out.
+pub fn sentiment_stats(messages: &[Message], sources: &Sources) -> SentimentStats {
+	let mut months = [(0.0f32, 0i32); 12];
+	for message in messages.iter().filter(|m| m.is_from_me && !is_tapback(m)) {
+		let (Some(text), Some(time)) = (message.text.as_deref(), local_time(message.date)) else {
+			continue;
+		};
+		if let Some(score) = score(text) {
+			let month = &mut months[time.month0() as usize];
+			month.0 += score;
+			month.1 += 1;
+		}
+	}
+	let monthly: Vec<MonthTone> = months
+		.iter()
+		.enumerate()
+		.map(|(index, &(sum, count))| MonthTone {
+			month: index as i32 + 1,
+			tone: (count > 0).then(|| sum / count as f32),
+			messages: count
+		})
+		.collect();
+	let steady: Vec<(i32, f32)> = monthly
+		.iter()
+		.filter(|month| month.messages >= MIN_MONTH_MESSAGES)
+		.filter_map(|month| Some((month.month, month.tone?)))
+		.collect();
+	let gloomiest_month = steady.iter().min_by(|a, b| a.1.total_cmp(&b.1)).map(|&(month, _)| month);
+
+	let conversations = super::conversations_by_contact(messages, sources);
+	let limit = sources.options.top(TopList::Contacts);
+	let contacts: Vec<ContactSentiment> = top_contacts(&conversations, limit)
+		.into_iter()
+		.filter_map(|handle| {
+			let mut sent = (0.0f32, 0i32);
+			let mut received = (0.0f32, 0i32);
+			for message in &conversations[&handle] {
+				let Some(score) = message.text.as_deref().and_then(score) else { continue };
+				let side = if message.is_from_me { &mut sent } else { &mut received };
+				side.0 += score;
+				side.1 += 1;
+			}
+			let scored_messages = sent.1 + received.1;
+			if scored_messages < MIN_CONTACT_MESSAGES {
+				return None;
+			}
+
+			let mean = |(sum, count): (f32, i32)| (count > 0).then(|| sum / count as f32);
+			let (name, handle_id) = sources.person(handle);
+			Some(ContactSentiment {
+				name,
+				handle_id,
+				tone: (sent.0 + received.0) / scored_messages as f32,
+				sent: mean(sent),
+				received: mean(received),
+				scored_messages,
+				avatar: None
+			})
+		})
+		.collect();
+	let most_positive = contacts.iter().max_by(|a, b| a.tone.total_cmp(&b.tone)).cloned();
+
+	SentimentStats { contacts, most_positive, tone_shift: shift(&steady), gloomiest_month, monthly }
+}
+
+/// Compound score of `text` from -1 to 1, or `None` when none of its words
+/// are in the lexicon.
+fn score(text: &str) -> Option<f32> {
+	let words: Vec<String> = words(text).collect();
+	let mut sum = 0.0;
+	let mut scored = false;
+	for (index, word) in words.iter().enumerate() {
+		let Some(mut valence) = valence(word) else { continue };
+		let before = &words[index.saturating_sub(3)..index];
+		if before.last().is_some_and(|word| BOOSTERS.contains(&word.as_str())) {
+			valence += BOOST * valence.signum();
+		}
+		if before.iter().any(|word| NEGATIONS.contains(&word.as_str())) {
+			valence *= NEGATION_SCALE;
+		}
+		sum += valence;
+		scored = true;
+	}
+	if !scored {
+		return None;
+	}
+
+	let exclamations = text.matches('!').count().min(4) as f32;
+	sum += sum.signum() * EXCLAMATION_BOOST * exclamations;
+	Some(sum / (sum * sum + NORMALIZATION).sqrt())
+}
+
+fn valence(word: &str) -> Option<f32> {
+	LEXICON.binary_search_by_key(&word, |&(word, _)| word).ok().map(|index| LEXICON[index].1)
+}
+
+/// How my tone changed from the first three steady months to the last three.
+fn shift(steady: &[(i32, f32)]) -> Option<f32> {
+	if steady.len() < 6 {
+		return None;
+	}
+	let mean = |months: &[(i32, f32)]| months.iter().map(|&(_, tone)| tone).sum::<f32>() / 3.0;
+	Some(mean(&steady[steady.len() - 3..]) - mean(&steady[..3]))
+}
@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::Sources;
+use crate::options::{PrivacyLevel, TopList};
+use crate::stats::stats::SharedLink;
+
+/// The articles and videos I shared most, from cached link previews. Titles
+/// stay on the device unless the privacy level is permissive; otherwise only
+/// the domain is included.
+pub fn top_shared_links(messages: &[Message], sources: &Sources) -> Vec<SharedLink> {
+	let mut links = SharedLinks::default();
+	for message in messages {
+		links.add(message, sources);
+	}
+	links.finish(sources)
+}
+
+/// Share counts by URL, filled one message at a time so the fused engine can
+/// share its scan.
+#[derive(Default)]
+pub struct SharedLinks<'a> {
+	counts: HashMap<&'a str, (i32, &'a str, &'a str)>
+}
+
+impl<'a> SharedLinks<'a> {
+	pub fn add(&mut self, message: &Message, sources: &Sources<'a>) {
+		if !message.is_from_me {
+			return;
+		}
+		let Some(preview) = sources.link_previews.get(message.rowid) else { return };
+		let title = preview.title.as_deref().unwrap_or_default();
+		self.counts.entry(preview.url.as_str()).or_insert((0, title, preview.domain())).0 += 1;
+	}
+
+	pub fn finish(self, sources: &Sources) -> Vec<SharedLink> {
+		let include_titles = sources.options.privacy_level() == PrivacyLevel::Permissive;
+
+		let mut links: Vec<(i32, &str, &str)> = self.counts.into_values().collect();
+		links.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+		links
+			.into_iter()
+			.take(sources.options.top(TopList::Links))
+			.map(|(count, title, domain)| SharedLink {
+				title: if include_titles { title.to_string() } else { String::new() },
+				domain: domain.to_string(),
+				count
+			})
+			.collect()
+	}
+}
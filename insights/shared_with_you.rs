@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::Sources;
+use crate::stats::stats::{SharedWithYouSharer, SharedWithYouStats};
+
+const MUSIC_DOMAINS: &[&str] =
+	&["music.apple.com", "music.youtube.com", "open.spotify.com", "soundcloud.com", "tidal.com"];
+
+/// What friends shared into my apps through Shared with You, by kind, and
+/// who shared the most.
+pub fn shared_with_you_stats(messages: &[Message], sources: &Sources) -> Option<SharedWithYouStats> {
+	let mut shared = SharedWithYou::default();
+	for message in messages {
+		shared.add(message, sources);
+	}
+	shared.finish(sources)
+}
+
+/// Shared with You counts, filled one message at a time so the fused engine
+/// can share its scan.
+pub struct SharedWithYou {
+	stats: SharedWithYouStats,
+	by_handle: HashMap<i32, i32>
+}
+
+impl Default for SharedWithYou {
+	fn default() -> Self {
+		Self {
+			stats: SharedWithYouStats {
+				links: 0,
+				music: 0,
+				photos: 0,
+				other: 0,
+				biggest_sharer: None
+			},
+			by_handle: HashMap::new()
+		}
+	}
+}
+
+impl SharedWithYou {
+	pub fn add(&mut self, message: &Message, sources: &Sources) {
+		if message.is_from_me || !sources.syndicated.contains(message.rowid) {
+			return;
+		}
+		let stats = &mut self.stats;
+		if let Some(preview) = sources.link_previews.get(message.rowid) {
+			if MUSIC_DOMAINS.contains(&preview.domain()) {
+				stats.music += 1;
+			} else {
+				stats.links += 1;
+			}
+		} else if sources.attachments.for_message(message.rowid).iter().any(|a| a.is_photo()) {
+			stats.photos += 1;
+		} else {
+			stats.other += 1;
+		}
+		if let Some(handle) = message.handle_id.filter(|&handle| handle != 0) {
+			*self.by_handle.entry(handle).or_default() += 1;
+		}
+	}
+
+	pub fn finish(self, sources: &Sources) -> Option<SharedWithYouStats> {
+		let Self { mut stats, by_handle } = self;
+		if stats.links + stats.music + stats.photos + stats.other == 0 {
+			return None;
+		}
+
+		stats.biggest_sharer = by_handle
+			.into_iter()
+			.max_by_key(|&(handle, shared)| (shared, -handle))
+			.map(|(handle, shared)| {
+				let (name, handle_id) = sources.person(handle);
+				SharedWithYouSharer { name, handle_id, shared, avatar: None }
+			});
+
+		Some(stats)
+	}
+}
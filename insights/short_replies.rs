@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::words::{is_dry_reply, words};
+use super::{replies, top_contacts, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{ShortReplyCounts, ShortReplyStats};
+
+/// Replies needed from a contact before they can win most dismissive
+const MIN_REPLIES: usize = 30;
+
+/// Counts one-word replies and bare "k"/"ok"/"lol" replies in one-on-one
+/// chats, per contact and in total. The most dismissive correspondent is the
+/// top contact whose replies to me are most often bare acknowledgements.
+pub fn short_reply_stats(messages: &[Message], sources: &Sources) -> ShortReplyStats {
+	let conversations = super::conversations_by_contact(messages, sources);
+
+	let mut totals = ShortReplyStats {
+		one_word_sent: 0,
+		one_word_received: 0,
+		dry_sent: 0,
+		dry_received: 0,
+		contacts: Vec::new(),
+		most_dismissive: None
+	};
+	let mut dismissive_rate = 0.0;
+	let mut by_contact: HashMap<i32, ShortReplyCounts> = HashMap::new();
+
+	for (&handle, conversation) in &conversations {
+		let (name, handle_id) = sources.person(handle);
+		let mut counts = ShortReplyCounts {
+			name,
+			handle_id,
+			one_word_sent: 0,
+			one_word_received: 0,
+			dry_sent: 0,
+			dry_received: 0,
+			avatar: None
+		};
+		let mut their_replies = 0;
+
+		for reply in replies(conversation) {
+			let from_me = reply.reply.is_from_me;
+			if !from_me {
+				their_replies += 1;
+			}
+			let Some(text) = reply.reply.text.as_deref() else { continue };
+			if words(text).count() == 1 {
+				if from_me { counts.one_word_sent += 1 } else { counts.one_word_received += 1 }
+			}
+			if is_dry_reply(text) {
+				if from_me { counts.dry_sent += 1 } else { counts.dry_received += 1 }
+			}
+		}
+
+		totals.one_word_sent += counts.one_word_sent;
+		totals.one_word_received += counts.one_word_received;
+		totals.dry_sent += counts.dry_sent;
+		totals.dry_received += counts.dry_received;
+
+		if their_replies >= MIN_REPLIES {
+			let rate = counts.dry_received as f64 / their_replies as f64;
+			if rate > dismissive_rate {
+				dismissive_rate = rate;
+				totals.most_dismissive = Some(counts.clone());
+			}
+		}
+		by_contact.insert(handle, counts);
+	}
+
+	totals.contacts = top_contacts(&conversations, sources.options.top(TopList::Contacts))
+		.into_iter()
+		.filter_map(|handle| by_contact.remove(&handle))
+		.collect();
+
+	totals
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::{apple_seconds, top_contacts, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{SilenceBreaks, SilenceStats};
+
+const SILENCE_SECONDS: i64 = 7 * 24 * 60 * 60;
+/// Silences needed before someone can be the contact I always cave for
+const MIN_SILENCES: i32 = 3;
+
+/// For each one-on-one conversation, finds week-plus silences, who broke
+/// them, and how long the other side took to answer the message that did.
+pub fn silence_stats(messages: &[Message], sources: &Sources) -> SilenceStats {
+	let conversations = super::conversations_by_contact(messages, sources);
+
+	let mut stats = SilenceStats {
+		broken_by_me: 0,
+		broken_by_them: 0,
+		contacts: Vec::new(),
+		i_cave_first: None
+	};
+	let mut by_contact: HashMap<i32, SilenceBreaks> = HashMap::new();
+	let mut cave_rate = 0.0;
+
+	for (&handle, conversation) in &conversations {
+		let mut broken_by_me = 0;
+		let mut broken_by_them = 0;
+		let mut reply_seconds = Vec::new();
+
+		for (index, pair) in conversation.windows(2).enumerate() {
+			if apple_seconds(pair[1].date) - apple_seconds(pair[0].date) < SILENCE_SECONDS {
+				continue;
+			}
+
+			let breaker = pair[1];
+			if breaker.is_from_me {
+				broken_by_me += 1;
+			} else {
+				broken_by_them += 1;
+			}
+			if let Some(reply) = conversation[index + 1..]
+				.iter()
+				.find(|m| m.is_from_me != breaker.is_from_me)
+			{
+				reply_seconds.push(apple_seconds(reply.date) - apple_seconds(breaker.date));
+			}
+		}
+
+		let silences = broken_by_me + broken_by_them;
+		if silences == 0 {
+			continue;
+		}
+		stats.broken_by_me += broken_by_me;
+		stats.broken_by_them += broken_by_them;
+
+		reply_seconds.sort_unstable();
+		let (name, handle_id) = sources.person(handle);
+		let breaks = SilenceBreaks {
+			name,
+			handle_id,
+			silences,
+			broken_by_me,
+			broken_by_them,
+			median_reply_seconds: reply_seconds.get(reply_seconds.len() / 2).copied().unwrap_or(0),
+			avatar: None
+		};
+
+		if silences >= MIN_SILENCES {
+			let rate = broken_by_me as f64 / silences as f64;
+			if rate > cave_rate {
+				cave_rate = rate;
+				stats.i_cave_first = Some(breaks.clone());
+			}
+		}
+		by_contact.insert(handle, breaks);
+	}
+
+	stats.contacts = top_contacts(&conversations, sources.options.top(TopList::Contacts))
+		.into_iter()
+		.filter_map(|handle| by_contact.remove(&handle))
+		.collect();
+
+	stats
+}
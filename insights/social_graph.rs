@@ -0,0 +1,90 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use imessage_database::tables::messages::Message;
+
+use super::Sources;
+use crate::options::TopList;
+use crate::stats::stats::{ConnectedFriend, SocialGraphStats};
+
+/// Members of each group chat that had messages this year.
+pub fn active_groups(messages: &[Message], sources: &Sources) -> Vec<(i32, Vec<i32>)> {
+	let active: BTreeSet<i32> = messages.iter().filter_map(|m| m.chat_id).collect();
+	active
+		.into_iter()
+		.filter_map(|chat_id| {
+			let chat = sources.chats.get(chat_id).filter(|chat| chat.is_group())?;
+			Some((chat_id, chat.members.clone()))
+		})
+		.collect()
+}
+
+/// Infers my most connected friends (in the most group chats with me) and the
+/// friend bridging the most otherwise separate circles. A friend's circles
+/// are the groups of their co-members that stay connected without them.
+pub fn social_graph_stats(messages: &[Message], sources: &Sources) -> SocialGraphStats {
+	let groups = active_groups(messages, sources);
+
+	let mut shared_groups: HashMap<i32, i32> = HashMap::new();
+	for (_, members) in &groups {
+		for &member in members {
+			*shared_groups.entry(member).or_default() += 1;
+		}
+	}
+
+	let to_friend = |handle: i32| {
+		let (name, handle_id) = sources.person(handle);
+		ConnectedFriend { name, handle_id, shared_groups: shared_groups[&handle], avatar: None }
+	};
+
+	let mut ranked: Vec<i32> = shared_groups.keys().copied().collect();
+	ranked.sort_by_key(|handle| (-shared_groups[handle], *handle));
+
+	let bridge = ranked
+		.iter()
+		.map(|&handle| (handle, circles_bridged(handle, &groups)))
+		.filter(|&(_, circles)| circles >= 2)
+		.max_by_key(|&(handle, circles)| (circles, shared_groups[&handle], -handle));
+
+	SocialGraphStats {
+		most_connected: ranked
+			.iter()
+			.take(sources.options.top(TopList::People))
+			.map(|&h| to_friend(h))
+			.collect(),
+		bridge: bridge.map(|(handle, _)| to_friend(handle)),
+		bridged_circles: bridge.map_or(0, |(_, circles)| circles as i32)
+	}
+}
+
+/// Counts the connected components among `handle`'s co-members once
+/// `handle` is taken out of every group.
+fn circles_bridged(handle: i32, groups: &[(i32, Vec<i32>)]) -> usize {
+	let neighbors: HashSet<i32> = groups
+		.iter()
+		.filter(|(_, members)| members.contains(&handle))
+		.flat_map(|(_, members)| members.iter().copied())
+		.filter(|&member| member != handle)
+		.collect();
+
+	let mut parent: HashMap<i32, i32> = neighbors.iter().map(|&n| (n, n)).collect();
+	fn find(parent: &mut HashMap<i32, i32>, node: i32) -> i32 {
+		let next = parent[&node];
+		if next == node {
+			return node;
+		}
+		let root = find(parent, next);
+		parent.insert(node, root);
+		root
+	}
+
+	for (_, members) in groups {
+		let mut in_group = members.iter().copied().filter(|m| neighbors.contains(m));
+		let Some(first) = in_group.next() else { continue };
+		for member in in_group {
+			let (a, b) = (find(&mut parent, first), find(&mut parent, member));
+			parent.insert(a, b);
+		}
+	}
+
+	neighbors.iter().filter(|&&n| find(&mut parent, n) == n).count()
+}
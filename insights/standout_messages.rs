@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::{associated_guid, is_added_tapback, is_tapback, unix_seconds, Sources};
+use crate::options::PrivacyLevel;
+use crate::stats::stats::{StandoutMessage, StandoutMessages};
+
+/// My message with the most tapbacks and my message that started the longest
+/// reply thread. Removed tapbacks cancel out added ones.
+pub fn standout_messages(messages: &[Message], sources: &Sources) -> StandoutMessages {
+	let mine: HashMap<&str, &Message> = messages
+		.iter()
+		.filter(|m| m.is_from_me && !is_tapback(m))
+		.map(|m| (m.guid.as_str(), m))
+		.collect();
+
+	let mut reactions: HashMap<&str, i32> = HashMap::new();
+	let mut replies: HashMap<&str, i32> = HashMap::new();
+	for message in messages {
+		if is_tapback(message) {
+			if let Some(guid) = associated_guid(message).filter(|guid| mine.contains_key(guid)) {
+				*reactions.entry(guid).or_default() += if is_added_tapback(message) { 1 } else { -1 };
+			}
+		} else if let Some(guid) = message
+			.thread_originator_guid
+			.as_deref()
+			.filter(|guid| mine.contains_key(guid))
+		{
+			*replies.entry(guid).or_default() += 1;
+		}
+	}
+
+	let include_text = sources.options.privacy_level() == PrivacyLevel::Permissive;
+	let standout = |counts: HashMap<&str, i32>| {
+		let (guid, count) = counts
+			.into_iter()
+			.filter(|&(_, count)| count > 0)
+			.max_by_key(|&(guid, count)| (count, std::cmp::Reverse(guid)))?;
+		let message = mine[guid];
+		Some(StandoutMessage {
+			chat_name: message.chat_id.map(|id| sources.chat_name(id)).unwrap_or_default(),
+			chat_id: message.chat_id,
+			date: unix_seconds(message.date),
+			count,
+			text: include_text.then(|| message.text.clone()).flatten()
+		})
+	};
+
+	StandoutMessages { most_reacted: standout(reactions), most_replied: standout(replies) }
+}
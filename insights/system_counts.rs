@@ -0,0 +1,24 @@
+use imessage_database::tables::messages::Message;
+
+use crate::stats::stats::SystemMessageCounts;
+use crate::system_messages::{kind, SystemKind};
+
+/// How many group events and other system messages the year had. They're
+/// left out of every other stat. `None` when there were none.
+pub fn system_message_counts(system: &[Message]) -> Option<SystemMessageCounts> {
+	if system.is_empty() {
+		return None;
+	}
+
+	let mut counts =
+		SystemMessageCounts { member_changes: 0, renames: 0, group_events: 0, other: 0 };
+	for message in system {
+		match kind(message) {
+			Some(SystemKind::MemberChange) => counts.member_changes += 1,
+			Some(SystemKind::Rename) => counts.renames += 1,
+			Some(SystemKind::GroupEvent) => counts.group_events += 1,
+			Some(SystemKind::Other) | None => counts.other += 1
+		}
+	}
+	Some(counts)
+}
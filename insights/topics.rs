@@ -0,0 +1,114 @@
+use std::collections::{HashMap, HashSet};
+
+use imessage_database::tables::messages::Message;
+
+use super::words::{is_stop_word, words};
+use super::{top_contacts, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{ContactTopics, TopicCluster};
+
+const MAX_CLUSTERS: usize = 3;
+const MAX_CLUSTER_SIZE: usize = 4;
+const MIN_KEYWORD_COUNT: usize = 3;
+/// Share of a keyword's messages that must also contain the cluster seed
+const CLUSTER_AFFINITY: f64 = 0.2;
+
+/// Extracts "what you and X talk about" keyword clusters. Keywords are ranked
+/// by TF-IDF with each contact's conversation as one document, then grouped
+/// by how often they appear in the same messages.
+pub fn contact_topics(messages: &[Message], sources: &Sources) -> Vec<ContactTopics> {
+	let conversations = super::conversations_by_contact(messages, sources);
+	let keywords_per_contact = sources.options.top(TopList::TopicKeywords);
+
+	// Per-contact term counts plus, per term, the messages it appears in
+	let mut documents: HashMap<i32, (HashMap<String, usize>, HashMap<String, HashSet<usize>>)> =
+		HashMap::new();
+	for (&handle, conversation) in &conversations {
+		let (counts, occurrences) = documents.entry(handle).or_default();
+		for (index, message) in conversation.iter().enumerate() {
+			let Some(text) = message.text.as_deref() else { continue };
+			for word in words(text).filter(|w| w.len() >= 3 && !is_stop_word(w)) {
+				if word.chars().all(|c| c.is_ascii_digit()) {
+					continue;
+				}
+				*counts.entry(word.clone()).or_default() += 1;
+				occurrences.entry(word).or_default().insert(index);
+			}
+		}
+	}
+
+	let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+	for (counts, _) in documents.values() {
+		for word in counts.keys() {
+			*document_frequency.entry(word).or_default() += 1;
+		}
+	}
+	let document_count = documents.len() as f64;
+
+	top_contacts(&conversations, sources.options.top(TopList::TopicContacts))
+		.into_iter()
+		.filter_map(|handle| {
+			let (counts, occurrences) = documents.get(&handle)?;
+			let total: usize = counts.values().sum();
+
+			let mut scored: Vec<(&str, f64)> = counts
+				.iter()
+				.filter(|(_, &count)| count >= MIN_KEYWORD_COUNT)
+				.map(|(word, &count)| {
+					let tf = count as f64 / total as f64;
+					let idf = (document_count / document_frequency[word.as_str()] as f64).ln() + 1.0;
+					(word.as_str(), tf * idf)
+				})
+				.collect();
+			scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+			scored.truncate(keywords_per_contact);
+
+			let clusters = cluster_keywords(&scored, occurrences);
+			if clusters.is_empty() {
+				return None;
+			}
+
+			let (name, handle_id) = sources.person(handle);
+			Some(ContactTopics { name, handle_id, clusters, avatar: None })
+		})
+		.collect()
+}
+
+/// Greedily seeds clusters with the highest scoring unclustered keyword and
+/// pulls in keywords that frequently share messages with the seed.
+fn cluster_keywords(
+	scored: &[(&str, f64)], occurrences: &HashMap<String, HashSet<usize>>
+) -> Vec<TopicCluster> {
+	let mut used: HashSet<&str> = HashSet::new();
+	let mut clusters = Vec::new();
+
+	for &(seed, _) in scored {
+		if clusters.len() == MAX_CLUSTERS {
+			break;
+		}
+		if !used.insert(seed) {
+			continue;
+		}
+
+		let seed_messages = &occurrences[seed];
+		let mut keywords = vec![seed.to_string()];
+		for &(candidate, _) in scored {
+			if keywords.len() == MAX_CLUSTER_SIZE {
+				break;
+			}
+			if used.contains(candidate) {
+				continue;
+			}
+			let candidate_messages = &occurrences[candidate];
+			let shared = candidate_messages.intersection(seed_messages).count();
+			if shared as f64 / candidate_messages.len() as f64 >= CLUSTER_AFFINITY {
+				used.insert(candidate);
+				keywords.push(candidate.to_string());
+			}
+		}
+
+		clusters.push(TopicCluster { keywords });
+	}
+
+	clusters
+}
@@ -0,0 +1,51 @@
+use imessage_database::tables::messages::Message;
+
+use super::words::words;
+use super::{top_contacts, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{WordBalance, WordBalanceStats};
+
+/// Words needed in a conversation before it can be the most lopsided
+const MIN_WORDS: i32 = 200;
+
+/// Share of the words in each top one-on-one conversation that I wrote.
+/// Unlike message counts this catches the essay writer texting the "lol"
+/// replier.
+pub fn word_balance(messages: &[Message], sources: &Sources) -> WordBalanceStats {
+	let conversations = super::conversations_by_contact(messages, sources);
+
+	let limit = sources.options.top(TopList::Contacts);
+	let contacts: Vec<WordBalance> = top_contacts(&conversations, limit)
+		.into_iter()
+		.filter_map(|handle| {
+			let (mut my_words, mut their_words) = (0i32, 0i32);
+			for message in &conversations[&handle] {
+				let Some(text) = message.text.as_deref() else { continue };
+				let count = words(text).count() as i32;
+				if message.is_from_me { my_words += count } else { their_words += count }
+			}
+			let total = my_words + their_words;
+			if total == 0 {
+				return None;
+			}
+
+			let (name, handle_id) = sources.person(handle);
+			Some(WordBalance {
+				name,
+				handle_id,
+				my_words,
+				their_words,
+				my_share: my_words as f64 / total as f64,
+				avatar: None
+			})
+		})
+		.collect();
+
+	let most_lopsided = contacts
+		.iter()
+		.filter(|b| b.my_words + b.their_words >= MIN_WORDS)
+		.max_by(|a, b| (a.my_share - 0.5).abs().total_cmp(&(b.my_share - 0.5).abs()))
+		.cloned();
+
+	WordBalanceStats { contacts, most_lopsided }
+}
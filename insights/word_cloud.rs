@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use imessage_database::tables::messages::Message;
+
+use super::words::{is_stop_word, words};
+use super::{is_tapback, top_items, Sources};
+use crate::options::TopList;
+use crate::stats::stats::{WordCloud, WordCloudWord};
+
+/// Longer "words" are nearly always links or keyboard mashing
+const MAX_WORD_LENGTH: usize = 20;
+
+/// The words I typed most, weighted against the most used one. Stop words,
+/// numbers, words under three letters and the remains of links are left out.
+pub fn word_cloud(messages: &[Message], sources: &Sources) -> WordCloud {
+	let mut cloud = WordCounts::default();
+	for message in messages {
+		cloud.add(message);
+	}
+	cloud.finish(sources)
+}
+
+/// Word counts, filled one message at a time so the fused engine can share
+/// its scan.
+#[derive(Default)]
+pub struct WordCounts {
+	counts: HashMap<String, i32>
+}
+
+impl WordCounts {
+	pub fn add(&mut self, message: &Message) {
+		if !message.is_from_me || is_tapback(message) {
+			return;
+		}
+		let Some(text) = message.text.as_deref() else { return };
+		for word in words(text).filter(|word| is_cloud_word(word)) {
+			*self.counts.entry(word).or_default() += 1;
+		}
+	}
+
+	pub fn finish(self, sources: &Sources) -> WordCloud {
+		let items = top_items(self.counts, sources.options.top(TopList::WordCloud));
+		let most = items.first().map_or(1, |item| item.count) as f32;
+		let words = items
+			.into_iter()
+			.map(|item| WordCloudWord {
+				weight: item.count as f32 / most,
+				word: item.key,
+				count: item.count
+			})
+			.collect();
+		WordCloud { words }
+	}
+}
+
+fn is_cloud_word(word: &str) -> bool {
+	let length = word.chars().count();
+	(3..=MAX_WORD_LENGTH).contains(&length) &&
+		!is_stop_word(word) &&
+		!word.starts_with("http") &&
+		!word.chars().all(|c| c.is_numeric())
+}
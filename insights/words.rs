@@ -0,0 +1,103 @@
+use std::collections::HashSet;
+
+/// Common English words that carry no topic on their own.
+const STOP_WORDS: &[&str] = &[
+	"a", "about", "after", "again", "all", "also", "am", "an", "and", "any", "are", "as", "at",
+	"be", "because", "been", "before", "being", "but", "by", "can", "could", "did", "do", "does",
+	"doing", "dont", "for", "from", "get", "got", "had", "has", "have", "he", "her", "here", "him",
+	"his", "how", "i", "if", "im", "in", "into", "is", "it", "its", "ive", "just", "know", "like",
+	"me", "more", "my", "no", "not", "now", "of", "oh", "ok", "okay", "on", "one", "or", "our",
+	"out", "really", "she", "so", "some", "that", "thats", "the", "their", "them", "then", "there",
+	"they", "think", "this", "to", "too", "u", "up", "was", "we", "well", "were", "what", "when",
+	"where", "which", "who", "why", "will", "with", "would", "yeah", "yes", "you", "your", "youre"
+];
+
+/// Replies that carry no content beyond acknowledging the other person.
+const DRY_REPLIES: &[&str] = &["k", "kk", "lmao", "lol", "mhm", "ok", "okay", "sure", "ya", "ye"];
+
+/// Built-in profanity list, extended by `FetchOptions::extra_profanity`.
+pub const PROFANITY: &[&str] = &[
+	"ass", "asshole", "bastard", "bitch", "bitches", "bullshit", "crap", "damn", "dick", "dickhead",
+	"fuck", "fucked", "fucker", "fucking", "goddamn", "hell", "motherfucker", "piss", "pissed",
+	"shit", "shitty", "stfu", "wtf"
+];
+
+/// A set of words to look for in messages: a built-in list plus whatever the
+/// user configured.
+pub struct WordList {
+	words: HashSet<String>
+}
+
+impl WordList {
+	pub fn new(builtin: &[&str], extra: &[String]) -> Self {
+		let words = builtin
+			.iter()
+			.map(|word| word.to_string())
+			.chain(extra.iter().map(|word| word.to_lowercase()))
+			.collect();
+		Self { words }
+	}
+
+	/// Returns every word of `text` that is on the list.
+	pub fn matches<'a>(&'a self, text: &'a str) -> impl Iterator<Item = String> + 'a {
+		words(text).filter(|word| self.words.contains(word))
+	}
+}
+
+pub fn is_stop_word(word: &str) -> bool {
+	STOP_WORDS.binary_search(&word).is_ok()
+}
+
+/// Splits text into lowercase words, dropping punctuation and apostrophes so
+/// "Don't" and "dont" count as the same word.
+pub fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+	text.split_whitespace()
+		.map(|word| {
+			word.chars()
+				.filter(|c| c.is_alphanumeric())
+				.flat_map(char::to_lowercase)
+				.collect::<String>()
+		})
+		.filter(|word| !word.is_empty())
+}
+
+/// Whether the whole message is a bare acknowledgement like "k" or "lol".
+pub fn is_dry_reply(text: &str) -> bool {
+	let mut words = words(text);
+	match (words.next(), words.next()) {
+		(Some(word), None) => DRY_REPLIES.binary_search(&word.as_str()).is_ok(),
+		_ => false
+	}
+}
+
+pub fn is_emoji(c: char) -> bool {
+	matches!(
+		c as u32,
+		0x1F300..=0x1F5FF | // Symbols & pictographs
+		0x1F600..=0x1F64F | // Emoticons
+		0x1F680..=0x1F6FF | // Transport & map
+		0x1F900..=0x1F9FF | // Supplemental symbols & pictographs
+		0x1FA70..=0x1FAFF | // Symbols & pictographs extended-A
+		0x2600..=0x26FF | // Miscellaneous symbols
+		0x2700..=0x27BF // Dingbats
+	)
+}
+
+/// Emoji in the text, skipping skin tone modifiers so 👍🏽 counts as 👍.
+pub fn emojis(text: &str) -> impl Iterator<Item = char> + '_ {
+	text.chars().filter(|&c| is_emoji(c) && !matches!(c as u32, 0x1F3FB..=0x1F3FF))
+}
+
+/// Whether the text is nothing but emoji (ignoring whitespace and the joiners
+/// and modifiers that build up composite emoji).
+pub fn is_emoji_only(text: &str) -> bool {
+	let mut has_emoji = false;
+	for c in text.chars().filter(|c| !c.is_whitespace()) {
+		match c as u32 {
+			0x200D | 0xFE0F | 0x20E3 | 0x1F1E6..=0x1F1FF => {}
+			_ if is_emoji(c) => has_emoji = true,
+			_ => return false
+		}
+	}
+	has_emoji
+}
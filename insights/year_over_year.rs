@@ -0,0 +1,86 @@
+use crate::options::{FetchOptions, TopList};
+use crate::stats::stats::{YearOverYear, YearStats, YearsStats};
+
+/// Fills `year_over_year` for every year whose previous year is also in
+/// `stats`. Runs on the finished stats, so it needs no messages.
+pub fn apply(stats: &mut YearsStats, options: &FetchOptions) {
+	let emojis = options.top(TopList::ComparedEmojis);
+	let previous: Vec<Option<YearStats>> = stats
+		.stats
+		.iter()
+		.map(|current| stats.stats.iter().find(|s| s.year == current.year - 1).cloned())
+		.collect();
+
+	for (current, previous) in stats.stats.iter_mut().zip(previous) {
+		if let Some(previous) = previous {
+			current.year_over_year = Some(compare(&previous, current, emojis));
+		}
+	}
+}
+
+fn compare(previous: &YearStats, current: &YearStats, emojis: usize) -> YearOverYear {
+	let total = |year: &YearStats| year.message_count.as_ref().map_or(0, |c| c.sent + c.received);
+	let (previous_total, current_total) = (total(previous), total(current));
+
+	let top_contact = top_contact(current);
+	let previous_top_contact = top_contact(previous);
+	let previous_emojis = top_emojis(previous, emojis);
+	let current_emojis = top_emojis(current, emojis);
+
+	YearOverYear {
+		previous_year: previous.year,
+		previous_message_count: Some(previous.message_count.clone().unwrap_or_default()),
+		percent_change: if previous_total > 0 {
+			(current_total - previous_total) as f32 / previous_total as f32 * 100.0
+		} else {
+			0.0
+		},
+		new_top_contact: top_contact.is_some() && top_contact != previous_top_contact,
+		top_contact,
+		previous_top_contact,
+		my_average_reply_seconds: my_average_reply_seconds(current),
+		previous_my_average_reply_seconds: my_average_reply_seconds(previous),
+		new_emojis: current_emojis
+			.iter()
+			.filter(|emoji| !previous_emojis.contains(emoji))
+			.cloned()
+			.collect(),
+		dropped_emojis: previous_emojis
+			.iter()
+			.filter(|emoji| !current_emojis.contains(emoji))
+			.cloned()
+			.collect()
+	}
+}
+
+fn top_contact(year: &YearStats) -> Option<String> {
+	year.top_individual_chats
+		.as_ref()?
+		.chats
+		.iter()
+		.max_by_key(|chat| chat.sent + chat.received)
+		.map(|chat| chat.name.clone())
+}
+
+fn top_emojis(year: &YearStats, limit: usize) -> Vec<String> {
+	year.word_count
+		.iter()
+		.flat_map(|count| &count.emojis)
+		.flat_map(|emojis| &emojis.sent)
+		.take(limit)
+		.map(|item| item.key.clone())
+		.collect()
+}
+
+/// My reply time across everyone in the leaderboard, weighted by replies.
+fn my_average_reply_seconds(year: &YearStats) -> Option<i64> {
+	let contacts = &year.response_time_leaderboard.as_ref()?.contacts;
+	let replies: i64 = contacts.iter().map(|pair| i64::from(pair.my_replies)).sum();
+	if replies == 0 {
+		return None;
+	}
+
+	let total: i64 =
+		contacts.iter().map(|pair| pair.my_average_seconds * i64::from(pair.my_replies)).sum();
+	Some(total / replies)
+}
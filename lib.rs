@@ -1,43 +1,110 @@
 #![warn(clippy::all)]
 
-use std::io::Write;
 use std::path::Path;
-use std::time::{Duration, Instant, SystemTime};
-use std::{env, fs, io};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use std::{fs, io};
 
-use aes_gcm::aead::{Aead, KeyInit};
-use aes_gcm::{Aes256Gcm, Nonce};
+use attachments::Attachments;
+use chats::Chats;
 use base64::engine::general_purpose::URL_SAFE;
 use base64::Engine as _;
-use brotli::enc::writer::CompressorWriter;
-use brotli::enc::BrotliEncoderParams;
+use batch::BatchPerson;
 use connection::{
 	get_address_book_db_connections, get_chat_db_connection, init_sqlite, shutdown_sqlite
 };
+use contact_details::ContactDetails;
 use contacts::{Contact, Contacts};
 use from_query::QueryAll;
 use handles::Handles;
 use hex;
 use imessage_database::error::table::TableError;
 use imessage_database::tables::messages::Message;
+#[cfg(not(target_env = "msvc"))]
 use jemallocator::Jemalloc;
+use link_previews::LinkPreviews;
+use napi::bindgen_prelude::Buffer;
 use napi_derive::napi;
+use options::{DateRange, FetchOptions, TopList, TopSizes};
+use progress::{ProgressCallback, Reporter};
 use prost::Message as ProstMessage;
 use rand::Rng;
+use report::UploadReport;
+use rusqlite::Connection;
+use schema::{Schema, SchemaVersion};
+use serde::Serialize;
 use sha2::{Digest, Sha256};
-use stats::stats::YearsStats;
+use stats::stats::{GroupWrapped, YearsStats};
+use syndication::Syndicated;
 use thiserror::Error;
+use transport::{HttpTransport, Transport, UploadCallback};
+use warnings::Warning;
 
+// jemalloc doesn't build with MSVC, Windows uses the system allocator
+#[cfg(not(target_env = "msvc"))]
 #[global_allocator]
 static GLOBAL: Jemalloc = Jemalloc;
 
+mod address_book;
+mod archive;
+mod attachments;
+mod attestation;
+mod automated;
+mod batch;
+mod build_info;
+mod busy;
+mod cache;
+mod cards;
+mod chats;
+#[cfg(feature = "cli")]
+pub mod cli;
+mod comparison;
+mod compression;
 mod connection;
+mod contact_details;
 mod contacts;
+mod dedup;
+mod demo;
+mod envelope;
+mod exclusions;
 mod extensions;
 mod from_query;
+mod graph_export;
 mod handles;
+mod html_report;
+mod icloud_history;
+mod identities;
+mod importers;
+mod ingest;
+mod insights;
+mod link_previews;
+mod low_impact;
 mod message;
+mod network;
+mod options;
+mod paths;
+mod payload_sizes;
+mod pdf_report;
+mod permissions;
+mod photo_strip;
+mod progress;
+mod provenance;
+mod readonly;
+mod report;
+mod run_lock;
+mod schema;
+mod schema_doc;
+mod shares;
 mod stats;
+mod storage;
+mod supplemental;
+mod syndication;
+mod system_messages;
+mod test_vector;
+mod timestamps;
+pub mod transport;
+mod typescript;
+mod warnings;
 
 #[derive(Error, Debug)]
 pub enum AnalyzerError {
@@ -51,7 +118,13 @@ pub enum AnalyzerError {
 	Sql(#[from] rusqlite::Error),
 
 	#[error(transparent)]
-	Image(#[from] image::ImageError)
+	Image(#[from] image::ImageError),
+
+	#[error(transparent)]
+	Json(#[from] serde_json::Error),
+
+	#[error("Stats are already being generated")]
+	AlreadyRunning
 }
 
 impl From<TableError> for AnalyzerError {
@@ -60,17 +133,46 @@ impl From<TableError> for AnalyzerError {
 	}
 }
 
+impl From<AnalyzerError> for napi::Error {
+	fn from(value: AnalyzerError) -> Self {
+		napi::Error::from_reason(value.to_string())
+	}
+}
+
 pub type AnalyzerResult<T> = Result<T, AnalyzerError>;
 
+/// `errorType` reported to the app for a failed run.
+fn error_type(err: &AnalyzerError, fallback: &'static str) -> &'static str {
+	match err {
+		AnalyzerError::AlreadyRunning => "already_running",
+		_ => fallback
+	}
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct AnalysisTiming {
 	chat_db_time: Duration,
 	messages_query_time: Duration,
 	contacts_time: Duration,
 	handles_time: Duration,
+	attachments_time: Duration,
+	chats_time: Duration,
+	link_previews_time: Duration,
+	syndication_time: Duration,
 	total_time: Duration
 }
 
+/// Size of the upload payload at each stage of the pipeline.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PayloadMetrics {
+	original_size: usize,
+	compressed_size: usize,
+	encrypted_size: usize,
+	codec: &'static str,
+	compression_ratio: f64
+}
+
 #[derive(Debug)]
 struct StatsGenerationTiming {
 	year_time: Duration,
@@ -99,111 +201,266 @@ struct StatsGenerationTiming {
 	degenerate_time: Duration,
 }
 
+/// Stats generated by `prepare_upload` that are waiting for the user to
+/// confirm the upload.
+struct PendingUpload {
+	stats: YearsStats,
+	api_url: String,
+	transport: Box<dyn Transport>
+}
+
+static PENDING_UPLOAD: Mutex<Option<PendingUpload>> = Mutex::new(None);
+
+/// Wrappeds generated by `fetch_stats_batch`, by person label, that are
+/// waiting for the user to confirm the upload.
+struct PendingBatch {
+	people: Vec<(String, YearsStats)>,
+	api_url: String,
+	transport: Box<dyn Transport>
+}
+
+static PENDING_BATCH: Mutex<Option<PendingBatch>> = Mutex::new(None);
+
+/// Everything loaded from chat.db and the AddressBook.
+pub struct ImessageData {
+	pub messages: Vec<Message>,
+	/// Group events and other system messages, kept out of `messages`
+	pub system: Vec<Message>,
+	pub contacts: Contacts,
+	pub contact_details: ContactDetails,
+	pub handles: Handles,
+	pub attachments: Attachments,
+	pub chats: Chats,
+	pub link_previews: LinkPreviews,
+	pub syndicated: Syndicated,
+	pub warnings: Vec<Warning>,
+	/// Release that wrote chat.db, as far as its columns tell
+	pub schema: SchemaVersion,
+	pub timing: AnalysisTiming
+}
+
+/// Loads everything the analysis needs from chat.db and the AddressBook.
+/// Messages from any of `options.my_handles` (detected when unset) count as
+/// sent. Without AddressBook access the run continues with handles as names
+/// and a `contacts_unavailable` warning.
 pub fn gather_imessage_data<P>(
-	path: P, address_book_path: P
-) -> AnalyzerResult<(Vec<Message>, Contacts, Handles, AnalysisTiming)>
+	path: P, address_book_path: P, options: &FetchOptions, progress: &Reporter
+) -> AnalyzerResult<ImessageData>
 where
 	P: AsRef<Path>
 {
 	let total_start = Instant::now();
 
+	progress.report("openingDatabase", 0.0);
 	let chat_db = get_chat_db_connection(path)?;
+	readonly::enforce(&chat_db)?;
+	busy::configure(&chat_db)?;
+	let schema = Schema::probe(&chat_db)?;
 	let chat_db_time = total_start.elapsed();
 
+	progress.report("loadingMessages", 5.0);
 	let messages_start = Instant::now();
-	let mut messages = Message::query_all(&chat_db, [])?;
+	let range = options.date_range()?;
+	let mut messages = busy::with_retry(progress, || {
+		load_messages(&chat_db, &range, options.low_impact(), progress)
+	})?;
+	let merged = supplemental::merge_into(&chat_db, &mut messages, progress)?;
+	if !range.is_all_time() {
+		messages.retain(|m| range.contains(m.date));
+	}
+	let corrected_dates = timestamps::normalize(&mut messages);
+	let undated = timestamps::remove_undated(&mut messages);
+	let duplicates = dedup::remove_duplicates(&mut messages);
 	messages.sort_by_key(|m| m.date);
+	let my_handles = match &options.my_handles {
+		Some(handles) => handles.clone(),
+		None => busy::with_retry(progress, || identities::detect(&chat_db, &schema))?
+			.into_iter()
+			.map(|i| i.handle)
+			.collect()
+	};
+	let mine = identities::handle_rowids(&chat_db, &my_handles)?;
+	let claimed = identities::claim_messages(&mut messages, &mine);
+	let (mut messages, mut system) = system_messages::split(messages);
 	let messages_query_time = messages_start.elapsed();
 
+	progress.report("loadingContacts", 40.0);
 	let contacts_start = Instant::now();
-	let address_book_dbs = get_address_book_db_connections(address_book_path.as_ref())?;
-	let contacts = Contacts::new(&address_book_dbs, address_book_path.as_ref())?;
-	for conn in address_book_dbs {
-		let _ = conn.close();
-	}
+	let mut warnings = Vec::new();
+	let sources = options.address_book_sources.as_deref();
+	let loaded = load_contacts(address_book_path.as_ref(), sources, &my_handles);
+	let (contacts, contact_details) = match loaded {
+		Ok(loaded) => loaded,
+		Err(e) => {
+			eprintln!("Continuing without contacts: {:?}", e);
+			warnings.push(Warning::new(
+				"contacts_unavailable",
+				"Contacts couldn't be read, people are shown by phone number or email"
+			));
+			(Contacts::new(&Vec::new(), address_book_path.as_ref())?, ContactDetails::default())
+		}
+	};
 	let contacts_time = contacts_start.elapsed();
+	if corrected_dates > 0 {
+		warnings.push(Warning::new(
+			"timestamps_corrected",
+			format!("{} messages had out-of-range dates that were corrected", corrected_dates)
+		));
+	}
+	if merged > 0 {
+		warnings.push(Warning::new(
+			"supplemental_merged",
+			format!("{} messages were added from supplemental databases", merged)
+		));
+	}
+	if claimed > 0 {
+		warnings.push(Warning::new(
+			"identities_claimed",
+			format!("{} messages from my other handles were counted as sent", claimed)
+		));
+	}
+	if undated > 0 {
+		warnings.push(Warning::new(
+			"skipped_rows",
+			format!("{} messages have no date and were left out", undated)
+		));
+	}
+	if duplicates > 0 {
+		warnings.push(Warning::new(
+			"duplicates_removed",
+			format!("{} duplicate messages were left out", duplicates)
+		));
+	}
 
+	progress.report("loadingHandles", 45.0);
 	let handles_start = Instant::now();
-	let handles = Handles::new(&chat_db)?;
+	let handles = busy::with_retry(progress, || Ok(Handles::new(&chat_db)?))?;
 	let handles_time = handles_start.elapsed();
 
+	progress.report("loadingAttachments", 48.0);
+	let attachments_start = Instant::now();
+	let attachments = busy::with_retry(progress, || Attachments::new(&chat_db))?;
+	let attachments_time = attachments_start.elapsed();
+
+	progress.report("loadingChats", 52.0);
+	let chats_start = Instant::now();
+	let chats = busy::with_retry(progress, || Chats::new(&chat_db))?;
+	let chats_time = chats_start.elapsed();
+
+	let excluded = exclusions::apply(&mut messages, &handles, &chats, options) +
+		exclusions::apply(&mut system, &handles, &chats, options);
+	if excluded > 0 {
+		warnings.push(Warning::new(
+			"excluded_messages",
+			format!("{} messages from excluded contacts and chats were left out", excluded)
+		));
+	}
+
+	progress.report("loadingLinkPreviews", 55.0);
+	let link_previews_start = Instant::now();
+	let link_previews = busy::with_retry(progress, || LinkPreviews::new(&chat_db, &schema))?;
+	let link_previews_time = link_previews_start.elapsed();
+
+	progress.report("loadingSharedWithYou", 58.0);
+	let syndication_start = Instant::now();
+	let syndicated = busy::with_retry(progress, || Syndicated::new(&chat_db, &schema))?;
+	let syndication_time = syndication_start.elapsed();
+
 	let _ = chat_db.close();
 
-	Ok((
+	Ok(ImessageData {
 		messages,
+		system,
 		contacts,
+		contact_details,
 		handles,
-		AnalysisTiming {
+		attachments,
+		chats,
+		link_previews,
+		syndicated,
+		warnings,
+		schema: schema.version,
+		timing: AnalysisTiming {
 			chat_db_time,
 			messages_query_time,
 			contacts_time,
 			handles_time,
+			attachments_time,
+			chats_time,
+			link_previews_time,
+			syndication_time,
 			total_time: total_start.elapsed()
 		}
-	))
+	})
 }
 
-fn encrypt_data(data: &[u8]) -> AnalyzerResult<(Vec<u8>, Vec<u8>)> {
-	let mut compressed = Vec::new();
-	{
-		let params = BrotliEncoderParams { quality: 11, lgwin: 22, ..Default::default() };
+/// Collects the streamed messages. The vector grows with what's actually in
+/// range rather than being sized for the whole table, then gets trimmed, so
+/// memory still scales with the messages in range.
+fn load_messages(
+	chat_db: &Connection, range: &DateRange, paced: bool, progress: &Reporter
+) -> AnalyzerResult<Vec<Message>> {
+	let mut messages = Vec::new();
+	ingest::stream(chat_db, range, paced, progress, |message| messages.push(message))?;
+	messages.shrink_to_fit();
 
-		let mut compressor = CompressorWriter::with_params(
-			&mut compressed,
-			4096, // buffer size
-			&params
-		);
+	Ok(messages)
+}
 
-		compressor.write_all(data)?;
-		compressor.flush()?;
-		// Ensure all data is written
-		drop(compressor);
+/// Loads contacts from every AddressBook source, or only from `sources`.
+/// `my_handles` find my own card, whose related names are my relationships.
+fn load_contacts(
+	address_book_path: &Path, sources: Option<&[String]>, my_handles: &[String]
+) -> AnalyzerResult<(Contacts, ContactDetails)> {
+	let address_book_dbs = match sources {
+		Some(ids) => address_book::open_sources(address_book_path, ids)?,
+		None => get_address_book_db_connections(address_book_path)?
+	};
+	for db in &address_book_dbs {
+		readonly::enforce(db)?;
+	}
+	let contacts = Contacts::new(&address_book_dbs, address_book_path)?;
+	let contact_details = ContactDetails::new(&address_book_dbs, my_handles);
+	for conn in address_book_dbs {
+		let _ = conn.close();
 	}
+	Ok((contacts, contact_details))
+}
 
-	println!(
-		"Rust encryption - Original size: {}, Compressed size: {}",
-		data.len(),
-		compressed.len()
-	);
+fn encrypt_data(data: &[u8]) -> AnalyzerResult<(Vec<u8>, Vec<u8>, PayloadMetrics)> {
+	let compressed = compression::compress(data)?;
 
 	// Generate random key
 	let mut rng = rand::thread_rng();
 	let mut key_bytes = [0u8; 32];
 	rng.fill(&mut key_bytes);
 
-	let cipher = Aes256Gcm::new_from_slice(&key_bytes)
-		.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+	let encrypted = envelope::seal(&key_bytes, &compressed)?;
 
-	// Use fixed IV of all zeros
-	let iv = Nonce::from_slice(&[0u8; 12]);
-	let encrypted = cipher
-		.encrypt(iv, compressed.as_ref())
-		.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
-
-	println!(
-		"Rust encryption - Key length: {}, Encrypted size: {}, Key bytes: {:?}",
-		key_bytes.len(),
-		encrypted.len(),
-		&key_bytes
-	);
+	let metrics = PayloadMetrics {
+		original_size: data.len(),
+		compressed_size: compressed.len(),
+		encrypted_size: encrypted.len(),
+		codec: "brotli",
+		compression_ratio: if data.is_empty() {
+			1.0
+		} else {
+			compressed.len() as f64 / data.len() as f64
+		}
+	};
 
-	println!(
-		"Rust encryption - First 32 bytes of encrypted data: {:?}",
-		&encrypted[..32.min(encrypted.len())]
-	);
+	Ok((key_bytes.to_vec(), encrypted, metrics))
+}
 
-	Ok((key_bytes.to_vec(), encrypted))
+/// Reverses `encrypt_data`: opens the envelope and decompresses the payload
+/// back to the encoded `YearsStats`.
+pub fn decrypt_data(key: &[u8], data: &[u8]) -> AnalyzerResult<Vec<u8>> {
+	compression::decompress(&envelope::open(key, data)?)
 }
 
 pub async fn send_stats(
-	stats: &YearsStats, api_url: Option<String>
-) -> AnalyzerResult<(String, String, Duration, Duration)> {
-	let base_url = api_url.unwrap_or_else(|| String::from("https://messageswrapped.com"));
-	let upload_url = format!("{}/api/upload", base_url);
-
-	let db_path = Path::new(&env::var("HOME").unwrap()).join("Library/Messages/chat.db");
-	let chat_db = get_chat_db_connection(&db_path)?;
-
+	stats: &YearsStats, transport: &dyn Transport, reuse_unchanged: bool
+) -> AnalyzerResult<(String, String, Duration, Duration, Option<PayloadMetrics>)> {
 	// let phone_number = chat_db
 	// 	.prepare(
 	// 		"SELECT account FROM message WHERE service = 'SMS' AND account LIKE 'P:+%'
@@ -226,213 +483,789 @@ pub async fn send_stats(
 	// hex::encode(&hasher.finalize()[..8]); print!("Rust - Final hash: {}",
 	// hashed_phone);
 
-	let stats_bytes = stats.encode_to_vec();
+	send_payload(&stats.encode_to_vec(), &stats.years, None, transport, reuse_unchanged).await
+}
+
+/// Encrypts and uploads an encoded payload covering `years`, and for a group
+/// chat wrapped the chat's GUID as `group_chat`.
+async fn send_payload(
+	stats_bytes: &[u8], years: &[i32], group_chat: Option<&str>, transport: &dyn Transport,
+	reuse_unchanged: bool
+) -> AnalyzerResult<(String, String, Duration, Duration, Option<PayloadMetrics>)> {
+	let payload_hash = hex::encode(Sha256::digest(stats_bytes));
+
+	if let (true, Some(server)) = (reuse_unchanged, transport.server()) {
+		match shares::find_unchanged(server, years, group_chat, &payload_hash) {
+			// Callers see the reuse as `metrics` being `None`
+			Ok(Some(receipt)) => {
+				let key_base64 = receipt.key().to_string();
+				return Ok((receipt.share_url, key_base64, Duration::ZERO, Duration::ZERO, None));
+			}
+			Ok(None) => {}
+			Err(e) => eprintln!("Failed to read share receipts: {:?}", e)
+		}
+	}
 
 	let encryption_start = Instant::now();
-	let original_size = stats_bytes.len();
-	let (key, encrypted_data) = encrypt_data(&stats_bytes)?;
-	println!(
-		"Original size: {}, Compressed + Encrypted size: {}, Reduction: {:.1}%",
-		original_size,
-		encrypted_data.len(),
-		(1.0 - (encrypted_data.len() as f64 / original_size as f64)) * 100.0
-	);
+	let (key, encrypted_data, metrics) = encrypt_data(stats_bytes)?;
 	let encryption_time = encryption_start.elapsed();
 
 	let upload_start = Instant::now();
+	let delivery = transport.send(encrypted_data).await?;
 
-	let client = reqwest::Client::new();
-	println!("Encrypted data size: {}", encrypted_data.len());
-	let response = client
-		.post(&upload_url)
-		.timeout(Duration::from_secs(30))
-		.header("Content-Type", "application/octet-stream")
-		.body(encrypted_data)
-		.send()
-		.await
-		.map_err(|e| {
-			let error_msg = if e.is_timeout() {
-				format!("Request timed out while uploading to {}", upload_url)
-			} else if e.is_connect() {
-				format!(
-					"Failed to connect to {}. Please check your internet connection",
-					upload_url
-				)
-			} else {
-				format!("Upload failed: {} (URL: {})", e, upload_url)
-			};
-			io::Error::new(io::ErrorKind::Other, error_msg)
-		})?;
-
-	if !response.status().is_success() {
-		let status = response.status();
-		let error_body = response.text().await.unwrap_or_default();
-		return Err(io::Error::new(
-			io::ErrorKind::Other,
-			format!(
-				"Upload failed with status {}: {}. Server response: {}",
-				status,
-				status.canonical_reason().unwrap_or("Unknown error"),
-				if error_body.is_empty() {
-					"No error details provided"
-				} else {
-					&error_body
+	let key_base64 = URL_SAFE.encode(key);
+	let share_url = transport.share_url(&delivery, &key_base64);
+
+	// Remember the share so purge_all_data can delete it later. Without an id
+	// and delete token the server wouldn't accept the delete, so there is
+	// nothing worth keeping
+	match (transport.server(), delivery.delete_token) {
+		(Some(server), Some(delete_token)) if !delivery.id.is_empty() => {
+			if let Err(e) = shares::record(shares::ShareReceipt::new(
+				delivery.id,
+				server.to_string(),
+				share_url.clone(),
+				Some(delete_token),
+				years.to_vec(),
+				payload_hash,
+				group_chat.map(String::from)
+			)) {
+				eprintln!("Failed to record share receipt: {:?}", e);
+			}
+		}
+		(Some(_), _) => {
+			eprintln!("Server returned no delete token, share {} can't be purged", delivery.id)
+		}
+		(None, _) => {}
+	}
+
+	let upload_time = upload_start.elapsed();
+
+	Ok((share_url, key_base64, encryption_time, upload_time, Some(metrics)))
+}
+
+/// Kept for the app's existing call path: analyzes chat.db and prepares the
+/// upload exactly like `prepare_upload`, so nothing is sent until
+/// `confirm_upload` is called. `on_progress` is called with the current stage
+/// and overall percentage as the run goes.
+#[napi(ts_return_type = "Promise<Json<Response<PrepareUploadData>>>")]
+pub async fn fetch_stats(
+	api_url: String, options: Option<FetchOptions>, on_progress: Option<ProgressCallback>,
+	upload_callback: Option<UploadCallback>
+) -> napi::Result<String> {
+	prepare_upload(api_url, options, on_progress, upload_callback).await
+}
+
+/// Runs the whole analysis for each of `people` in turn, e.g. family members
+/// sharing a Mac, and prepares a separate wrapped for each. Like
+/// `prepare_upload`, every person gets a report of what would be uploaded
+/// and nothing is sent until `confirm_batch_upload` is called. The runs share
+/// the analysis cache, which keeps one slot per chat.db. One person failing
+/// doesn't stop the others, every person gets their own result. Progress
+/// updates carry the person's label.
+#[napi(ts_return_type = "Promise<Json<Response<BatchData>>>")]
+pub async fn fetch_stats_batch(
+	api_url: String, people: Vec<BatchPerson>, options: Option<FetchOptions>,
+	on_progress: Option<ProgressCallback>, upload_callback: Option<UploadCallback>
+) -> napi::Result<String> {
+	let options = options.unwrap_or_default();
+	let progress = Reporter::new(on_progress);
+	let transport = transport::select(api_url.clone(), &options, upload_callback)?;
+
+	let mut results = Vec::new();
+	let mut prepared = Vec::new();
+	for person in &people {
+		let options = batch::options_for(&options, person);
+		let progress = progress.for_person(&person.label);
+		let result = match generate_stats(&options, &progress) {
+			Ok((year_stats, warnings, _)) => {
+				let result = serde_json::json!({
+					"label": person.label,
+					"success": true,
+					"data": {
+						"report": UploadReport::new(&year_stats),
+						"partial": year_stats.stats.iter().any(|s| !s.skipped_stats.is_empty()),
+						"warnings": warnings
+					}
+				});
+				prepared.push((person.label.clone(), year_stats));
+				result
+			}
+			Err(err) => {
+				eprintln!("Analysis error details for {}: {:?}", person.label, err);
+				serde_json::json!({
+					"label": person.label,
+					"success": false,
+					"error": {
+						"message": format!("Failed to analyze messages: {}", err),
+						"details": {
+							"errorType": error_type(&err, "analysis_failed"),
+							"fullError": format!("{:?}", err)
+						}
+					}
+				})
+			}
+		};
+		results.push(result);
+	}
+	let batch = PendingBatch { people: prepared, api_url, transport };
+	*PENDING_BATCH.lock().unwrap() = Some(batch).filter(|batch| !batch.people.is_empty());
+
+	Ok(serde_json::json!({
+		"success": true,
+		"data": {
+			"people": results
+		}
+	})
+	.to_string())
+}
+
+/// Uploads the wrappeds prepared by the last `fetch_stats_batch` call, one
+/// share per person.
+#[napi(ts_return_type = "Promise<Json<Response<BatchUploadedData>>>")]
+pub async fn confirm_batch_upload() -> napi::Result<String> {
+	let Some(pending) = PENDING_BATCH.lock().unwrap().take() else {
+		return Ok(serde_json::json!({
+			"success": false,
+			"error": {
+				"message": "There is no prepared batch to confirm",
+				"details": {
+					"errorType": "no_pending_upload"
 				}
-			)
-		)
-		.into());
+			}
+		})
+		.to_string());
+	};
+
+	let mut results = Vec::new();
+	for (label, year_stats) in &pending.people {
+		let result = match send_stats(year_stats, pending.transport.as_ref(), true).await {
+			Ok((share_url, encryption_key, _, _, metrics)) => serde_json::json!({
+				"label": label,
+				"success": true,
+				"data": {
+					"shareUrl": share_url,
+					"encryptionKey": encryption_key,
+					"metrics": metrics,
+					"build": year_stats.build
+				}
+			}),
+			Err(err) => {
+				eprintln!("Upload error details for {}: {:?}", label, err);
+				serde_json::json!({
+					"label": label,
+					"success": false,
+					"error": {
+						"message": format!("Failed to upload the wrapped: {}", err),
+						"url": pending.api_url,
+						"details": {
+							"errorType": "upload_failed",
+							"fullError": format!("{:?}", err)
+						}
+					}
+				})
+			}
+		};
+		results.push(result);
 	}
 
-	let response_data: serde_json::Value = response
-		.json()
-		.await
-		.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+	Ok(serde_json::json!({
+		"success": true,
+		"data": {
+			"people": results
+		}
+	})
+	.to_string())
+}
 
-	let key_base64 = URL_SAFE.encode(key);
-	let share_url = format!(
-		"{}/s/{}#{}",
-		base_url,
-		response_data["id"].as_str().unwrap_or_default(),
-		key_base64,
-		// &hashed_phone[..16] // Use first 16 chars of hash
-	);
+/// Deletes every share recorded on this machine from the server, then removes
+/// all local state, including the archive key in the Keychain. Receipts whose
+/// remote deletion failed are kept so the call can be retried. When the
+/// receipts can't be read nothing is removed, the shares they list could
+/// never be deleted otherwise. Fails with "already_running" while a run is in
+/// progress, which would write its cache and archive back afterwards.
+#[napi(ts_return_type = "Promise<Json<{ success: boolean; data: PurgeData }>>")]
+pub async fn purge_all_data() -> napi::Result<String> {
+	let _lock = run_lock::acquire(&Reporter::default())?;
+	let receipts = shares::load()?;
 
-	let upload_time = upload_start.elapsed();
+	let mut remote_deleted = 0;
+	let mut remaining = Vec::new();
+	let mut failures = Vec::new();
+	for receipt in receipts {
+		match shares::delete_remote(&receipt).await {
+			Ok(()) => remote_deleted += 1,
+			Err(e) => {
+				failures.push(serde_json::json!({
+					"id": receipt.id,
+					"error": e.to_string()
+				}));
+				remaining.push(receipt);
+			}
+		}
+	}
 
-	Ok((share_url, key_base64, encryption_time, upload_time))
+	let pending_discarded = PENDING_UPLOAD.lock().unwrap().take().is_some() |
+		PENDING_BATCH.lock().unwrap().take().is_some();
+	let local_removed = storage::remove_all().map_err(AnalyzerError::from)?;
+	archive::forget_device_key()?;
+	if !remaining.is_empty() {
+		shares::save(&remaining)?;
+	}
+
+	Ok(serde_json::json!({
+		"success": failures.is_empty(),
+		"data": {
+			"remoteDeleted": remote_deleted,
+			"remoteFailures": failures,
+			"localDataRemoved": local_removed,
+			"pendingUploadDiscarded": pending_discarded
+		}
+	})
+	.to_string())
 }
 
-#[napi]
-pub async fn fetch_stats(api_url: String) -> napi::Result<String> {
-	let api_url_clone = api_url.clone();
-	let total_start = SystemTime::now();
+/// What the analysis cache has for a run on `db_path`, `None` when it's
+/// turned off or can't be read, which only means analyzing everything.
+fn cache_plan(options: &FetchOptions, db_path: &Path) -> Option<cache::Plan> {
+	if !options.cache.unwrap_or(true) {
+		return None;
+	}
+	match cache::plan(db_path, options) {
+		Ok(plan) => Some(plan),
+		Err(e) => {
+			eprintln!("Not using the analysis cache: {:?}", e);
+			None
+		}
+	}
+}
+
+/// Runs the full analysis without uploading anything. Returns the stats with
+/// the run's warnings and a report of where the time went.
+fn generate_stats(
+	options: &FetchOptions, progress: &Reporter
+) -> AnalyzerResult<(YearsStats, Vec<Warning>, String)> {
+	low_impact::run(options.low_impact(), || analyze(options, progress))
+}
 
+/// `generate_stats` on whichever thread low-impact mode picks.
+fn analyze(
+	options: &FetchOptions, progress: &Reporter
+) -> AnalyzerResult<(YearsStats, Vec<Warning>, String)> {
+	let total_start = Instant::now();
+	let _lock = run_lock::acquire(progress)?;
+	let deadline = options.time_budget().map(|budget| Instant::now() + budget);
 	// Create a guard that ensures SQLite is properly shut down
 	let _guard = scopeguard::guard((), |()| shutdown_sqlite());
-
 	let sqlite_start = Instant::now();
 	init_sqlite();
 	let sqlite_init_time = sqlite_start.elapsed();
 
-	let db_path = Path::new(&env::var("HOME").unwrap()).join("Library/Messages/chat.db");
+	let db_path = options.chat_db_path();
+	let address_book_path = options.address_book_path();
 
-	let address_book_path =
-		Path::new(&env::var("HOME").unwrap()).join("Library/Application Support/AddressBook");
+	let (reused, chat_db_state) = match cache_plan(options, &db_path) {
+		Some(cache::Plan::Unchanged(mut year_stats)) => {
+			build_info::stamp(&mut year_stats);
+			progress.report("done", 100.0);
+			let timing = "chat.db is unchanged since the cached run, its stats were reused";
+			return Ok((year_stats, Vec::new(), timing.to_string()));
+		}
+		Some(cache::Plan::Partial { reused, chat_db }) => (reused, Some(chat_db)),
+		Some(cache::Plan::Full(chat_db)) => (Vec::new(), Some(chat_db)),
+		None => (Vec::new(), None)
+	};
+	let reused_years = reused.len();
+	let resumed = cache::resume_options(options, &reused);
 
 	let analysis_start = Instant::now();
-	let result = match gather_imessage_data(&db_path, &address_book_path) {
-		Ok((messages, contacts, handles, timing)) => {
-			let analysis_time = analysis_start.elapsed();
-
-			let stats_start = Instant::now();
-			let (year_stats, stats_timing) =
-				stats::get_all_yearly_stats(&messages, &contacts, &handles);
-			let stats_time = stats_start.elapsed();
-
-			// Drop large data structures
-			drop(messages);
-			drop(contacts);
-			drop(handles);
-
-			match send_stats(&year_stats, Some(api_url)).await {
-				Ok((share_url, encryption_key, encryption_time, upload_time)) => {
-					let timing_info = format!(
-						"\
-						=== System Info ===\nChat.db Size: {:.2} MB\n\n=== Initial Setup ===\nSQLite Init: \
-						 {:?}\n\n=== Gather iMessage Data Phase ===\nDB Connection: \
-						 {:?}\nMessages Query: {:?}\nContacts Load: {:?}\nHandles Load: \
-						 {:?}\nTotal Analysis Time: {:?}\nTotal Gather iMessage Data Time: \
-						 {:?}\n\n=== Stats Generation Phase ===\nBy Year: {:?}\nBy Month: \
-						 {:?}\nBy Weekday: {:?}\nBy Hour: {:?}\nTop Sent Texts: {:?}\nWords and \
-						 Emojis: {:?}\nMessages Per Day: {:?}\nMessage Length: {:?}\nMost \
-						 Reactions: {:?}\nResponse Time: {:?}\nChat Stats: {:?}\nLeft on Read: \
-						 {:?}\nSlurs: {:?}\nReactionner Time: {:?}\nFavor Time: {:?}\nFreaky \
-						 Time: {:?}\nDouble Text Time: {:?}\nLongest Texting Sessions: \
-						 {:?}\nGroup Chat Slurs: {:?}\nSend/Received Ratio: {:?}\nRealest Friend: \
-						 {:?}\nTotal Stats Generation: {:?}\n\n=== Final Phase ===\nEncryption \
-						 Time: {:?}\nUpload Time: {:?}\nTotal Encryption & Upload Time: \
-						 {:?}\n\n=== Total Time Breakdown ===\nSQLite Init: {:?}\nGather iMessage \
-						 Data: {:?}\nStats Generation: {:?}\nEncryption: {:?}\nUpload: {:?}\nSum \
-						 of All Phases: {:?}\nTotal Time: {:?}\nDirty Mouth: {:?}\nDegenerate Phrases: \
-						 {:?}",
-						get_chat_db_size()? as f64,
-						sqlite_init_time,
-						timing.chat_db_time,
-						timing.messages_query_time,
-						timing.contacts_time,
-						timing.handles_time,
-						timing.total_time,
-						analysis_time,
-						stats_timing.year_time,
-						stats_timing.month_time,
-						stats_timing.weekday_time,
-						stats_timing.hour_time,
-						stats_timing.top_sent_time,
-						stats_timing.words_emoji_time,
-						stats_timing.messages_per_day_time,
-						stats_timing.message_length_time,
-						stats_timing.reactions_time,
-						stats_timing.response_time,
-						stats_timing.chat_stats_time,
-						stats_timing.left_on_read_time,
-						stats_timing.slurs_time,
-						stats_timing.reactionner_time,
-						stats_timing.favor_time,
-						stats_timing.freaky_time,
-						stats_timing.double_text_time,
-						stats_timing.session_time,
-						stats_timing.group_chat_slurs_time,
-						stats_timing.send_received_ratio_time,
-						stats_timing.realest_time,
-						stats_time,
-						encryption_time,
-						upload_time,
-						upload_time + encryption_time,
-						sqlite_init_time,
-						analysis_time,
-						stats_time,
-						encryption_time,
-						upload_time,
-						sqlite_init_time +
-							analysis_time + stats_time +
-							encryption_time + upload_time,
-						total_start.elapsed().unwrap_or_default(),
-						stats_timing.dirty_mouth_time,
-						stats_timing.degenerate_time
-					);
-
-					serde_json::json!({
-						"success": true,
-						"data": {
-							"shareUrl": share_url,
-							"encryptionKey": encryption_key,
-						},
-						"timing": timing_info
-					})
-					.to_string()
+	let ImessageData {
+		messages,
+		system,
+		contacts,
+		contact_details,
+		handles,
+		attachments,
+		chats,
+		link_previews,
+		syndicated,
+		mut warnings,
+		schema,
+		timing
+	} = gather_imessage_data(&db_path, &address_book_path, &resumed, progress)?;
+	let analysis_time = analysis_start.elapsed();
+	let (messages, automated) = automated::split(messages, &contacts, &handles, &chats, options);
+
+	progress.report("computingStats", 60.0);
+	let stats_start = Instant::now();
+	let (mut year_stats, stats_timing) =
+		stats::get_all_yearly_stats(&messages, &contacts, &handles);
+	let insights_start = Instant::now();
+	let insight_timings = insights::apply(
+		&mut year_stats,
+		&insights::Sources {
+			messages: &messages,
+			automated: &automated,
+			system: &system,
+			contacts: &contacts,
+			contact_details: &contact_details,
+			handles: &handles,
+			attachments: &attachments,
+			chats: &chats,
+			link_previews: &link_previews,
+			syndicated: &syndicated,
+			options
+		},
+		deadline,
+		progress
+	);
+	cache::merge(&mut year_stats, reused, options);
+	if let Some(chat_db_state) = chat_db_state {
+		if let Err(e) = cache::store(&year_stats, chat_db_state, options) {
+			eprintln!("Failed to cache stats: {:?}", e);
+		}
+	}
+	build_info::stamp(&mut year_stats);
+	warnings::collect(&mut warnings, &messages, &contacts, &handles, &year_stats);
+	let insights_time = insights_start.elapsed();
+	let stats_time = stats_start.elapsed();
+
+	let provenance_path = if options.provenance.unwrap_or(false) {
+		provenance::write(&year_stats, &messages)
+			.map_err(|e| eprintln!("Failed to write stat provenance: {:?}", e))
+			.ok()
+	} else {
+		None
+	};
+	if options.archive.unwrap_or(true) {
+		if let Err(e) = archive::store(&year_stats) {
+			eprintln!("Failed to archive stats: {:?}", e);
+		}
+	}
+	progress.report("done", 100.0);
+
+	let timing_info = format!(
+		"\
+		=== System Info ===\nChat.db Size: {:.2} MB\nChat.db Schema: {}\n\n=== Initial Setup \
+		 ===\nSQLite Init: {:?}\n\n=== Gather iMessage Data Phase ===\nDB Connection: \
+		 {:?}\nMessages Query: {:?}\nContacts Load: {:?}\nHandles Load: {:?}\nAttachments Load: \
+		 {:?}\nChats Load: {:?}\nLink Previews Load: {:?}\nShared with You Load: {:?}\nTotal \
+		 Analysis Time: {:?}\nTotal Gather iMessage Data Time: {:?}\n\n=== Stats Generation \
+		 Phase ===\nBy Year: {:?}\nBy Month: {:?}\nBy Weekday: {:?}\nBy Hour: {:?}\nTop Sent \
+		 Texts: {:?}\nWords and Emojis: {:?}\nMessages Per Day: {:?}\nMessage Length: {:?}\nMost \
+		 Reactions: {:?}\nResponse Time: {:?}\nChat Stats: {:?}\nLeft on Read: {:?}\nSlurs: \
+		 {:?}\nReactionner Time: {:?}\nFavor Time: {:?}\nFreaky Time: {:?}\nDouble Text Time: \
+		 {:?}\nLongest Texting Sessions: {:?}\nGroup Chat Slurs: {:?}\nSend/Received Ratio: \
+		 {:?}\nRealest Friend: {:?}\nDirty Mouth: {:?}\nDegenerate Phrases: {:?}\nInsights: \
+		 {:?}\nTotal Stats Generation: {:?}\n\n=== Total Time Breakdown ===\nSQLite Init: \
+		 {:?}\nGather iMessage Data: {:?}\nStats Generation: {:?}\nTotal Time: {:?}\nYears \
+		 Reused From Cache: {}",
+		file_size_mb(&db_path),
+		schema,
+		sqlite_init_time,
+		timing.chat_db_time,
+		timing.messages_query_time,
+		timing.contacts_time,
+		timing.handles_time,
+		timing.attachments_time,
+		timing.chats_time,
+		timing.link_previews_time,
+		timing.syndication_time,
+		timing.total_time,
+		analysis_time,
+		stats_timing.year_time,
+		stats_timing.month_time,
+		stats_timing.weekday_time,
+		stats_timing.hour_time,
+		stats_timing.top_sent_time,
+		stats_timing.words_emoji_time,
+		stats_timing.messages_per_day_time,
+		stats_timing.message_length_time,
+		stats_timing.reactions_time,
+		stats_timing.response_time,
+		stats_timing.chat_stats_time,
+		stats_timing.left_on_read_time,
+		stats_timing.slurs_time,
+		stats_timing.reactionner_time,
+		stats_timing.favor_time,
+		stats_timing.freaky_time,
+		stats_timing.double_text_time,
+		stats_timing.session_time,
+		stats_timing.group_chat_slurs_time,
+		stats_timing.send_received_ratio_time,
+		stats_timing.realest_time,
+		stats_timing.dirty_mouth_time,
+		stats_timing.degenerate_time,
+		insights_time,
+		stats_time,
+		sqlite_init_time,
+		analysis_time,
+		stats_time,
+		total_start.elapsed(),
+		reused_years
+	);
+	let timing_info = insight_timings.iter().fold(
+		format!("{}\n\n=== Insight Passes ===", timing_info),
+		|info, (name, time)| format!("{}\n{}: {:?}", info, name, time)
+	);
+	let timing_info = match provenance_path {
+		Some(path) => format!("{}\n\nStat Provenance: {}", timing_info, path.display()),
+		None => timing_info
+	};
+
+	Ok((year_stats, warnings, timing_info))
+}
+
+/// Runs the analysis for a group chat wrapped. Returns it with the chat's GUID,
+/// or `None` when the chat isn't a group chat with messages in `year`.
+fn generate_group_wrapped(
+	options: &FetchOptions, chat_id: i32, year: i32, progress: &Reporter
+) -> AnalyzerResult<Option<(GroupWrapped, String)>> {
+	let _lock = run_lock::acquire(progress)?;
+	let _guard = scopeguard::guard((), |()| shutdown_sqlite());
+	init_sqlite();
+
+	let ImessageData {
+		messages,
+		system,
+		contacts,
+		contact_details,
+		handles,
+		attachments,
+		chats,
+		link_previews,
+		syndicated,
+		..
+	} = gather_imessage_data(
+		&options.chat_db_path(),
+		&options.address_book_path(),
+		options,
+		progress
+	)?;
+	let (messages, automated) = automated::split(messages, &contacts, &handles, &chats, options);
+	progress.report("computingInsights", 80.0);
+	let sources = insights::Sources {
+		messages: &messages,
+		automated: &automated,
+		system: &system,
+		contacts: &contacts,
+		contact_details: &contact_details,
+		handles: &handles,
+		attachments: &attachments,
+		chats: &chats,
+		link_previews: &link_previews,
+		syndicated: &syndicated,
+		options
+	};
+	let wrapped = insights::group_wrapped(&sources, chat_id, year).map(|mut wrapped| {
+		wrapped.build = Some(build_info::current());
+		wrapped
+	});
+
+	Ok(wrapped.zip(chats.get(chat_id).map(|chat| chat.guid.clone())))
+}
+
+/// Generates a wrapped for one group chat (`chat_id` as in the `groupChats`
+/// of a wrapped) and uploads it as its own share, so everyone in the chat can
+/// open it. Nothing from my other chats is in it. Like `prepare_upload`, the
+/// payload goes to `api_url` unless `options.upload_transport` or
+/// `upload_callback` routes it elsewhere.
+#[napi(ts_return_type = "Promise<Json<Response<GroupWrappedData>>>")]
+pub async fn share_group_wrapped(
+	api_url: String, chat_id: i32, year: i32, options: Option<FetchOptions>,
+	on_progress: Option<ProgressCallback>, upload_callback: Option<UploadCallback>
+) -> napi::Result<String> {
+	let options = FetchOptions {
+		from: Some(format!("{}-01-01", year)),
+		to: Some(format!("{}-12-31", year)),
+		..options.unwrap_or_default()
+	};
+	let transport = transport::select_group(api_url, &options, upload_callback)?;
+	let progress = Reporter::new(on_progress);
+	let failure = |message: String, error_type: &str, full_error: String| {
+		serde_json::json!({
+			"success": false,
+			"error": {
+				"message": message,
+				"details": {
+					"errorType": error_type,
+					"fullError": full_error
 				}
-				Err(e) => {
-					eprintln!("Upload error details: {:?}", e);
-
-					serde_json::json!({
-						"success": false,
-						"error": {
-							"message": format!("Failed to generate your Messages Wrapped: {}", e),
-							"url": api_url_clone,
-							"details": {
-								"timestamp": SystemTime::now()
-									.duration_since(SystemTime::UNIX_EPOCH)
-									.unwrap_or_default()
-									.as_secs(),
-								"errorType": "upload_failed",
-								"fullError": format!("{:?}", e)
-							}
-						}
-					})
-					.to_string()
+			}
+		})
+		.to_string()
+	};
+
+	let (wrapped, guid) = match generate_group_wrapped(&options, chat_id, year, &progress) {
+		Ok(Some(generated)) => generated,
+		Ok(None) => {
+			return Ok(failure(
+				format!("No group chat {} with messages in {}", chat_id, year),
+				"group_chat_not_found",
+				String::new()
+			));
+		}
+		Err(err) => {
+			eprintln!("Analysis error details: {:?}", err);
+			return Ok(failure(
+				format!("Failed to analyze the group chat: {}", err),
+				error_type(&err, "analysis_failed"),
+				format!("{:?}", err)
+			));
+		}
+	};
+
+	progress.report("uploading", 95.0);
+	let upload =
+		send_payload(&wrapped.encode_to_vec(), &[year], Some(&guid), transport.as_ref(), true)
+			.await;
+	progress.report("done", 100.0);
+	let result = match upload {
+		Ok((share_url, encryption_key, _, _, metrics)) => serde_json::json!({
+			"success": true,
+			"data": {
+				"shareUrl": share_url,
+				"encryptionKey": encryption_key,
+				"metrics": metrics,
+				"wrapped": wrapped
+			}
+		})
+		.to_string(),
+		Err(e) => {
+			eprintln!("Upload error details: {:?}", e);
+			failure(
+				format!("Failed to share the group chat wrapped: {}", e),
+				"upload_failed",
+				format!("{:?}", e)
+			)
+		}
+	};
+
+	Ok(result)
+}
+
+/// Renders blurred ("blur", default) or pixelated ("mosaic") thumbnails of
+/// the year's most shared photos as one PNG strip for the local recap. Only
+/// returned to the app, never uploaded. `null` when no photo could be read.
+#[napi]
+pub fn render_photo_strip(
+	year: i32, count: Option<u32>, style: Option<String>
+) -> napi::Result<Option<Buffer>> {
+	let png = photo_strip::render(
+		&paths::chat_db(),
+		year,
+		count.unwrap_or(photo_strip::DEFAULT_COUNT),
+		photo_strip::Style::parse(style.as_deref())
+	)?;
+	Ok(png.map(Buffer::from))
+}
+
+/// Writes my messaging network as GraphML (default) or JSON for tools like
+/// Gephi. Identifiers are pseudonymized unless `pseudonymize` is false.
+#[napi(ts_return_type = "Json<Response<SocialGraphData>>")]
+pub fn export_social_graph(
+	out_path: String, format: Option<String>, pseudonymize: Option<bool>
+) -> napi::Result<String> {
+	let _guard = scopeguard::guard((), |()| shutdown_sqlite());
+	init_sqlite();
+
+	let db_path = paths::chat_db();
+	let address_book_path = paths::address_book();
+
+	let ImessageData { messages, contacts, handles, chats, .. } = gather_imessage_data(
+		&db_path,
+		&address_book_path,
+		&FetchOptions::default(),
+		&Reporter::default()
+	)?;
+	let (messages, _) =
+		automated::split(messages, &contacts, &handles, &chats, &FetchOptions::default());
+	let graph = graph_export::SocialGraph::new(
+		&messages,
+		&contacts,
+		&handles,
+		&chats,
+		pseudonymize.unwrap_or(true)
+	);
+	graph.write(Path::new(&out_path), format.as_deref().unwrap_or("graphml"))?;
+
+	Ok(serde_json::json!({
+		"success": true,
+		"data": {
+			"path": out_path,
+			"nodes": graph.nodes.len(),
+			"edges": graph.edges.len()
+		}
+	})
+	.to_string())
+}
+
+/// Generates stats and returns a report of what would be uploaded. Nothing
+/// leaves the machine until `confirm_upload` is called.
+#[napi(ts_return_type = "Promise<Json<Response<PrepareUploadData>>>")]
+pub async fn prepare_upload(
+	api_url: String, options: Option<FetchOptions>, on_progress: Option<ProgressCallback>,
+	upload_callback: Option<UploadCallback>
+) -> napi::Result<String> {
+	let options = options.unwrap_or_default();
+	let progress = Reporter::new(on_progress);
+	let transport = transport::select(api_url.clone(), &options, upload_callback)?;
+	let result = match generate_stats(&options, &progress) {
+		Ok((year_stats, warnings, timing)) => {
+			let report = UploadReport::new(&year_stats);
+			let partial = year_stats.stats.iter().any(|s| !s.skipped_stats.is_empty());
+			*PENDING_UPLOAD.lock().unwrap() =
+				Some(PendingUpload { stats: year_stats, api_url, transport });
+
+			serde_json::json!({
+				"success": true,
+				"data": {
+					"report": report,
+					"partial": partial,
+					"warnings": warnings
+				},
+				"timing": timing
+			})
+			.to_string()
+		}
+		Err(err) => {
+			eprintln!("Analysis error details: {:?}", err);
+			serde_json::json!({
+				"success": false,
+				"error": {
+					"message": format!("Failed to analyze messages: {}", err),
+					"details": {
+						"errorType": error_type(&err, "analysis_failed"),
+						"fullError": format!("{:?}", err)
+					}
+				}
+			})
+			.to_string()
+		}
+	};
+
+	Ok(result)
+}
+
+/// Encrypts the stats prepared by the last `prepare_upload` call exactly as an
+/// upload would, then decrypts and decodes them again and returns the result,
+/// so the app can show precisely what would be shared.
+#[napi(ts_return_type = "Json<Response<RoundtripData>>")]
+pub fn verify_upload_roundtrip() -> napi::Result<String> {
+	let pending = PENDING_UPLOAD.lock().unwrap();
+	let Some(pending) = pending.as_ref() else {
+		return Ok(serde_json::json!({
+			"success": false,
+			"error": {
+				"message": "There is no prepared upload to verify",
+				"details": {
+					"errorType": "no_pending_upload"
 				}
 			}
+		})
+		.to_string());
+	};
+
+	let stats_bytes = pending.stats.encode_to_vec();
+	let (key, encrypted, metrics) = encrypt_data(&stats_bytes)?;
+	let decrypted = decrypt_data(&key, &encrypted)?;
+	let decoded = YearsStats::decode(decrypted.as_slice())
+		.map_err(|e| AnalyzerError::from(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+
+	Ok(serde_json::json!({
+		"success": true,
+		"data": {
+			"matches": decrypted == stats_bytes,
+			"metrics": metrics,
+			"stats": decoded
 		}
+	})
+	.to_string())
+}
+
+/// Emits a known-plaintext test vector: fixed sample stats encrypted through
+/// the production code path with every intermediate value, so reviewers can
+/// verify the payload format independently.
+#[napi(ts_return_type = "Json<Response<TestVector>>")]
+pub fn export_test_vector() -> napi::Result<String> {
+	Ok(serde_json::json!({
+		"success": true,
+		"data": test_vector::generate()?
+	})
+	.to_string())
+}
+
+/// Uploads the stats generated by the last `prepare_upload` call.
+#[napi(ts_return_type = "Promise<Json<Response<UploadedData>>>")]
+pub async fn confirm_upload() -> napi::Result<String> {
+	let Some(pending) = PENDING_UPLOAD.lock().unwrap().take() else {
+		return Ok(serde_json::json!({
+			"success": false,
+			"error": {
+				"message": "There is no prepared upload to confirm",
+				"details": {
+					"errorType": "no_pending_upload"
+				}
+			}
+		})
+		.to_string());
+	};
+
+	let result = match send_stats(&pending.stats, pending.transport.as_ref(), true).await {
+		Ok((share_url, encryption_key, _, _, metrics)) => serde_json::json!({
+			"success": true,
+			"data": {
+				"shareUrl": share_url,
+				"encryptionKey": encryption_key,
+				"metrics": metrics,
+				"build": pending.stats.build,
+			}
+		})
+		.to_string(),
+		Err(e) => {
+			eprintln!("Upload error details: {:?}", e);
+			serde_json::json!({
+				"success": false,
+				"error": {
+					"message": format!("Failed to generate your Messages Wrapped: {}", e),
+					"url": pending.api_url,
+					"details": {
+						"errorType": "upload_failed",
+						"fullError": format!("{:?}", e)
+					}
+				}
+			})
+			.to_string()
+		}
+	};
+
+	Ok(result)
+}
+
+/// Discards prepared uploads, single or batch, without sending them.
+#[napi]
+pub fn cancel_upload() -> napi::Result<bool> {
+	let single = PENDING_UPLOAD.lock().unwrap().take().is_some();
+	Ok(PENDING_BATCH.lock().unwrap().take().is_some() | single)
+}
+
+/// Runs the full analysis and returns the stats as JSON. Never calls
+/// `send_stats` or opens a network connection, so the data provably stays on
+/// this machine.
+#[napi(ts_return_type = "Json<Response<LocalStatsData>>")]
+pub fn fetch_stats_local(
+	options: Option<FetchOptions>, on_progress: Option<ProgressCallback>
+) -> napi::Result<String> {
+	let progress = Reporter::new(on_progress);
+	let result = match generate_stats(&options.unwrap_or_default(), &progress) {
+		Ok((year_stats, warnings, _)) => serde_json::json!({
+			"success": true,
+			"data": {
+				"stats": year_stats,
+				"warnings": warnings
+			}
+		})
+		.to_string(),
 		Err(err) => {
 			eprintln!("Analysis error details: {:?}", err);
 			serde_json::json!({
@@ -440,11 +1273,51 @@ pub async fn fetch_stats(api_url: String) -> napi::Result<String> {
 				"error": {
 					"message": format!("Failed to analyze messages: {}", err),
 					"details": {
-						"timestamp": SystemTime::now()
-							.duration_since(SystemTime::UNIX_EPOCH)
-							.unwrap_or_default()
-							.as_secs(),
-						"errorType": "analysis_failed",
+						"errorType": error_type(&err, "analysis_failed"),
+						"fullError": format!("{:?}", err)
+					}
+				}
+			})
+			.to_string()
+		}
+	};
+
+	Ok(result)
+}
+
+/// Generates stats and writes the full `YearsStats` as pretty-printed JSON to
+/// `path` so it can be inspected or kept. Nothing is uploaded.
+#[napi(ts_return_type = "Json<Response<ExportStatsData>>")]
+pub fn export_stats_json(
+	path: String, options: Option<FetchOptions>, on_progress: Option<ProgressCallback>
+) -> napi::Result<String> {
+	let progress = Reporter::new(on_progress);
+	let result = generate_stats(&options.unwrap_or_default(), &progress).and_then(
+		|(year_stats, warnings, _)| {
+			let json = serde_json::to_string_pretty(&year_stats)?;
+			fs::write(&path, &json)?;
+			Ok((json.len(), warnings))
+		}
+	);
+
+	let result = match result {
+		Ok((size, warnings)) => serde_json::json!({
+			"success": true,
+			"data": {
+				"path": path,
+				"size": size,
+				"warnings": warnings
+			}
+		})
+		.to_string(),
+		Err(err) => {
+			eprintln!("Export error details: {:?}", err);
+			serde_json::json!({
+				"success": false,
+				"error": {
+					"message": format!("Failed to export stats: {}", err),
+					"details": {
+						"errorType": error_type(&err, "export_failed"),
 						"fullError": format!("{:?}", err)
 					}
 				}
@@ -456,28 +1329,571 @@ pub async fn fetch_stats(api_url: String) -> napi::Result<String> {
 	Ok(result)
 }
 
+/// Renders protobuf-encoded `stats`, e.g. from `openArchiveEntry`, into one
+/// self-contained HTML page at `out_path` that opens offline and can be
+/// passed on as a file. Nothing is uploaded.
+#[napi(ts_return_type = "Json<Response<ReportHtmlData>>")]
+pub fn render_report_html(stats: Buffer, out_path: String) -> napi::Result<String> {
+	let result = YearsStats::decode(stats.as_ref())
+		.map_err(|e| AnalyzerError::from(io::Error::new(io::ErrorKind::InvalidData, e)))
+		.and_then(|stats| html_report::render(&stats))
+		.and_then(|html| {
+			fs::write(&out_path, &html)?;
+			Ok(html.len())
+		});
+
+	let result = match result {
+		Ok(size) => serde_json::json!({
+			"success": true,
+			"data": {
+				"path": out_path,
+				"size": size
+			}
+		}),
+		Err(err) => {
+			eprintln!("Report error details: {:?}", err);
+			serde_json::json!({
+				"success": false,
+				"error": {
+					"message": format!("Failed to render the report: {}", err),
+					"details": {
+						"errorType": error_type(&err, "export_failed"),
+						"fullError": format!("{:?}", err)
+					}
+				}
+			})
+		}
+	};
+
+	Ok(result.to_string())
+}
+
+/// Lays out protobuf-encoded `stats` as a paginated A4 PDF at `path`, every
+/// stat section of every year included, for keeping or printing offline.
+#[napi(ts_return_type = "Json<Response<ReportPdfData>>")]
+pub fn render_report_pdf(stats: Buffer, path: String) -> napi::Result<String> {
+	let result = YearsStats::decode(stats.as_ref())
+		.map_err(|e| AnalyzerError::from(io::Error::new(io::ErrorKind::InvalidData, e)))
+		.and_then(|stats| pdf_report::render(&stats))
+		.and_then(|pdf| {
+			fs::write(&path, &pdf)?;
+			Ok(pdf.len())
+		});
+
+	let result = match result {
+		Ok(size) => serde_json::json!({
+			"success": true,
+			"data": {
+				"path": path,
+				"size": size
+			}
+		}),
+		Err(err) => {
+			eprintln!("Report error details: {:?}", err);
+			serde_json::json!({
+				"success": false,
+				"error": {
+					"message": format!("Failed to render the PDF report: {}", err),
+					"details": {
+						"errorType": error_type(&err, "export_failed"),
+						"fullError": format!("{:?}", err)
+					}
+				}
+			})
+		}
+	};
+
+	Ok(result.to_string())
+}
+
+/// Draws PNG share cards (message count, top contact and top emoji for each
+/// year) from protobuf-encoded `stats` into `out_dir`, so a wrapped can be
+/// shared as images without the web service.
+#[napi(ts_return_type = "Json<Response<ShareCardsData>>")]
+pub fn render_share_cards(stats: Buffer, out_dir: String) -> napi::Result<String> {
+	let result = YearsStats::decode(stats.as_ref())
+		.map_err(|e| AnalyzerError::from(io::Error::new(io::ErrorKind::InvalidData, e)))
+		.and_then(|stats| cards::render_all(&stats, Path::new(&out_dir)));
+
+	let result = match result {
+		Ok(cards) => serde_json::json!({
+			"success": true,
+			"data": {
+				"cards": cards
+			}
+		}),
+		Err(err) => {
+			eprintln!("Share card error details: {:?}", err);
+			serde_json::json!({
+				"success": false,
+				"error": {
+					"message": format!("Failed to render share cards: {}", err),
+					"details": {
+						"errorType": error_type(&err, "export_failed"),
+						"fullError": format!("{:?}", err)
+					}
+				}
+			})
+		}
+	};
+
+	Ok(result.to_string())
+}
+
+/// Draws each year's word cloud from protobuf-encoded `stats` into `out_dir`
+/// as a PNG the size of a share card. Years without a word cloud are skipped.
+#[napi(ts_return_type = "Json<Response<ShareCardsData>>")]
+pub fn render_word_clouds(stats: Buffer, out_dir: String) -> napi::Result<String> {
+	let result = YearsStats::decode(stats.as_ref())
+		.map_err(|e| AnalyzerError::from(io::Error::new(io::ErrorKind::InvalidData, e)))
+		.and_then(|stats| cards::render_word_clouds(&stats, Path::new(&out_dir)));
+
+	let result = match result {
+		Ok(cards) => serde_json::json!({
+			"success": true,
+			"data": {
+				"cards": cards
+			}
+		}),
+		Err(err) => {
+			eprintln!("Word cloud error details: {:?}", err);
+			serde_json::json!({
+				"success": false,
+				"error": {
+					"message": format!("Failed to render word clouds: {}", err),
+					"details": {
+						"errorType": error_type(&err, "export_failed"),
+						"fullError": format!("{:?}", err)
+					}
+				}
+			})
+		}
+	};
+
+	Ok(result.to_string())
+}
+
+/// Generates stats from a built-in fictional chat.db so the app can show a
+/// full sample wrapped before asking for Full Disk Access. Nothing is read
+/// from the user's chat.db or AddressBook, and nothing is archived or cached.
+#[napi(ts_return_type = "Json<Response<LocalStatsData>>")]
+pub fn fetch_demo_stats(on_progress: Option<ProgressCallback>) -> napi::Result<String> {
+	let progress = Reporter::new(on_progress);
+	let demo_dir = std::env::temp_dir().join(format!("messages-wrapped-demo-{}", std::process::id()));
+	let _cleanup = scopeguard::guard(demo_dir.clone(), |dir| {
+		let _ = fs::remove_dir_all(dir);
+	});
+
+	let result = fs::create_dir_all(&demo_dir)
+		.map_err(AnalyzerError::from)
+		.and_then(|()| demo::write_database(&demo_dir.join("chat.db")))
+		.and_then(|()| {
+			let options = FetchOptions {
+				chat_db_path: Some(demo_dir.join("chat.db").to_string_lossy().into_owned()),
+				// Never fall back to the real AddressBook
+				address_book_path: Some(demo_dir.join("AddressBook").to_string_lossy().into_owned()),
+				my_handles: Some(vec![demo::MY_HANDLE.to_string()]),
+				archive: Some(false),
+				cache: Some(false),
+				..FetchOptions::default()
+			};
+			generate_stats(&options, &progress)
+		});
+
+	let result = match result {
+		Ok((year_stats, warnings, _)) => serde_json::json!({
+			"success": true,
+			"data": {
+				"stats": year_stats,
+				"warnings": warnings
+					.iter()
+					.filter(|warning| warning.code != "contacts_unavailable")
+					.collect::<Vec<_>>()
+			}
+		})
+		.to_string(),
+		Err(err) => {
+			eprintln!("Demo analysis error details: {:?}", err);
+			serde_json::json!({
+				"success": false,
+				"error": {
+					"message": format!("Failed to generate demo stats: {}", err),
+					"details": {
+						"errorType": error_type(&err, "demo_failed"),
+						"fullError": format!("{:?}", err)
+					}
+				}
+			})
+			.to_string()
+		}
+	};
+
+	Ok(result)
+}
+
+/// Describes the fields of `YearsStats` (names, numbers, types, units and
+/// notes from stats.proto) as JSON for the web viewer and other tools.
+#[napi]
+pub fn describe_stats_schema() -> napi::Result<String> {
+	Ok(serde_json::to_string(&schema_doc::describe()).map_err(AnalyzerError::from)?)
+}
+
+/// Returns TypeScript declarations for the JSON the other functions return,
+/// and writes them to `out_path` when given, e.g. from the app's build.
+#[napi]
+pub fn generate_type_definitions(out_path: Option<String>) -> napi::Result<String> {
+	let definitions = typescript::generate();
+	if let Some(out_path) = out_path {
+		fs::write(out_path, &definitions).map_err(AnalyzerError::from)?;
+	}
+	Ok(definitions)
+}
+
+/// Reports whether stats are being generated. When the run is in this process
+/// `on_progress` receives its progress from now on, e.g. after the user clicked
+/// "generate" a second time.
+#[napi(ts_return_type = "Json<Response<AttachData>>")]
+pub fn attach_to_run(on_progress: Option<ProgressCallback>) -> napi::Result<String> {
+	let attached = on_progress.is_some_and(run_lock::attach);
+
+	Ok(serde_json::json!({
+		"success": true,
+		"data": {
+			"running": attached || run_lock::is_running(),
+			"attached": attached
+		}
+	})
+	.to_string())
+}
+
+/// Lists previously generated runs stored in the local archive.
+#[napi(ts_return_type = "Json<ArchiveEntry[]>")]
+pub fn list_archive() -> napi::Result<String> {
+	Ok(serde_json::to_string(&archive::list()?).map_err(AnalyzerError::from)?)
+}
+
+/// Returns the protobuf-encoded `YearsStats` of an archived run, or `null` if
+/// it does not exist.
+#[napi]
+pub fn open_archive_entry(id: String) -> napi::Result<Option<Buffer>> {
+	Ok(archive::open(&id)?.map(|stats| stats.encode_to_vec().into()))
+}
+
+/// Uploads an archived run again without re-analyzing chat.db. By default the
+/// existing share is returned if the same stats were already uploaded; pass
+/// `force_new` to create a fresh share, e.g. when the old one expired.
+#[napi(ts_return_type = "Promise<Json<Response<UploadedData>>>")]
+pub async fn reshare_archive_entry(
+	id: String, api_url: String, force_new: Option<bool>
+) -> napi::Result<String> {
+	let Some(year_stats) = archive::open(&id)? else {
+		return Ok(serde_json::json!({
+			"success": false,
+			"error": {
+				"message": format!("No archived wrapped with id {}", id),
+				"details": {
+					"errorType": "archive_entry_not_found"
+				}
+			}
+		})
+		.to_string());
+	};
+
+	let transport = HttpTransport::new(Some(api_url.clone()));
+	let result = match send_stats(&year_stats, &transport, !force_new.unwrap_or(false)).await {
+		Ok((share_url, encryption_key, _, _, metrics)) => serde_json::json!({
+			"success": true,
+			"data": {
+				"shareUrl": share_url,
+				"encryptionKey": encryption_key,
+				"metrics": metrics,
+				"build": year_stats.build,
+			}
+		})
+		.to_string(),
+		Err(e) => {
+			eprintln!("Upload error details: {:?}", e);
+			serde_json::json!({
+				"success": false,
+				"error": {
+					"message": format!("Failed to share your archived Messages Wrapped: {}", e),
+					"url": api_url,
+					"details": {
+						"errorType": "upload_failed",
+						"fullError": format!("{:?}", e)
+					}
+				}
+			})
+			.to_string()
+		}
+	};
+
+	Ok(result)
+}
+
+/// Compares a year from one archived run with a year from another, entirely
+/// locally. When a year is not given the run's latest year is used. `top_sizes`
+/// sets how many risers are listed.
+#[napi(ts_return_type = "Json<YearComparison>")]
+pub fn compare_archived_years(
+	first_id: String, second_id: String, first_year: Option<i32>, second_year: Option<i32>,
+	top_sizes: Option<TopSizes>
+) -> napi::Result<String> {
+	let (Some(first), Some(second)) = (archive::open(&first_id)?, archive::open(&second_id)?) else {
+		return Err(napi::Error::from_reason("Archived wrapped not found"));
+	};
+	let (Some(first), Some(second)) = (
+		comparison::select_year(&first, first_year),
+		comparison::select_year(&second, second_year)
+	) else {
+		return Err(napi::Error::from_reason("Requested year is not in the archived wrapped"));
+	};
+
+	let options = FetchOptions { top_sizes, ..Default::default() };
+	let comparison = comparison::compare(first, second, options.top(TopList::Risers));
+	Ok(serde_json::to_string(&comparison).map_err(AnalyzerError::from)?)
+}
+
+#[napi]
+pub fn delete_archive_entry(id: String) -> napi::Result<bool> {
+	Ok(archive::delete(&id)?)
+}
+
+/// Deletes the incremental analysis cache so the next run analyzes every
+/// message again. Returns whether anything was cached.
+#[napi]
+pub fn invalidate_cache() -> napi::Result<bool> {
+	Ok(cache::invalidate().map_err(AnalyzerError::from)?)
+}
+
+/// Describes the incremental analysis cache: which years are cached, the
+/// chat.db watermark and whether chat.db changed since.
+#[napi(ts_return_type = "Json<Response<CacheStatus>>")]
+pub fn cache_status() -> napi::Result<String> {
+	let data = match cache::status()? {
+		Some((index, size)) => serde_json::json!({
+			"cached": true,
+			"current": cache::is_current(&index, &paths::chat_db()),
+			"years": index.years,
+			"watermark": index.chat_db.watermark,
+			"chatDbModified": index.chat_db.modified,
+			"sizeBytes": size
+		}),
+		None => serde_json::json!({ "cached": false })
+	};
+
+	Ok(serde_json::json!({
+		"success": true,
+		"data": data
+	})
+	.to_string())
+}
+
+/// Registers a copy of chat.db (e.g. an archive from before old messages were
+/// deleted) whose messages are merged into every future analysis.
+#[napi]
+pub fn add_supplemental_database(path: String) -> napi::Result<bool> {
+	Ok(supplemental::add(&path)?)
+}
+
+/// Converts an SMS Backup & Restore XML export from Android into a chat.db
+/// and AddressBook in the data directory. Analyze it by passing the returned
+/// `chatDbPath` and `addressBookPath` in the options.
+#[napi(ts_return_type = "Json<Response<Import>>")]
+pub fn import_android_backup(xml_path: String) -> napi::Result<String> {
+	let out_dir = storage::data_dir().join("imports/android");
+	let result = match importers::android_xml::import(Path::new(&xml_path), &out_dir) {
+		Ok(import) => serde_json::json!({
+			"success": true,
+			"data": import
+		}),
+		Err(e) => serde_json::json!({
+			"success": false,
+			"error": {
+				"message": format!("Failed to import the Android backup: {}", e),
+				"details": {
+					"errorType": error_type(&e, "import_failed"),
+					"fullError": format!("{:?}", e)
+				}
+			}
+		})
+	};
+
+	Ok(result.to_string())
+}
+
+/// Lists the iPhone backups Finder or iTunes made on this computer, most
+/// recent first.
+#[napi(ts_return_type = "Json<Response<IosBackupsData>>")]
+pub fn list_ios_backups() -> napi::Result<String> {
+	Ok(serde_json::json!({
+		"success": true,
+		"data": {
+			"backups": importers::ios_backup::list()?
+		}
+	})
+	.to_string())
+}
+
+/// Copies Messages and contacts out of an iPhone backup into the data
+/// directory, for when Messages in iCloud is off on this Mac. Encrypted
+/// backups need the backup `password`. Uses the most recent backup when
+/// `backup_id` is not given. Analyze it by passing the returned `chatDbPath`
+/// and `addressBookPath` in the options. The copy of an encrypted backup is
+/// stored decrypted, which `decrypted` flags, until `remove_ios_import` or
+/// `purge_all_data`.
+#[napi(ts_return_type = "Json<Response<Import>>")]
+pub fn import_ios_backup(
+	backup_id: Option<String>, password: Option<String>
+) -> napi::Result<String> {
+	let out_dir = storage::data_dir().join("imports/ios");
+	let imported =
+		importers::ios_backup::import(backup_id.as_deref(), password.as_deref(), &out_dir);
+	let result = match imported {
+		Ok(import) => serde_json::json!({
+			"success": true,
+			"data": import
+		}),
+		Err(e) => serde_json::json!({
+			"success": false,
+			"error": {
+				"message": format!("Failed to import the iPhone backup: {}", e),
+				"details": {
+					"errorType": error_type(&e, "import_failed"),
+					"fullError": format!("{:?}", e)
+				}
+			}
+		})
+	};
+
+	Ok(result.to_string())
+}
+
+/// Deletes what `import_ios_backup` copied out of a backup. Returns whether
+/// there was an import.
+#[napi]
+pub fn remove_ios_import() -> napi::Result<bool> {
+	let out_dir = storage::data_dir().join("imports/ios");
+	Ok(importers::ios_backup::remove(&out_dir)?)
+}
+
+#[napi]
+pub fn list_supplemental_databases() -> napi::Result<Vec<String>> {
+	Ok(supplemental::list()?)
+}
+
+#[napi]
+pub fn remove_supplemental_database(path: String) -> napi::Result<bool> {
+	Ok(supplemental::remove(&path)?)
+}
+
+/// Lists the handles that look like mine (phone number and Apple ID emails),
+/// most used first, so the user can confirm them and pass them back as
+/// `myHandles`.
+#[napi(ts_return_type = "Json<Response<IdentitiesData>>")]
+pub fn detect_my_identities() -> napi::Result<String> {
+	let _guard = scopeguard::guard((), |()| shutdown_sqlite());
+	init_sqlite();
+
+	let db_path = paths::chat_db();
+	let chat_db = get_chat_db_connection(&db_path).map_err(AnalyzerError::from)?;
+	readonly::enforce(&chat_db)?;
+	busy::configure(&chat_db)?;
+	let schema = Schema::probe(&chat_db)?;
+	let identities =
+		busy::with_retry(&Reporter::default(), || identities::detect(&chat_db, &schema))?;
+	let _ = chat_db.close();
+
+	Ok(serde_json::json!({
+		"success": true,
+		"data": {
+			"identities": identities
+		}
+	})
+	.to_string())
+}
+
+/// Looks for signs that most of the history is in Messages in iCloud and
+/// was never downloaded to this Mac, and returns a warning the app can show
+/// when the wrapped is likely to be incomplete.
+#[napi(ts_return_type = "Json<Response<ChatDbDiagnosis>>")]
+pub fn diagnose_chat_db(options: Option<FetchOptions>) -> napi::Result<String> {
+	let _guard = scopeguard::guard((), |()| shutdown_sqlite());
+	init_sqlite();
+
+	let db_path = options.unwrap_or_default().chat_db_path();
+	let chat_db = get_chat_db_connection(&db_path).map_err(AnalyzerError::from)?;
+	readonly::enforce(&chat_db)?;
+	busy::configure(&chat_db)?;
+	let schema = Schema::probe(&chat_db)?;
+	let diagnosis =
+		busy::with_retry(&Reporter::default(), || icloud_history::diagnose(&chat_db, &schema))?;
+	let _ = chat_db.close();
+
+	Ok(serde_json::json!({ "success": true, "data": diagnosis }).to_string())
+}
+
+/// Reports how the analyzer guarantees it never writes to the user's
+/// databases, and whether any write was ever attempted.
+#[napi(ts_return_type = "Json<Response<ReadOnlyAttestation>>")]
+pub fn read_only_attestation() -> napi::Result<String> {
+	Ok(serde_json::json!({
+		"success": true,
+		"data": readonly::attestation()
+	})
+	.to_string())
+}
+
 #[napi]
 pub fn get_chat_db_size() -> napi::Result<f64> {
-	let db_path = Path::new(&env::var("HOME").unwrap()).join("Library/Messages/chat.db");
+	Ok(file_size_mb(&paths::chat_db()))
+}
 
-	let file_size_mb = fs::metadata(&db_path)
-		.map(|metadata| (metadata.len() as f64 / 1_048_576.0))
-		.unwrap_or(0.0);
+fn file_size_mb(path: &Path) -> f64 {
+	fs::metadata(path).map(|metadata| (metadata.len() as f64 / 1_048_576.0)).unwrap_or(0.0)
+}
+
+/// Lists the AddressBook sources with their contact counts, so the user can
+/// pick which ones to pass back as `addressBookSources`.
+#[napi(ts_return_type = "Json<Response<AddressBookSourcesData>>")]
+pub fn list_address_book_sources() -> napi::Result<String> {
+	let _guard = scopeguard::guard((), |()| shutdown_sqlite());
+	init_sqlite();
+
+	Ok(serde_json::json!({
+		"success": true,
+		"data": {
+			"sources": address_book::list_sources(&paths::address_book())?
+		}
+	})
+	.to_string())
+}
+
+/// Checks that chat.db and the AddressBook can be read, with the error
+/// number and what to do about it when they can't. Call it before analyzing
+/// so a missing Full Disk Access grant is explained instead of failing as an
+/// SQLite error partway through.
+#[napi(ts_return_type = "Json<Response<PermissionCheck>>")]
+pub fn check_permissions(options: Option<FetchOptions>) -> napi::Result<String> {
+	let _guard = scopeguard::guard((), |()| shutdown_sqlite());
+	init_sqlite();
 
-	Ok(file_size_mb)
+	let options = options.unwrap_or_default();
+	let check = permissions::check(&options.chat_db_path(), &options.address_book_path());
+	Ok(serde_json::json!({ "success": true, "data": check }).to_string())
 }
 
 #[napi]
 pub fn has_contacts() -> napi::Result<bool> {
-	let address_book_path =
-		Path::new(&env::var("HOME").unwrap()).join("Library/Application Support/AddressBook");
+	let address_book_path = paths::address_book();
 
 	match get_address_book_db_connections(&address_book_path) {
 		Ok(connections) => {
 			let has_contacts = connections.iter().any(|conn| {
-				Contact::query_all(conn, [])
-					.map(|contacts| !contacts.is_empty())
-					.unwrap_or(false)
+				readonly::enforce(conn).is_ok()
+					&& Contact::query_all(conn, [])
+						.map(|contacts| !contacts.is_empty())
+						.unwrap_or(false)
 			});
 			Ok(has_contacts)
 		}
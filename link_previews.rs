@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use imessage_database::message_types::url::URLMessage;
+use imessage_database::util::plist::parse_ns_keyed_archiver;
+use rusqlite::Connection;
+
+use crate::schema::Schema;
+use crate::{readonly, AnalyzerResult};
+
+const URL_BALLOON_PROVIDER: &str = "com.apple.messages.URLBalloonProvider";
+
+#[derive(Debug, Clone)]
+pub struct LinkPreview {
+	pub url: String,
+	pub title: Option<String>
+}
+
+impl LinkPreview {
+	/// Host of the link without a leading "www.".
+	pub fn domain(&self) -> &str {
+		let rest = self.url.split_once("://").map_or(self.url.as_str(), |(_, rest)| rest);
+		let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+		host.strip_prefix("www.").unwrap_or(host)
+	}
+}
+
+/// Rich link previews cached in chat.db, keyed by message ROWID.
+pub struct LinkPreviews {
+	by_message: HashMap<i32, LinkPreview>
+}
+
+impl LinkPreviews {
+	pub fn new(db: &Connection, schema: &Schema) -> AnalyzerResult<Self> {
+		let mut by_message = HashMap::new();
+		// Rich links arrived in macOS 10.12, anything older has no previews
+		if !schema.has_column("message", "balloon_bundle_id")
+			|| !schema.has_column("message", "payload_data")
+		{
+			return Ok(Self { by_message });
+		}
+
+		let mut statement = readonly::prepare(
+			db,
+			"SELECT ROWID, payload_data FROM message
+			WHERE balloon_bundle_id = ?1 AND payload_data IS NOT NULL"
+		)?;
+
+		let rows = statement.query_map([URL_BALLOON_PROVIDER], |row| {
+			Ok((row.get::<_, i32>(0)?, row.get::<_, Vec<u8>>(1)?))
+		})?;
+		for row in rows {
+			let (message_id, payload) = row?;
+			// Malformed payloads are skipped, the message still counts as a link
+			if let Some(preview) = parse_payload(&payload) {
+				by_message.insert(message_id, preview);
+			}
+		}
+
+		Ok(Self { by_message })
+	}
+
+	pub fn get(&self, message_id: i32) -> Option<&LinkPreview> {
+		self.by_message.get(&message_id)
+	}
+}
+
+fn parse_payload(payload: &[u8]) -> Option<LinkPreview> {
+	let value = plist::Value::from_reader(Cursor::new(payload)).ok()?;
+	let archive = parse_ns_keyed_archiver(&value).ok()?;
+	let message = URLMessage::from_map(&archive).ok()?;
+
+	Some(LinkPreview {
+		url: message.url.or(message.original_url)?.to_string(),
+		title: message.title.filter(|title| !title.is_empty()).map(String::from)
+	})
+}
@@ -0,0 +1,55 @@
+//! Low-impact mode: runs the analysis on a background-priority thread and
+//! paces the heavy loops, so generating a wrapped during a meeting doesn't
+//! spin the fans up. Slower, but the rest of the machine stays responsive.
+
+use std::thread;
+use std::time::Duration;
+
+/// Rows read between pauses while loading messages
+pub const ROWS_PER_BATCH: usize = 5_000;
+/// Pause between batches and between insight passes
+const PAUSE: Duration = Duration::from_millis(15);
+
+/// Lets other work run for a moment.
+pub fn pause() {
+	thread::sleep(PAUSE);
+}
+
+/// Runs `work` on a thread of its own at background priority when `enabled`,
+/// otherwise right here. Only that thread is lowered: the caller may be the
+/// JS main thread, and Linux doesn't let a thread raise its nice value back
+/// without privileges.
+pub fn run<T: Send>(enabled: bool, work: impl FnOnce() -> T + Send) -> T {
+	if !enabled {
+		return work();
+	}
+	thread::scope(|scope| {
+		let worker = scope.spawn(|| {
+			lower_priority();
+			work()
+		});
+		worker.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+	})
+}
+
+/// Drops the current thread to background priority: the background QoS class
+/// on macOS, which also throttles its disk I/O, and the lowest nice value on
+/// Linux. Does nothing elsewhere.
+#[cfg(target_os = "macos")]
+fn lower_priority() {
+	// SAFETY: only changes the calling thread's QoS class.
+	unsafe {
+		libc::pthread_set_qos_class_self_np(libc::qos_class_t::QOS_CLASS_BACKGROUND, 0);
+	}
+}
+
+#[cfg(target_os = "linux")]
+fn lower_priority() {
+	// SAFETY: with PRIO_PROCESS and a thread id this only affects this thread.
+	unsafe {
+		libc::setpriority(libc::PRIO_PROCESS, libc::gettid() as libc::id_t, 19);
+	}
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn lower_priority() {}
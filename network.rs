@@ -0,0 +1,110 @@
+//! Outbound network guard. Every request is made through a client pinned to
+//! one host: requests and redirects anywhere else, and proxies from the
+//! environment, are refused with a hard error instead of being followed. The
+//! host is resolved once when the client is made and every connection goes to
+//! those addresses, so a later lookup can't send the payload elsewhere.
+
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+
+use reqwest::{redirect, Client, Method, RequestBuilder, Response, Url};
+
+use crate::AnalyzerResult;
+
+const MAX_REDIRECTS: usize = 5;
+
+/// HTTP client that may only talk to the host of the URL it was created for.
+pub struct PinnedClient {
+	client: Client,
+	host: String
+}
+
+impl PinnedClient {
+	pub async fn new(base_url: &str) -> AnalyzerResult<Self> {
+		let base = parse(base_url)?;
+		let host = base.host_str().unwrap_or_default().to_string();
+		if host.is_empty() {
+			return Err(violation(format!("{} has no host to allow", base_url)).into());
+		}
+		check_scheme(&base)?;
+		let addresses = resolve(&base, &host).await?;
+
+		let allowed = host.clone();
+		let client = Client::builder()
+			.no_proxy()
+			.resolve_to_addrs(&host, &addresses)
+			.redirect(redirect::Policy::custom(move |attempt| {
+				if attempt.previous().len() >= MAX_REDIRECTS {
+					attempt.error("Too many redirects")
+				} else if attempt.url().host_str() != Some(allowed.as_str()) {
+					let message = format!("Blocked redirect to {}", attempt.url());
+					attempt.error(violation(message))
+				} else {
+					attempt.follow()
+				}
+			}))
+			.build()
+			.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+		Ok(Self { client, host })
+	}
+
+	/// Starts a request, refusing URLs outside the allowed host.
+	pub fn request(&self, method: Method, url: &str) -> AnalyzerResult<RequestBuilder> {
+		let parsed = parse(url)?;
+		if parsed.host_str() != Some(self.host.as_str()) {
+			return Err(violation(format!(
+				"Blocked request to {}, only {} is allowed",
+				parsed.host_str().unwrap_or_default(),
+				self.host
+			))
+			.into());
+		}
+		check_scheme(&parsed)?;
+
+		Ok(self.client.request(method, parsed))
+	}
+
+	/// Confirms the response came from the allowed host.
+	pub fn verify(&self, response: Response) -> AnalyzerResult<Response> {
+		if response.url().host_str() != Some(self.host.as_str()) {
+			return Err(violation(format!("Response came from {}", response.url())).into());
+		}
+		Ok(response)
+	}
+}
+
+/// Addresses of `host`, looked up once on tokio's blocking pool so the lookup
+/// doesn't stall the runtime. IP literals need no lookup.
+async fn resolve(url: &Url, host: &str) -> AnalyzerResult<Vec<SocketAddr>> {
+	let port = url.port_or_known_default().unwrap_or(443);
+	if let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+		return Ok(vec![SocketAddr::new(ip, port)]);
+	}
+	let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+	if addresses.is_empty() {
+		return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} has no address", host))
+			.into());
+	}
+	Ok(addresses)
+}
+
+fn parse(url: &str) -> AnalyzerResult<Url> {
+	Url::parse(url).map_err(|e| {
+		io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid URL {}: {}", url, e)).into()
+	})
+}
+
+/// Payloads only travel over HTTPS, plain HTTP is allowed for local servers.
+fn check_scheme(url: &Url) -> AnalyzerResult<()> {
+	let local = matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+	match url.scheme() {
+		"https" => Ok(()),
+		"http" if local => Ok(()),
+		scheme => Err(violation(format!("Blocked {} request to {}", scheme, url)).into())
+	}
+}
+
+fn violation(message: String) -> io::Error {
+	io::Error::new(io::ErrorKind::PermissionDenied, message)
+}
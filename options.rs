@@ -0,0 +1,317 @@
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::{Local, NaiveDate, NaiveTime, TimeZone};
+use napi_derive::napi;
+
+use crate::{insights, paths, AnalyzerResult};
+
+/// Options accepted by `fetch_stats` and `prepare_upload`. Every field is
+/// optional so callers only pass what they want to change.
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct FetchOptions {
+	/// Total run time after which lower-priority stats are skipped
+	pub time_budget_seconds: Option<u32>,
+	/// Words counted as profanity on top of the built-in list
+	pub extra_profanity: Option<Vec<String>>,
+	/// "strict", "standard" (default) or "permissive"
+	pub privacy_level: Option<String>,
+	/// Include the playful dictated-vs-typed estimate
+	pub dictation_stats: Option<bool>,
+	/// Messages in a row needed to count as a burst (default 5)
+	pub burst_min_messages: Option<u32>,
+	/// Longest pause between messages of a burst, in seconds (default 60)
+	pub burst_max_gap_seconds: Option<u32>,
+	/// Reply times, in seconds, that count as instant replies (default [10])
+	pub instant_reply_thresholds_seconds: Option<Vec<u32>>,
+	/// Keep short codes and bot senders out of relationship stats (default true)
+	pub filter_automated_senders: Option<bool>,
+	/// Handles always treated as automated senders
+	pub automated_senders: Option<Vec<String>>,
+	/// Handles never treated as automated senders
+	pub human_senders: Option<Vec<String>>,
+	/// Handles that belong to me, as confirmed from `detect_my_identities`.
+	/// Detected automatically when not set
+	pub my_handles: Option<Vec<String>>,
+	/// Write the messages behind the headline numbers to a local debug file
+	pub provenance: Option<bool>,
+	/// Keep an encrypted copy of the run in the local archive (default true)
+	pub archive: Option<bool>,
+	/// AddressBook source ids from `list_address_book_sources` used for names.
+	/// All sources when not set
+	pub address_book_sources: Option<Vec<String>>,
+	/// chat.db to analyze instead of the current user's, e.g. a copy or a
+	/// backup on an external drive
+	pub chat_db_path: Option<String>,
+	/// AddressBook folder to read contacts from instead of the current user's
+	pub address_book_path: Option<String>,
+	/// "http" (default) posts to the API, "presigned" PUTs to a presigned
+	/// object storage URL and "file" writes the encrypted payload to disk
+	pub upload_transport: Option<String>,
+	/// Presigned URL or file path for the "presigned" and "file" transports
+	pub upload_target: Option<String>,
+	/// First day to analyze as YYYY-MM-DD in local time. All time when not set
+	pub from: Option<String>,
+	/// Last day to analyze as YYYY-MM-DD in local time, inclusive
+	pub to: Option<String>,
+	/// Handles (phone numbers or emails) left out of every stat: our chat and
+	/// their messages in groups
+	pub excluded_handles: Option<Vec<String>>,
+	/// Chat GUIDs or chat identifiers left out of every stat
+	pub excluded_chats: Option<Vec<String>>,
+	/// Run at background priority with paced reads. Slower, but keeps the
+	/// machine responsive
+	pub low_impact: Option<bool>,
+	/// "parallel" (default) runs each insight pass over the messages on its
+	/// own; "fused" computes the counting passes in one shared iteration
+	pub engine: Option<String>,
+	/// Reuse cached years that gained no messages since the last run (default
+	/// true)
+	pub cache: Option<bool>,
+	/// Lengths of the leaderboard lists. Defaults keep the payload small
+	pub top_sizes: Option<TopSizes>
+}
+
+/// How many entries each kind of leaderboard keeps. Every field is optional;
+/// values are capped so an expanded wrapped still uploads quickly.
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct TopSizes {
+	/// Per-contact stats such as reply times, dryness and relationships
+	/// (default 10, at most 50)
+	pub contacts: Option<u32>,
+	/// Contacts whose conversation topics are clustered (default 5, at most 20)
+	pub topic_contacts: Option<u32>,
+	/// Group chat rankings and profanity (default 5, at most 25)
+	pub group_chats: Option<u32>,
+	/// Members listed per group chat (default 5, at most 25)
+	pub group_members: Option<u32>,
+	/// Shared links (default 10, at most 50)
+	pub links: Option<u32>,
+	/// Emojis and running jokes of a group wrapped (default 5, at most 25)
+	pub group_wrapped_items: Option<u32>,
+	/// Shorter lists of people: revivers, best connected friends, most
+	/// shared contact cards and automated senders (default 5, at most 25)
+	pub people: Option<u32>,
+	/// Words in the word cloud (default 100, at most 200)
+	pub word_cloud: Option<u32>,
+	/// Top contacts compared with my schedule to find my schedule twin
+	/// (default 20, at most 50)
+	pub schedule_twin_candidates: Option<u32>,
+	/// Keywords clustered into topics per contact (default 12, at most 24)
+	pub topic_keywords: Option<u32>,
+	/// Top emojis compared with the year before (default 5, at most 25)
+	pub compared_emojis: Option<u32>,
+	/// Contacts listed as biggest risers when comparing archived years
+	/// (default 5, at most 25)
+	pub risers: Option<u32>
+}
+
+/// A kind of leaderboard whose length `TopSizes` sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopList {
+	Contacts,
+	TopicContacts,
+	GroupChats,
+	GroupMembers,
+	Links,
+	GroupWrappedItems,
+	People,
+	WordCloud,
+	ScheduleTwinCandidates,
+	TopicKeywords,
+	ComparedEmojis,
+	Risers
+}
+
+impl TopList {
+	/// Default and largest allowed length. Topics cost the most bytes per
+	/// entry, so they grow the least.
+	fn sizes(self) -> (u32, u32) {
+		match self {
+			TopList::Contacts | TopList::Links => (10, 50),
+			TopList::TopicContacts => (5, 20),
+			TopList::GroupChats |
+			TopList::GroupMembers |
+			TopList::GroupWrappedItems |
+			TopList::People |
+			TopList::ComparedEmojis |
+			TopList::Risers => (5, 25),
+			TopList::WordCloud => (100, 200),
+			TopList::ScheduleTwinCandidates => (20, 50),
+			TopList::TopicKeywords => (12, 24)
+		}
+	}
+}
+
+/// Messages to analyze, as unix seconds. Both ends are optional.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DateRange {
+	start: Option<i64>,
+	/// Exclusive
+	end: Option<i64>
+}
+
+impl DateRange {
+	/// Whether a chat.db date falls in the range.
+	pub fn contains(&self, date: i64) -> bool {
+		let seconds = insights::unix_seconds(date);
+		self.start.map_or(true, |start| seconds >= start) &&
+			self.end.map_or(true, |end| seconds < end)
+	}
+
+	pub fn is_all_time(&self) -> bool {
+		self.start.is_none() && self.end.is_none()
+	}
+}
+
+/// How much message content may end up in the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PrivacyLevel {
+	Strict,
+	Standard,
+	/// Allows verbatim message text in new stats, link titles and the names
+	/// on shared contact cards
+	Permissive
+}
+
+impl FetchOptions {
+	pub fn chat_db_path(&self) -> PathBuf {
+		self.chat_db_path.as_ref().map(PathBuf::from).unwrap_or_else(paths::chat_db)
+	}
+
+	pub fn address_book_path(&self) -> PathBuf {
+		self.address_book_path.as_ref().map(PathBuf::from).unwrap_or_else(paths::address_book)
+	}
+
+	pub fn low_impact(&self) -> bool {
+		self.low_impact.unwrap_or(false)
+	}
+
+	pub fn fused_engine(&self) -> bool {
+		self.engine.as_deref() == Some("fused")
+	}
+
+	pub fn time_budget(&self) -> Option<Duration> {
+		self.time_budget_seconds.map(|seconds| Duration::from_secs(seconds.into()))
+	}
+
+	pub fn burst_min_messages(&self) -> usize {
+		self.burst_min_messages.unwrap_or(5).max(2) as usize
+	}
+
+	pub fn burst_max_gap_seconds(&self) -> i64 {
+		self.burst_max_gap_seconds.unwrap_or(60).into()
+	}
+
+	/// Number of entries `list` keeps.
+	pub fn top(&self, list: TopList) -> usize {
+		let sizes = self.top_sizes.as_ref();
+		let requested = sizes.and_then(|sizes| match list {
+			TopList::Contacts => sizes.contacts,
+			TopList::TopicContacts => sizes.topic_contacts,
+			TopList::GroupChats => sizes.group_chats,
+			TopList::GroupMembers => sizes.group_members,
+			TopList::Links => sizes.links,
+			TopList::GroupWrappedItems => sizes.group_wrapped_items,
+			TopList::People => sizes.people,
+			TopList::WordCloud => sizes.word_cloud,
+			TopList::ScheduleTwinCandidates => sizes.schedule_twin_candidates,
+			TopList::TopicKeywords => sizes.topic_keywords,
+			TopList::ComparedEmojis => sizes.compared_emojis,
+			TopList::Risers => sizes.risers
+		});
+		let (default, max) = list.sizes();
+		requested.unwrap_or(default).clamp(1, max) as usize
+	}
+
+	pub fn instant_reply_thresholds(&self) -> Vec<i64> {
+		let mut thresholds: Vec<i64> = match &self.instant_reply_thresholds_seconds {
+			Some(seconds) if !seconds.is_empty() => seconds.iter().map(|&s| s.into()).collect(),
+			_ => vec![10]
+		};
+		thresholds.sort_unstable();
+		thresholds.dedup();
+		thresholds
+	}
+
+	/// Parses `from` and `to` into the range of messages to analyze.
+	pub fn date_range(&self) -> AnalyzerResult<DateRange> {
+		let start = match self.from.as_deref() {
+			Some(from) => Some(local_midnight(parse_day(from)?)?),
+			None => None
+		};
+		// The day after `to` starts at its own midnight, which is not always
+		// 24 hours later when the clocks change
+		let end = match self.to.as_deref() {
+			Some(to) => {
+				let to = parse_day(to)?;
+				let next_day = to.succ_opt().ok_or_else(|| invalid_day(&to.to_string()))?;
+				Some(local_midnight(next_day)?)
+			}
+			None => None
+		};
+		if let (Some(start), Some(end)) = (start, end) {
+			if start >= end {
+				return Err(io::Error::new(
+					io::ErrorKind::InvalidInput,
+					"The date range ends before it starts"
+				)
+				.into());
+			}
+		}
+
+		Ok(DateRange { start, end })
+	}
+
+	pub fn privacy_level(&self) -> PrivacyLevel {
+		match self.privacy_level.as_deref() {
+			Some("strict") => PrivacyLevel::Strict,
+			Some("permissive") => PrivacyLevel::Permissive,
+			_ => PrivacyLevel::Standard
+		}
+	}
+}
+
+fn parse_day(day: &str) -> AnalyzerResult<NaiveDate> {
+	Ok(NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(|_| invalid_day(day))?)
+}
+
+fn local_midnight(date: NaiveDate) -> AnalyzerResult<i64> {
+	let midnight = Local.from_local_datetime(&date.and_time(NaiveTime::MIN)).earliest();
+
+	Ok(midnight.ok_or_else(|| invalid_day(&date.to_string()))?.timestamp())
+}
+
+fn invalid_day(day: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid date {}", day))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn range(from: &str, to: &str) -> DateRange {
+		let options =
+			FetchOptions { from: Some(from.into()), to: Some(to.into()), ..Default::default() };
+		options.date_range().unwrap()
+	}
+
+	#[test]
+	fn range_ends_at_the_next_local_midnight_across_dst() {
+		// Nothing else under test depends on the local time zone
+		std::env::set_var("TZ", "America/New_York");
+
+		// Clocks go forward on 2024-03-10, the day is 23 hours long
+		let spring = range("2024-03-10", "2024-03-10");
+		assert_eq!(spring.start, Some(1_710_046_800));
+		assert_eq!(spring.end, Some(1_710_129_600));
+
+		// And back on 2024-11-03, which is 25 hours long
+		let fall = range("2024-11-03", "2024-11-03");
+		assert_eq!(fall.start, Some(1_730_606_400));
+		assert_eq!(fall.end, Some(1_730_696_400));
+	}
+}
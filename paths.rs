@@ -0,0 +1,94 @@
+//! Default locations of the Messages database, the AddressBook and our own
+//! data. Only macOS has real Messages data; elsewhere the defaults point at a
+//! copied chat.db and can be overridden with environment variables.
+
+use std::env;
+use std::path::PathBuf;
+
+/// Overrides the default chat.db location on every platform
+const CHAT_DB_VAR: &str = "MESSAGES_WRAPPED_CHAT_DB";
+/// Overrides the default AddressBook location on every platform
+const ADDRESS_BOOK_VAR: &str = "MESSAGES_WRAPPED_ADDRESS_BOOK";
+/// Overrides the default iPhone backup folder on every platform
+const IOS_BACKUPS_VAR: &str = "MESSAGES_WRAPPED_IOS_BACKUPS";
+
+pub fn home_dir() -> PathBuf {
+	env::var_os("HOME")
+		.or_else(|| env::var_os("USERPROFILE"))
+		.map(PathBuf::from)
+		.unwrap_or_default()
+}
+
+#[cfg(target_os = "macos")]
+pub fn chat_db() -> PathBuf {
+	env::var_os(CHAT_DB_VAR)
+		.map(PathBuf::from)
+		.unwrap_or_else(|| home_dir().join("Library/Messages/chat.db"))
+}
+
+/// A chat.db copied from a Mac, in the working directory by default.
+#[cfg(not(target_os = "macos"))]
+pub fn chat_db() -> PathBuf {
+	env::var_os(CHAT_DB_VAR).map(PathBuf::from).unwrap_or_else(|| PathBuf::from("chat.db"))
+}
+
+#[cfg(target_os = "macos")]
+pub fn address_book() -> PathBuf {
+	env::var_os(ADDRESS_BOOK_VAR)
+		.map(PathBuf::from)
+		.unwrap_or_else(|| home_dir().join("Library/Application Support/AddressBook"))
+}
+
+/// A copied AddressBook folder next to chat.db by default.
+#[cfg(not(target_os = "macos"))]
+pub fn address_book() -> PathBuf {
+	env::var_os(ADDRESS_BOOK_VAR)
+		.map(PathBuf::from)
+		.unwrap_or_else(|| PathBuf::from("AddressBook"))
+}
+
+/// Where Finder (or iTunes on Windows) keeps local iPhone backups, one folder
+/// per device.
+#[cfg(target_os = "macos")]
+pub fn ios_backups() -> PathBuf {
+	env::var_os(IOS_BACKUPS_VAR)
+		.map(PathBuf::from)
+		.unwrap_or_else(|| home_dir().join("Library/Application Support/MobileSync/Backup"))
+}
+
+#[cfg(target_os = "windows")]
+pub fn ios_backups() -> PathBuf {
+	env::var_os(IOS_BACKUPS_VAR).map(PathBuf::from).unwrap_or_else(|| {
+		env::var_os("APPDATA")
+			.map(PathBuf::from)
+			.unwrap_or_else(|| home_dir().join("AppData/Roaming"))
+			.join("Apple Computer/MobileSync/Backup")
+	})
+}
+
+/// A copied backup folder in the working directory by default.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn ios_backups() -> PathBuf {
+	env::var_os(IOS_BACKUPS_VAR).map(PathBuf::from).unwrap_or_else(|| PathBuf::from("Backup"))
+}
+
+#[cfg(target_os = "macos")]
+pub fn data_dir() -> PathBuf {
+	home_dir().join("Library/Application Support/MessagesWrapped")
+}
+
+#[cfg(target_os = "windows")]
+pub fn data_dir() -> PathBuf {
+	env::var_os("APPDATA")
+		.map(PathBuf::from)
+		.unwrap_or_else(|| home_dir().join("AppData/Roaming"))
+		.join("MessagesWrapped")
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn data_dir() -> PathBuf {
+	env::var_os("XDG_DATA_HOME")
+		.map(PathBuf::from)
+		.unwrap_or_else(|| home_dir().join(".local/share"))
+		.join("messages-wrapped")
+}
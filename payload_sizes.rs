@@ -0,0 +1,106 @@
+//! How many bytes each stat contributes to the encoded payload, summed over
+//! the years, to see what bloats uploads before deciding what to trim. Sizes
+//! are read off the protobuf wire format, so every field of stats.proto is
+//! covered without listing them here; names come from `schema_doc`.
+
+use std::collections::HashMap;
+
+use prost::Message as ProstMessage;
+use serde::Serialize;
+
+use crate::schema_doc;
+use crate::stats::stats::YearsStats;
+
+/// `YearsStats.stats`, whose entries are broken down by their own fields
+const STATS_FIELD: u32 = 2;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatSize {
+	/// Field of `YearStats` as named in stats.proto, or of `YearsStats` for
+	/// the parts outside the years
+	pub field: String,
+	/// Encoded bytes including field tags, before compression
+	pub bytes: usize,
+	/// Share of the whole encoded payload
+	pub share: f64
+}
+
+/// Every field present in the payload, largest first.
+pub fn stat_sizes(stats: &YearsStats) -> Vec<StatSize> {
+	let encoded = stats.encode_to_vec();
+	let mut totals: HashMap<(&str, u32), usize> = HashMap::new();
+	for (number, bytes, value) in fields(&encoded) {
+		if number != STATS_FIELD {
+			*totals.entry(("YearsStats", number)).or_default() += bytes;
+			continue;
+		}
+		for (number, bytes, _) in value.map(fields).unwrap_or_default() {
+			*totals.entry(("YearStats", number)).or_default() += bytes;
+		}
+	}
+
+	let schema = schema_doc::describe();
+	let name = |message: &str, number: u32| {
+		schema
+			.messages
+			.iter()
+			.find(|doc| doc.name == message)
+			.and_then(|doc| doc.fields.iter().find(|field| field.number == number))
+			.map_or_else(|| format!("{}.{}", message, number), |field| field.name.clone())
+	};
+
+	let mut sizes: Vec<StatSize> = totals
+		.into_iter()
+		.map(|((message, number), bytes)| StatSize {
+			field: name(message, number),
+			bytes,
+			share: bytes as f64 / encoded.len().max(1) as f64
+		})
+		.collect();
+	sizes.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.field.cmp(&b.field)));
+	sizes
+}
+
+/// Top-level fields of an encoded message: field number, bytes taken
+/// including the tag, and the contents of length-delimited fields. Stops at
+/// the first malformed field.
+fn fields(mut data: &[u8]) -> Vec<(u32, usize, Option<&[u8]>)> {
+	let mut fields = Vec::new();
+	while !data.is_empty() {
+		let start = data.len();
+		let Some(key) = varint(&mut data) else { break };
+		let value = match key & 7 {
+			0 => varint(&mut data).map(|_| None),
+			1 => skip(&mut data, 8).map(|_| None),
+			2 => varint(&mut data).and_then(|length| skip(&mut data, length as usize)).map(Some),
+			5 => skip(&mut data, 4).map(|_| None),
+			_ => None
+		};
+		let Some(value) = value else { break };
+		fields.push(((key >> 3) as u32, start - data.len(), value));
+	}
+	fields
+}
+
+fn varint(data: &mut &[u8]) -> Option<u64> {
+	let bytes = *data;
+	let mut value = 0u64;
+	for (index, &byte) in bytes.iter().enumerate().take(10) {
+		value |= u64::from(byte & 0x7f) << (7 * index);
+		if byte & 0x80 == 0 {
+			*data = &bytes[index + 1..];
+			return Some(value);
+		}
+	}
+	None
+}
+
+fn skip<'a>(data: &mut &'a [u8], length: usize) -> Option<&'a [u8]> {
+	if data.len() < length {
+		return None;
+	}
+	let (skipped, rest) = data.split_at(length);
+	*data = rest;
+	Some(skipped)
+}
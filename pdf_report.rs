@@ -0,0 +1,269 @@
+//! Lays out a `YearsStats` as an A4 PDF to keep offline, one year after
+//! another, every stat the run filled as its own section. Stats are walked
+//! from their JSON form like in `html_report`, whose formatting this shares.
+//! Text uses the fonts embedded for share cards; characters they have no
+//! glyph for are left out.
+
+use std::io::{self, Cursor};
+
+use ab_glyph::{Font, FontRef};
+use chrono::Local;
+use printpdf::{IndirectFontRef, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference};
+use serde_json::Value;
+
+use crate::html_report::{humanize, is_empty, number, plain_scalar, HIDDEN_FIELDS};
+use crate::schema_doc::{self, FieldDoc};
+use crate::stats::stats::{YearStats, YearsStats};
+use crate::AnalyzerResult;
+
+const REGULAR: &[u8] = include_bytes!("fonts/DejaVuSans.ttf");
+const BOLD: &[u8] = include_bytes!("fonts/DejaVuSans-Bold.ttf");
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 18.0;
+/// Indent per nesting level, in millimeters
+const INDENT: f32 = 5.0;
+const MM_PER_PT: f32 = 0.3528;
+const LINE_SPACING: f32 = 1.4;
+
+/// One line of text before it is wrapped to the page width.
+struct Line {
+	text: String,
+	/// Points
+	size: f32,
+	bold: bool,
+	depth: usize,
+	/// Start the line on a new page
+	page_break: bool
+}
+
+impl Line {
+	fn new(text: impl Into<String>, size: f32, bold: bool, depth: usize) -> Self {
+		Self { text: text.into(), size, bold, depth, page_break: false }
+	}
+}
+
+struct Fonts<'a> {
+	regular: FontRef<'a>,
+	bold: FontRef<'a>
+}
+
+/// The whole document.
+pub fn render(stats: &YearsStats) -> AnalyzerResult<Vec<u8>> {
+	let mut years: Vec<&YearStats> = stats.stats.iter().collect();
+	years.sort_by(|a, b| b.year.cmp(&a.year));
+
+	let schema = schema_doc::describe();
+	let fields: &[FieldDoc] = schema
+		.messages
+		.iter()
+		.find(|message| message.name == "YearStats")
+		.map_or(&[][..], |message| message.fields.as_slice());
+
+	let mut lines = vec![
+		Line::new("Messages Wrapped", 28.0, true, 0),
+		Line::new(format!("Generated {}", Local::now().format("%B %-d, %Y")), 10.0, false, 0)
+	];
+	for year in &years {
+		year_lines(year, fields, &mut lines)?;
+	}
+	if years.is_empty() {
+		lines.push(Line::new("No messages were found for any year.", 11.0, false, 0));
+	}
+
+	let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+	let metrics = Fonts {
+		regular: FontRef::try_from_slice(REGULAR).map_err(invalid)?,
+		bold: FontRef::try_from_slice(BOLD).map_err(invalid)?
+	};
+	let pdf_error = |e: printpdf::Error| io::Error::new(io::ErrorKind::Other, e.to_string());
+	let (document, page, layer) =
+		PdfDocument::new("Messages Wrapped", Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Page 1");
+	let regular = document.add_external_font(Cursor::new(REGULAR)).map_err(pdf_error)?;
+	let bold = document.add_external_font(Cursor::new(BOLD)).map_err(pdf_error)?;
+
+	let mut writer = Writer {
+		document: &document,
+		layer: document.get_page(page).get_layer(layer),
+		pages: 1,
+		y: PAGE_HEIGHT - MARGIN
+	};
+	for line in &lines {
+		let font = if line.bold { &metrics.bold } else { &metrics.regular };
+		let pdf_font = if line.bold { &bold } else { &regular };
+		if line.page_break {
+			writer.new_page();
+		}
+		let width = PAGE_WIDTH - 2.0 * MARGIN - INDENT * line.depth as f32;
+		for wrapped in wrap(font, &line.text, line.size, width) {
+			writer.write(&wrapped, line.size, MARGIN + INDENT * line.depth as f32, pdf_font);
+		}
+	}
+
+	Ok(document.save_to_bytes().map_err(pdf_error)?)
+}
+
+/// Places lines top to bottom, starting a page when one is full.
+struct Writer<'a> {
+	document: &'a PdfDocumentReference,
+	layer: PdfLayerReference,
+	pages: usize,
+	/// Baseline of the next line, in millimeters from the bottom
+	y: f32
+}
+
+impl Writer<'_> {
+	fn write(&mut self, text: &str, size: f32, x: f32, font: &IndirectFontRef) {
+		let height = size * LINE_SPACING * MM_PER_PT;
+		if self.y - height < MARGIN {
+			self.new_page();
+		}
+		self.y -= height;
+		self.layer.use_text(text, size, Mm(x), Mm(self.y), font);
+	}
+
+	fn new_page(&mut self) {
+		self.pages += 1;
+		let (page, layer) = self.document.add_page(
+			Mm(PAGE_WIDTH),
+			Mm(PAGE_HEIGHT),
+			format!("Page {}", self.pages)
+		);
+		self.layer = self.document.get_page(page).get_layer(layer);
+		self.y = PAGE_HEIGHT - MARGIN;
+	}
+}
+
+fn year_lines(
+	year: &YearStats,
+	fields: &[FieldDoc],
+	lines: &mut Vec<Line>
+) -> AnalyzerResult<()> {
+	lines.push(Line { page_break: true, ..Line::new(year.year.to_string(), 24.0, true, 0) });
+	if let Some(count) = &year.message_count {
+		let text = format!(
+			"{} messages sent, {} received",
+			number(count.sent.into()),
+			number(count.received.into())
+		);
+		lines.push(Line::new(text, 12.0, false, 0));
+	}
+	if let Some(average) = &year.average_per_day {
+		let text = format!(
+			"{} sent and {} received per day",
+			number(average.sent.into()),
+			number(average.received.into())
+		);
+		lines.push(Line::new(text, 12.0, false, 0));
+	}
+
+	let Value::Object(values) = serde_json::to_value(year)? else { return Ok(()) };
+	for field in fields.iter().filter(|field| !HIDDEN_FIELDS.contains(&field.name.as_str())) {
+		let Some(value) = values.get(&field.name).filter(|value| !is_empty(value)) else {
+			continue;
+		};
+		lines.push(Line::new(humanize(&field.name), 14.0, true, 0));
+		if let Some(description) = &field.description {
+			lines.push(Line::new(description.clone(), 9.0, false, 0));
+		}
+		value_lines(&field.name, value, 0, lines);
+	}
+	Ok(())
+}
+
+/// Objects become "Label: value" lines with nested values indented below,
+/// lists of objects one bullet per entry and lists of plain values one
+/// comma-separated line.
+fn value_lines(key: &str, value: &Value, depth: usize, lines: &mut Vec<Line>) {
+	match value {
+		Value::Object(fields) => {
+			for (key, value) in fields {
+				if HIDDEN_FIELDS.contains(&key.as_str()) || is_empty(value) {
+					continue;
+				}
+				if is_plain(value) {
+					let text = format!("{}: {}", humanize(key), plain(key, value));
+					lines.push(Line::new(text, 10.0, false, depth));
+				} else {
+					lines.push(Line::new(format!("{}:", humanize(key)), 10.0, true, depth));
+					value_lines(key, value, depth + 1, lines);
+				}
+			}
+		}
+		Value::Array(items) if items.iter().all(Value::is_object) => {
+			for fields in items.iter().filter_map(Value::as_object) {
+				let shown = fields.iter().filter(|(key, value)| {
+					!HIDDEN_FIELDS.contains(&key.as_str()) && !is_empty(value)
+				});
+				let summary: Vec<String> = shown
+					.clone()
+					.filter(|(_, value)| is_plain(value))
+					.map(|(key, value)| format!("{}: {}", humanize(key), plain(key, value)))
+					.collect();
+				lines.push(Line::new(format!("• {}", summary.join(" · ")), 10.0, false, depth));
+
+				for (key, value) in shown.filter(|(_, value)| !is_plain(value)) {
+					lines.push(Line::new(format!("{}:", humanize(key)), 10.0, true, depth + 1));
+					value_lines(key, value, depth + 2, lines);
+				}
+			}
+		}
+		_ => lines.push(Line::new(plain(key, value), 10.0, false, depth))
+	}
+}
+
+/// Scalars and lists of scalars, which fit on one line.
+fn is_plain(value: &Value) -> bool {
+	match value {
+		Value::Object(_) => false,
+		Value::Array(items) => !items.iter().any(|item| item.is_object() || item.is_array()),
+		_ => true
+	}
+}
+
+fn plain(key: &str, value: &Value) -> String {
+	match value {
+		Value::Array(items) => {
+			items.iter().map(|item| plain_scalar(key, item)).collect::<Vec<_>>().join(", ")
+		}
+		_ => plain_scalar(key, value)
+	}
+}
+
+/// Breaks `text` into lines no wider than `width` millimeters at `size`
+/// points, between words where possible.
+fn wrap(font: &FontRef, text: &str, size: f32, width: f32) -> Vec<String> {
+	let units_per_em = font.units_per_em().unwrap_or(1000.0);
+	let advance = |c: char| {
+		font.h_advance_unscaled(font.glyph_id(c)) / units_per_em * size * MM_PER_PT
+	};
+
+	let mut lines = Vec::new();
+	let mut line = String::new();
+	let mut line_width = 0.0;
+	for word in text.split(' ') {
+		let word: String = word.chars().filter(|&c| font.glyph_id(c).0 != 0).collect();
+		let word_width: f32 = word.chars().map(advance).sum();
+		let space = if line.is_empty() { 0.0 } else { advance(' ') };
+		if !line.is_empty() && line_width + space + word_width > width {
+			lines.push(std::mem::take(&mut line));
+			line_width = 0.0;
+		} else if !line.is_empty() {
+			line.push(' ');
+			line_width += space;
+		}
+		// Words wider than the page are cut
+		for c in word.chars() {
+			if line_width + advance(c) > width && !line.is_empty() {
+				lines.push(std::mem::take(&mut line));
+				line_width = 0.0;
+			}
+			line.push(c);
+			line_width += advance(c);
+		}
+	}
+	if !line.is_empty() || lines.is_empty() {
+		lines.push(line);
+	}
+	lines
+}
@@ -0,0 +1,145 @@
+//! Checks that chat.db and the AddressBook can be read before an analysis
+//! starts. Without Full Disk Access macOS refuses to open them with EPERM,
+//! which otherwise surfaces from deep inside the first query as an opaque
+//! SQLite error.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::{address_book, readonly};
+
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+const FULL_DISK_ACCESS: &str = "Open System Settings > Privacy & Security > Full Disk Access, \
+                                turn on Messages Wrapped and restart the app";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionCheck {
+	/// Everything needed for a full analysis can be read
+	pub granted: bool,
+	pub chat_db: Access,
+	/// Names are missing without it, but the analysis still runs
+	pub address_book: Access
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Access {
+	pub path: PathBuf,
+	pub readable: bool,
+	/// "ok", "not_found", "permission_denied", "not_a_database", "locked" or
+	/// "error"
+	pub status: &'static str,
+	/// OS error number when opening failed, e.g. 1 (EPERM) without Full Disk
+	/// Access
+	pub errno: Option<i32>,
+	pub message: Option<String>,
+	/// What the user can do about it
+	pub remediation: Option<&'static str>
+}
+
+impl Access {
+	fn ok(path: &Path) -> Self {
+		Self {
+			path: path.to_path_buf(),
+			readable: true,
+			status: "ok",
+			errno: None,
+			message: None,
+			remediation: None
+		}
+	}
+
+	fn failed(
+		path: &Path, status: &'static str, error: Option<&io::Error>, message: String
+	) -> Self {
+		let remediation = match status {
+			"not_found" => Some(
+				"Messages has no history on this Mac. Sign in to Messages with your Apple ID, or \
+				 import an iPhone backup"
+			),
+			"permission_denied" => Some(FULL_DISK_ACCESS),
+			"not_a_database" => Some("The file is damaged or not a Messages database"),
+			"locked" => Some("Another app is writing to the database, try again in a moment"),
+			_ => None
+		};
+		Self {
+			path: path.to_path_buf(),
+			readable: false,
+			status,
+			errno: error.and_then(io::Error::raw_os_error),
+			message: Some(message),
+			remediation
+		}
+	}
+}
+
+pub fn check(chat_db_path: &Path, address_book_path: &Path) -> PermissionCheck {
+	let chat_db = check_database(chat_db_path);
+	let address_book = check_address_book(address_book_path);
+	PermissionCheck { granted: chat_db.readable && address_book.readable, chat_db, address_book }
+}
+
+/// Opens the file, checks that it is SQLite and runs a query against it.
+fn check_database(path: &Path) -> Access {
+	let mut header = [0u8; 16];
+	if let Err(e) = File::open(path).and_then(|mut file| file.read_exact(&mut header)) {
+		return from_io(path, &e);
+	}
+	if &header != SQLITE_HEADER {
+		return Access::failed(path, "not_a_database", None, String::from("Not an SQLite file"));
+	}
+
+	let result = readonly::open(path).and_then(|db| {
+		let count = readonly::prepare(&db, "SELECT COUNT(*) FROM sqlite_master")?
+			.query_row([], |row| row.get::<_, i64>(0));
+		let _ = db.close();
+		Ok(count?)
+	});
+	match result {
+		Ok(_) => Access::ok(path),
+		Err(e) => {
+			let locked = e.to_string().contains("locked") || e.to_string().contains("busy");
+			let status = if locked { "locked" } else { "error" };
+			Access::failed(path, status, None, e.to_string())
+		}
+	}
+}
+
+/// The AddressBook is readable when its folder can be listed and its first
+/// source database opened.
+fn check_address_book(path: &Path) -> Access {
+	let access = match address_book::source_paths(path) {
+		Ok(sources) => match sources.first() {
+			Some((_, source)) => Access { path: path.to_path_buf(), ..check_database(source) },
+			None => Access::failed(
+				path,
+				"not_found",
+				None,
+				String::from("The AddressBook has no sources")
+			)
+		},
+		Err(e) => from_io(path, &e)
+	};
+	// Not having contacts isn't about Messages history
+	match access.status {
+		"not_found" => Access {
+			remediation: Some("Add an account in Contacts to see names instead of numbers"),
+			..access
+		},
+		_ => access
+	}
+}
+
+fn from_io(path: &Path, e: &io::Error) -> Access {
+	let status = match e.kind() {
+		io::ErrorKind::NotFound => "not_found",
+		io::ErrorKind::PermissionDenied => "permission_denied",
+		io::ErrorKind::UnexpectedEof => "not_a_database",
+		_ => "error"
+	};
+	Access::failed(path, status, Some(e), e.to_string())
+}
@@ -0,0 +1,130 @@
+//! A strip of thumbnails from the year's most shared photos for the app's
+//! local recap, blurred or pixelated so it reads as a mood rather than as
+//! the photos themselves. The PNG goes straight back to the app and never
+//! into a payload.
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, TimeZone};
+use image::codecs::png::PngEncoder;
+use image::imageops::{self, FilterType};
+use image::{ColorType, DynamicImage, ImageEncoder, RgbaImage};
+use rusqlite::params;
+
+use crate::insights::APPLE_EPOCH_OFFSET;
+use crate::{paths, readonly, AnalyzerResult};
+
+pub const DEFAULT_COUNT: u32 = 6;
+const MAX_COUNT: u32 = 12;
+const TILE_SIZE: u32 = 160;
+const BLUR_SIGMA: f32 = 8.0;
+/// Tiles are shrunk to this many pixels across for the mosaic style
+const MOSAIC_CELLS: u32 = 10;
+/// Candidates read per tile, since formats the image crate can't decode
+/// (HEIC above all) are skipped
+const CANDIDATES_PER_TILE: u32 = 4;
+
+/// How the thumbnails are obscured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+	Blur,
+	Mosaic
+}
+
+impl Style {
+	/// "mosaic", or "blur" (default).
+	pub fn parse(style: Option<&str>) -> Self {
+		match style {
+			Some("mosaic") => Self::Mosaic,
+			_ => Self::Blur
+		}
+	}
+}
+
+/// Renders up to `count` thumbnails side by side as a PNG. `None` when no
+/// photo of `year` could be read.
+pub fn render(
+	chat_db_path: &Path, year: i32, count: u32, style: Style
+) -> AnalyzerResult<Option<Vec<u8>>> {
+	let count = count.clamp(1, MAX_COUNT);
+	let candidates = most_shared_photos(chat_db_path, year, count * CANDIDATES_PER_TILE)?;
+	let tiles: Vec<RgbaImage> = candidates
+		.iter()
+		.filter_map(|path| image::open(path).ok())
+		.take(count as usize)
+		.map(|photo| obscure(&photo, style))
+		.collect();
+	if tiles.is_empty() {
+		return Ok(None);
+	}
+
+	let mut strip = RgbaImage::new(TILE_SIZE * tiles.len() as u32, TILE_SIZE);
+	for (index, tile) in tiles.iter().enumerate() {
+		imageops::replace(&mut strip, tile, i64::from(TILE_SIZE) * index as i64, 0);
+	}
+
+	let mut png = Cursor::new(Vec::new());
+	PngEncoder::new(&mut png).write_image(
+		strip.as_raw(),
+		strip.width(),
+		strip.height(),
+		ColorType::Rgba8.into()
+	)?;
+	Ok(Some(png.into_inner()))
+}
+
+/// Crops a photo to a square tile and blurs or pixelates it.
+fn obscure(photo: &DynamicImage, style: Style) -> RgbaImage {
+	let tile = photo.resize_to_fill(TILE_SIZE, TILE_SIZE, FilterType::Triangle).to_rgba8();
+	match style {
+		Style::Blur => imageops::blur(&tile, BLUR_SIGMA),
+		Style::Mosaic => {
+			let cells = imageops::resize(&tile, MOSAIC_CELLS, MOSAIC_CELLS, FilterType::Triangle);
+			imageops::resize(&cells, TILE_SIZE, TILE_SIZE, FilterType::Nearest)
+		}
+	}
+}
+
+/// Paths of the year's photos, the ones sent or received most often first.
+/// Copies of one photo are told apart from other photos by name and size.
+fn most_shared_photos(chat_db_path: &Path, year: i32, limit: u32) -> AnalyzerResult<Vec<PathBuf>> {
+	let apple_seconds = |year: i32| {
+		Local
+			.with_ymd_and_hms(year, 1, 1, 0, 0, 0)
+			.earliest()
+			.map_or(0, |start| start.timestamp() - APPLE_EPOCH_OFFSET)
+	};
+
+	let db = readonly::open(chat_db_path)?;
+	let mut statement = readonly::prepare(
+		&db,
+		"SELECT a.filename
+		FROM attachment a
+		JOIN message_attachment_join j ON j.attachment_id = a.ROWID
+		JOIN message m ON m.ROWID = j.message_id
+		WHERE a.mime_type LIKE 'image/%' AND a.mime_type != 'image/gif'
+			AND a.filename IS NOT NULL
+			AND (CASE WHEN m.date > 1000000000000 THEN m.date / 1000000000 ELSE m.date END)
+				BETWEEN ?1 AND ?2
+		GROUP BY a.transfer_name, a.total_bytes
+		ORDER BY COUNT(*) DESC, MAX(m.date) DESC
+		LIMIT ?3"
+	)?;
+	let filenames = statement
+		.query_map(params![apple_seconds(year), apple_seconds(year + 1) - 1, limit], |row| {
+			row.get::<_, String>(0)
+		})?
+		.collect::<Result<Vec<_>, _>>()?;
+	drop(statement);
+	let _ = db.close();
+
+	// chat.db stores paths under the home folder as ~/Library/Messages/...
+	Ok(filenames
+		.into_iter()
+		.map(|filename| match filename.strip_prefix("~/") {
+			Some(relative) => paths::home_dir().join(relative),
+			None => PathBuf::from(filename)
+		})
+		.collect())
+}
@@ -0,0 +1,82 @@
+use std::sync::{Arc, Mutex};
+
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi_derive::napi;
+
+/// One progress update sent to the JavaScript callback.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct Progress {
+	/// Stable stage id, e.g. "loadingMessages" or "computingInsights"
+	#[napi(ts_type = "ProgressStage")]
+	pub stage: String,
+	/// Overall progress of the run, 0-100
+	pub percent: f64,
+	/// Extra context, e.g. the insight being computed
+	pub detail: Option<String>,
+	/// Rows read so far during long queries, sent as heartbeats
+	pub rows: Option<i64>,
+	/// Label of the person a batch run is analyzing
+	pub person: Option<String>
+}
+
+pub type ProgressCallback = ThreadsafeFunction<Progress, ErrorStrategy::Fatal>;
+
+/// Forwards progress to the optional callback passed to `fetch_stats` and
+/// friends, and to callbacks attached to the run later. Reporting never blocks
+/// the analysis.
+#[derive(Clone, Default)]
+pub struct Reporter {
+	callback: Option<ProgressCallback>,
+	attached: Arc<Mutex<Vec<ProgressCallback>>>,
+	last: Arc<Mutex<Option<Progress>>>,
+	person: Option<String>
+}
+
+impl Reporter {
+	pub fn new(callback: Option<ProgressCallback>) -> Self {
+		Self { callback, ..Default::default() }
+	}
+
+	/// Reports to the same callbacks, tagging every update with `person`.
+	pub fn for_person(&self, person: &str) -> Self {
+		Self { person: Some(person.to_string()), ..self.clone() }
+	}
+
+	pub fn report(&self, stage: &str, percent: f64) {
+		self.send(stage, percent, None, None);
+	}
+
+	pub fn report_detail(&self, stage: &str, percent: f64, detail: impl Into<String>) {
+		self.send(stage, percent, Some(detail.into()), None);
+	}
+
+	/// Keeps the UI alive during queries that run for minutes.
+	pub fn heartbeat(&self, stage: &str, percent: f64, rows: i64) {
+		self.send(stage, percent, None, Some(rows));
+	}
+
+	/// Sends this run's progress to `callback` too, starting with the latest
+	/// update.
+	pub fn attach(&self, callback: ProgressCallback) {
+		if let Some(progress) = self.last() {
+			callback.call(progress, ThreadsafeFunctionCallMode::NonBlocking);
+		}
+		self.attached.lock().unwrap().push(callback);
+	}
+
+	pub fn last(&self) -> Option<Progress> {
+		self.last.lock().unwrap().clone()
+	}
+
+	fn send(&self, stage: &str, percent: f64, detail: Option<String>, rows: Option<i64>) {
+		let person = self.person.clone();
+		let progress = Progress { stage: stage.to_string(), percent, detail, rows, person };
+		*self.last.lock().unwrap() = Some(progress.clone());
+
+		let attached = self.attached.lock().unwrap();
+		for callback in self.callback.iter().chain(attached.iter()) {
+			callback.call(progress.clone(), ThreadsafeFunctionCallMode::NonBlocking);
+		}
+	}
+}
@@ -0,0 +1,142 @@
+//! Local-only record of which messages went into the headline numbers, so a
+//! disputed stat ("I never said that word") can be checked by hand. Never
+//! uploaded.
+
+use std::path::PathBuf;
+
+use imessage_database::tables::messages::Message;
+use serde::Serialize;
+
+use crate::insights::words::words;
+use crate::insights::{is_tapback, year_messages};
+use crate::stats::stats::{Item, YearStats, YearsStats};
+use crate::{storage, AnalyzerResult};
+
+const PROVENANCE_FILE: &str = "provenance.json";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct YearProvenance {
+	year: i32,
+	stats: Vec<StatProvenance>
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatProvenance {
+	stat: String,
+	/// How the recount below was done
+	rule: &'static str,
+	/// The number in the wrapped
+	reported: i32,
+	/// The same number recounted from `message_guids`
+	recounted: i32,
+	message_guids: Vec<String>
+}
+
+/// Recounts message totals, top words, top emojis and the most sent text of
+/// every year, writes them with the contributing message GUIDs to the data
+/// directory and returns the file's path. Replaces the previous run's file.
+pub fn write(stats: &YearsStats, messages: &[Message]) -> AnalyzerResult<PathBuf> {
+	let years: Vec<YearProvenance> = stats
+		.stats
+		.iter()
+		.map(|year| YearProvenance {
+			year: year.year,
+			stats: year_provenance(year, year_messages(messages, year.year))
+		})
+		.collect();
+
+	storage::write_json(PROVENANCE_FILE, &years)?;
+	Ok(storage::data_dir().join(PROVENANCE_FILE))
+}
+
+fn year_provenance(year: &YearStats, messages: &[Message]) -> Vec<StatProvenance> {
+	let typed: Vec<&Message> = messages.iter().filter(|m| !is_tapback(m)).collect();
+	let mut stats = Vec::new();
+
+	for (side, from_me, reported) in [
+		("sent", true, year.message_count.sent),
+		("received", false, year.message_count.received)
+	] {
+		let guids: Vec<String> = typed
+			.iter()
+			.filter(|m| m.is_from_me == from_me)
+			.map(|m| m.guid.clone())
+			.collect();
+		stats.push(StatProvenance {
+			stat: format!("messageCount.{}", side),
+			rule: "messages in the year, tapbacks excluded",
+			reported,
+			recounted: guids.len() as i32,
+			message_guids: guids
+		});
+	}
+
+	let words_count = &year.word_count.words;
+	let emojis_count = &year.word_count.emojis;
+	for (side, from_me, items) in [
+		("sent", true, &words_count.sent),
+		("received", false, &words_count.received)
+	] {
+		for item in items {
+			let word = item.key.to_lowercase();
+			stats.push(recount(
+				format!("words.{}.{}", side, item.key),
+				"occurrences of the word, ignoring case and punctuation",
+				item,
+				&typed,
+				from_me,
+				|text| words(text).filter(|w| *w == word).count()
+			));
+		}
+	}
+	for (side, from_me, items) in [
+		("sent", true, &emojis_count.sent),
+		("received", false, &emojis_count.received)
+	] {
+		for item in items {
+			stats.push(recount(
+				format!("emojis.{}.{}", side, item.key),
+				"occurrences of the emoji",
+				item,
+				&typed,
+				from_me,
+				|text| text.matches(item.key.as_str()).count()
+			));
+		}
+	}
+
+	let most_sent = &year.most_sent;
+	stats.push(recount(
+		format!("mostSent.{}", most_sent.key),
+		"sent messages whose whole text is this",
+		most_sent,
+		&typed,
+		true,
+		|text| (text.trim() == most_sent.key.trim()) as usize
+	));
+
+	stats
+}
+
+fn recount<F>(
+	stat: String, rule: &'static str, item: &Item, messages: &[&Message], from_me: bool,
+	occurrences: F
+) -> StatProvenance
+where
+	F: Fn(&str) -> usize
+{
+	let mut recounted = 0;
+	let mut message_guids = Vec::new();
+	for message in messages.iter().filter(|m| m.is_from_me == from_me) {
+		let Some(text) = message.text.as_deref() else { continue };
+		let count = occurrences(text);
+		if count > 0 {
+			recounted += count as i32;
+			message_guids.push(message.guid.clone());
+		}
+	}
+
+	StatProvenance { stat, rule, reported: item.count, recounted, message_guids }
+}
@@ -0,0 +1,79 @@
+//! Guarantees that the analyzer never writes to the user's databases.
+//! Connections are opened read-only where we open them ourselves and are put
+//! in `query_only` mode either way, and every statement we prepare is checked
+//! by SQLite before it can run. The counters back `read_only_attestation`.
+
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rusqlite::{Connection, DatabaseName, OpenFlags, Statement};
+use serde::Serialize;
+
+use crate::AnalyzerResult;
+
+static READ_ONLY_CONNECTIONS: AtomicU64 = AtomicU64::new(0);
+static QUERY_ONLY_CONNECTIONS: AtomicU64 = AtomicU64::new(0);
+static CHECKED_STATEMENTS: AtomicU64 = AtomicU64::new(0);
+static BLOCKED_WRITES: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attestation {
+	/// True when no write was ever attempted since the library was loaded
+	pub read_only: bool,
+	/// Connections opened with the read-only flag
+	pub read_only_connections: u64,
+	/// Connections opened elsewhere that we could only switch to query_only
+	pub query_only_connections: u64,
+	pub checked_statements: u64,
+	pub blocked_writes: u64
+}
+
+/// Opens a database with explicit read-only flags.
+pub fn open<P>(path: P) -> AnalyzerResult<Connection>
+where
+	P: AsRef<Path>
+{
+	let db = Connection::open_with_flags(
+		path,
+		OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX
+	)?;
+	enforce(&db)?;
+	Ok(db)
+}
+
+/// Makes SQLite reject any write on the connection, including ones prepared
+/// inside imessage_database where `prepare` can't see them.
+pub fn enforce(db: &Connection) -> AnalyzerResult<()> {
+	db.pragma_update(None, "query_only", true)?;
+	if db.is_readonly(DatabaseName::Main)? {
+		READ_ONLY_CONNECTIONS.fetch_add(1, Ordering::Relaxed);
+	} else {
+		QUERY_ONLY_CONNECTIONS.fetch_add(1, Ordering::Relaxed);
+	}
+	Ok(())
+}
+
+/// Prepares a statement, refusing anything SQLite says could write.
+pub fn prepare<'a>(db: &'a Connection, sql: &str) -> AnalyzerResult<Statement<'a>> {
+	let statement = db.prepare(sql)?;
+	if !statement.readonly() {
+		BLOCKED_WRITES.fetch_add(1, Ordering::Relaxed);
+		let message = format!("Refusing to prepare a write against a user database: {}", sql);
+		return Err(io::Error::new(io::ErrorKind::PermissionDenied, message).into());
+	}
+	CHECKED_STATEMENTS.fetch_add(1, Ordering::Relaxed);
+	Ok(statement)
+}
+
+pub fn attestation() -> Attestation {
+	let blocked_writes = BLOCKED_WRITES.load(Ordering::Relaxed);
+	Attestation {
+		read_only: blocked_writes == 0,
+		read_only_connections: READ_ONLY_CONNECTIONS.load(Ordering::Relaxed),
+		query_only_connections: QUERY_ONLY_CONNECTIONS.load(Ordering::Relaxed),
+		checked_statements: CHECKED_STATEMENTS.load(Ordering::Relaxed),
+		blocked_writes
+	}
+}
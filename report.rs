@@ -0,0 +1,402 @@
+use prost::Message as ProstMessage;
+use serde::Serialize;
+
+use crate::payload_sizes::{self, StatSize};
+use crate::stats::stats::{YearStats, YearsStats};
+
+/// Summary of what an upload would disclose, shown to the user before they
+/// confirm the upload.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadReport {
+	pub years: Vec<i32>,
+	pub categories: Vec<&'static str>,
+	pub verbatim_texts: usize,
+	pub payload_size: usize,
+	/// Bytes each stat adds to `payload_size`, largest first
+	pub stat_sizes: Vec<StatSize>,
+	pub contact_names: Vec<String>,
+	/// Stats left out because the time budget ran out
+	pub skipped_stats: Vec<String>
+}
+
+impl UploadReport {
+	pub fn new(stats: &YearsStats) -> Self {
+		let mut categories = Vec::new();
+		let mut verbatim_texts = 0;
+		let mut contact_names = Vec::new();
+		let mut skipped_stats = Vec::new();
+
+		for year in &stats.stats {
+			for category in year_categories(year) {
+				if !categories.contains(&category) {
+					categories.push(category);
+				}
+			}
+			verbatim_texts += count_verbatim_texts(year);
+			for name in year_contact_names(year) {
+				if !name.is_empty() && !contact_names.contains(&name) {
+					contact_names.push(name);
+				}
+			}
+			for skipped in &year.skipped_stats {
+				if !skipped_stats.contains(skipped) {
+					skipped_stats.push(skipped.clone());
+				}
+			}
+		}
+
+		Self {
+			years: stats.years.clone(),
+			categories,
+			verbatim_texts,
+			payload_size: stats.encoded_len(),
+			stat_sizes: payload_sizes::stat_sizes(stats),
+			contact_names,
+			skipped_stats
+		}
+	}
+}
+
+fn year_categories(year: &YearStats) -> Vec<&'static str> {
+	let mut categories = vec!["messageCounts", "activityByTime"];
+	if year.word_count.is_some() {
+		categories.push("wordsAndEmojis");
+	}
+	if year.most_sent.as_ref().is_some_and(|item| !item.key.is_empty()) {
+		categories.push("mostSentText");
+	}
+	if !year.most_reactions.is_empty() {
+		categories.push("mostReactedMessages");
+	}
+	if year.top_group_chats.as_ref().is_some_and(|r| !r.chats.is_empty()) {
+		categories.push("topGroupChats");
+	}
+	if year.top_individual_chats.as_ref().is_some_and(|r| !r.chats.is_empty()) {
+		categories.push("topIndividualChats");
+	}
+	if year.top_left_on_read.is_some() {
+		categories.push("leftOnRead");
+	}
+	if year.fastest_responder.is_some() || year.slowest_responder.is_some() {
+		categories.push("responseTimes");
+	}
+	if year.longest_message.as_ref().is_some_and(|m| !m.message.is_empty()) {
+		categories.push("longestMessage");
+	}
+	if year.top_hater.is_some() || year.top_glazer.is_some() {
+		categories.push("reactions");
+	}
+	if year.top_user_by_slurs.is_some() ||
+		year.dirtiest_mouth.is_some() ||
+		year.most_degenerate.is_some()
+	{
+		categories.push("languageLeaderboards");
+	}
+	if year.contact_cards.as_ref().is_some_and(|c| c.sent + c.received > 0) {
+		categories.push("contactCards");
+	}
+	if !year.topics.is_empty() {
+		categories.push("topics");
+	}
+	if year.dryness.is_some() {
+		categories.push("dryness");
+	}
+	if year.response_time_leaderboard.as_ref().is_some_and(|r| !r.contacts.is_empty()) {
+		categories.push("responseTimeLeaderboard");
+	}
+	if year.chronotype.is_some() {
+		categories.push("chronotype");
+	}
+	if year.archetype.is_some() {
+		categories.push("archetype");
+	}
+	if !year.group_chat_profanity.is_empty() {
+		categories.push("groupChatProfanity");
+	}
+	if year.reaction_balance.as_ref().is_some_and(|r| !r.contacts.is_empty()) {
+		categories.push("reactionBalance");
+	}
+	if year.reaction_speed.as_ref().is_some_and(|r| !r.contacts.is_empty()) {
+		categories.push("reactionSpeed");
+	}
+	if year.longest_messages.is_some() {
+		categories.push("longestMessages");
+	}
+	if year.send_received_trend.as_ref().is_some_and(|t| !t.contacts.is_empty()) {
+		categories.push("sendReceivedTrend");
+	}
+	if year.standout_messages.is_some() {
+		categories.push("standoutMessages");
+	}
+	if year.short_replies.is_some() {
+		categories.push("shortReplies");
+	}
+	if year.dictation.is_some() {
+		categories.push("dictation");
+	}
+	if year.bursts.as_ref().is_some_and(|b| b.burst_count > 0) {
+		categories.push("bursts");
+	}
+	if year.silence_breaks.as_ref().is_some_and(|s| !s.contacts.is_empty()) {
+		categories.push("silenceBreaks");
+	}
+	if year.half_life.as_ref().is_some_and(|h| !h.contacts.is_empty()) {
+		categories.push("halfLife");
+	}
+	if !year.contact_hourly.is_empty() {
+		categories.push("contactHourly");
+	}
+	if year.social_graph.as_ref().is_some_and(|g| !g.most_connected.is_empty()) {
+		categories.push("socialGraph");
+	}
+	if year.emoji_only.as_ref().is_some_and(|e| e.sent + e.received > 0) {
+		categories.push("emojiOnly");
+	}
+	if year.question_latency.as_ref().is_some_and(|q| !q.contacts.is_empty()) {
+		categories.push("questionLatency");
+	}
+	if year.revivals.is_some() {
+		categories.push("revivals");
+	}
+	if year.emoji_of_the_month.iter().any(|item| item.count > 0) {
+		categories.push("emojiOfTheMonth");
+	}
+	if year.emoji_arc.as_ref().is_some_and(|a| a.average.is_some()) {
+		categories.push("emojiArc");
+	}
+	if !year.nicknames.is_empty() {
+		categories.push("nicknames");
+	}
+	if year.photo_dumps.as_ref().is_some_and(|p| p.dump_count > 0) {
+		categories.push("photoDumps");
+	}
+	if !year.top_shared_links.is_empty() {
+		categories.push("sharedLinks");
+	}
+	if year.word_balance.as_ref().is_some_and(|b| !b.contacts.is_empty()) {
+		categories.push("wordBalance");
+	}
+	if year.word_cloud.as_ref().is_some_and(|c| !c.words.is_empty()) {
+		categories.push("wordCloud");
+	}
+	if year.sentiment.as_ref().is_some_and(|s| s.monthly.iter().any(|m| m.messages > 0)) {
+		categories.push("sentiment");
+	}
+	if year.length_histogram.as_ref().is_some_and(|h| h.overall.iter().any(|b| b.count > 0)) {
+		categories.push("lengthHistogram");
+	}
+	if year.robots.is_some() {
+		categories.push("robots");
+	}
+	if year.group_direct_split.is_some() {
+		categories.push("groupDirectSplit");
+	}
+	if year.shared_with_you.is_some() {
+		categories.push("sharedWithYou");
+	}
+	if !year.relationships.is_empty() {
+		categories.push("relationships");
+	}
+	if year.year_over_year.is_some() {
+		categories.push("yearOverYear");
+	}
+	if year.attachments.is_some() {
+		categories.push("attachments");
+	}
+	if !year.group_chats.is_empty() {
+		categories.push("groupChats");
+	}
+	if year.system_messages.is_some() {
+		categories.push("systemMessages");
+	}
+	categories
+}
+
+/// Counts message texts that are copied into the payload as-is.
+fn count_verbatim_texts(year: &YearStats) -> usize {
+	let most_sent = year.most_sent.as_ref().is_some_and(|item| !item.key.is_empty());
+	let longest = year.longest_message.as_ref().is_some_and(|m| !m.message.is_empty());
+	let reacted = year
+		.most_reactions
+		.iter()
+		.filter(|summary| !summary.message_content.is_empty())
+		.count();
+
+	let superlatives = year
+		.longest_messages
+		.iter()
+		.flat_map(|l| [&l.sent, &l.received])
+		.flatten()
+		.filter(|m| m.text.is_some())
+		.count();
+
+	let standouts = year
+		.standout_messages
+		.iter()
+		.flat_map(|s| [&s.most_reacted, &s.most_replied])
+		.flatten()
+		.filter(|m| m.text.is_some())
+		.count();
+
+	// Link titles and nicknames are copied out of messages too
+	let link_titles = year.top_shared_links.iter().filter(|link| !link.title.is_empty()).count();
+	let nicknames = year.nicknames.iter().filter(|n| !n.nickname.is_empty()).count();
+
+	usize::from(most_sent) +
+		usize::from(longest) +
+		reacted +
+		superlatives +
+		standouts +
+		link_titles +
+		nicknames
+}
+
+fn year_contact_names(year: &YearStats) -> Vec<String> {
+	let mut names = Vec::new();
+
+	for result in [&year.top_individual_chats, &year.top_group_chats, &year.top_down_bad_chats]
+		.into_iter()
+		.flatten()
+	{
+		names.extend(result.chats.iter().map(|chat| chat.name.clone()));
+	}
+	for by_chat in [&year.top_texters_by_top_chat, &year.top_group_chat_by_slurs]
+		.into_iter()
+		.flatten()
+	{
+		names.push(by_chat.name.clone());
+		names.extend(by_chat.top_texters.iter().map(|texter| texter.name.clone()));
+	}
+	names.extend(year.top_user_by_slurs.iter().map(|chat| chat.name.clone()));
+	if let Some(left_on_read) = &year.top_left_on_read {
+		names.extend(left_on_read.by_chat.iter().map(|chat| chat.name.clone()));
+	}
+	names.extend(year.most_reactions.iter().map(|summary| summary.name.clone()));
+	names.extend(year.fastest_responder.iter().map(|s| s.name.clone()));
+	names.extend(year.slowest_responder.iter().map(|s| s.name.clone()));
+	names.extend(year.longest_message.iter().map(|s| s.name.clone()));
+	names.extend(year.top_hater.iter().map(|s| s.name.clone()));
+	names.extend(year.top_glazer.iter().map(|s| s.name.clone()));
+	names.extend(year.top_double_texter.iter().map(|s| s.name.clone()));
+	names.extend(year.worst_send_received_ratio.iter().map(|s| s.name.clone()));
+	for phrase in [
+		&year.top_favor_asker,
+		&year.top_freaky_texter,
+		&year.top_realest_friend,
+		&year.dirtiest_mouth,
+		&year.most_degenerate
+	] {
+		names.extend(phrase.iter().map(|s| s.name.clone()));
+	}
+	if let Some(cards) = &year.contact_cards {
+		names.extend(cards.top_card_sender.iter().map(|s| s.name.clone()));
+		names.extend(cards.most_shared_contacts.iter().map(|item| item.key.clone()));
+	}
+	names.extend(year.topics.iter().map(|t| t.name.clone()));
+	if let Some(dryness) = &year.dryness {
+		names.extend(dryness.contacts.iter().map(|s| s.name.clone()));
+	}
+	if let Some(leaderboard) = &year.response_time_leaderboard {
+		names.extend(leaderboard.contacts.iter().map(|s| s.name.clone()));
+	}
+	for chat in &year.group_chat_profanity {
+		names.push(chat.name.clone());
+		names.extend(chat.members.iter().map(|s| s.name.clone()));
+	}
+	if let Some(balance) = &year.reaction_balance {
+		names.extend(balance.contacts.iter().map(|s| s.name.clone()));
+		names.extend(balance.least_reacted_to.iter().map(|s| s.name.clone()));
+	}
+	if let Some(speed) = &year.reaction_speed {
+		names.extend(speed.contacts.iter().map(|s| s.name.clone()));
+		names.extend(speed.fastest_reactor.iter().map(|s| s.name.clone()));
+	}
+	if let Some(longest) = &year.longest_messages {
+		names.extend(
+			[&longest.sent, &longest.received].into_iter().flatten().map(|m| m.name.clone())
+		);
+	}
+	if let Some(trend) = &year.send_received_trend {
+		names.extend(trend.contacts.iter().map(|s| s.name.clone()));
+	}
+	if let Some(short_replies) = &year.short_replies {
+		names.extend(short_replies.contacts.iter().map(|s| s.name.clone()));
+		names.extend(short_replies.most_dismissive.iter().map(|s| s.name.clone()));
+	}
+	if let Some(silences) = &year.silence_breaks {
+		names.extend(silences.contacts.iter().map(|s| s.name.clone()));
+		names.extend(silences.i_cave_first.iter().map(|s| s.name.clone()));
+	}
+	if let Some(half_life) = &year.half_life {
+		names.extend(half_life.contacts.iter().map(|h| h.name.clone()));
+	}
+	names.extend(year.contact_hourly.iter().map(|h| h.name.clone()));
+	if let Some(graph) = &year.social_graph {
+		names.extend(graph.most_connected.iter().map(|f| f.name.clone()));
+		names.extend(graph.bridge.iter().map(|f| f.name.clone()));
+	}
+	if let Some(emoji_only) = &year.emoji_only {
+		names.extend(
+			[&emoji_only.top_contact, &emoji_only.longest_streak]
+				.into_iter()
+				.flatten()
+				.map(|s| s.name.clone())
+		);
+	}
+	if let Some(questions) = &year.question_latency {
+		names.extend(questions.contacts.iter().map(|q| q.name.clone()));
+		names.extend(questions.my_worst_backlog.iter().map(|q| q.name.clone()));
+	}
+	if let Some(revivals) = &year.revivals {
+		names.extend(revivals.top_revivers.iter().map(|s| s.name.clone()));
+		names.extend(revivals.my_biggest_necropost.iter().map(|n| n.chat_name.clone()));
+	}
+	if let Some(standouts) = &year.standout_messages {
+		names.extend(
+			[&standouts.most_reacted, &standouts.most_replied]
+				.into_iter()
+				.flatten()
+				.map(|m| m.chat_name.clone())
+		);
+	}
+	if let Some(burst) = year.bursts.as_ref().and_then(|b| b.biggest.as_ref()) {
+		names.push(burst.chat_name.clone());
+	}
+	if let Some(dump) = year.photo_dumps.as_ref().and_then(|p| p.biggest.as_ref()) {
+		names.push(dump.chat_name.clone());
+	}
+	names.extend(year.nicknames.iter().map(|n| n.name.clone()));
+	if let Some(sharer) = year.shared_with_you.as_ref().and_then(|s| s.biggest_sharer.as_ref()) {
+		names.push(sharer.name.clone());
+	}
+	names.extend(year.relationships.iter().map(|r| r.name.clone()));
+	if let Some(balance) = &year.word_balance {
+		names.extend(balance.contacts.iter().map(|b| b.name.clone()));
+	}
+	if let Some(sentiment) = &year.sentiment {
+		names.extend(sentiment.contacts.iter().map(|s| s.name.clone()));
+	}
+	if let Some(histogram) = &year.length_histogram {
+		names.extend(histogram.contacts.iter().map(|h| h.name.clone()));
+	}
+	if let Some(twin) = year.chronotype.as_ref().and_then(|c| c.schedule_twin.as_ref()) {
+		names.push(twin.name.clone());
+	}
+	for chat in &year.group_chats {
+		names.push(chat.name.clone());
+		names.extend(
+			[&chat.carrier, &chat.most_reacted_to].into_iter().flatten().map(|s| s.name.clone())
+		);
+	}
+	if let Some(partner) = year.attachments.as_ref().and_then(|a| a.top_media_partner.as_ref()) {
+		names.push(partner.name.clone());
+	}
+	if let Some(comparison) = &year.year_over_year {
+		names.extend(
+			comparison.top_contact.iter().chain(&comparison.previous_top_contact).cloned()
+		);
+	}
+
+	names
+}
@@ -0,0 +1,257 @@
+//! Keeps two runs from reading chat.db at the same time. Inside the process a
+//! second run can attach to the first one's progress; across processes (the
+//! app and the CLI) a lock file in the data directory is used, and locks left
+//! behind by a crashed run are taken over.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::progress::{ProgressCallback, Reporter};
+use crate::{storage, AnalyzerError, AnalyzerResult};
+
+const LOCK_FILE: &str = "run.lock";
+/// Locks older than this are considered left behind even if the process
+/// can't be checked
+const STALE_AFTER: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// How long an unreadable lock file is left alone, in case a run of an older
+/// version, which wrote the lock in place, is still writing it
+const UNREADABLE_GRACE: Duration = Duration::from_secs(10);
+
+static ACTIVE: Mutex<Option<Reporter>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LockInfo {
+	pid: u32,
+	started_at: u64
+}
+
+/// Held for the duration of a run. Dropping it releases the lock.
+pub struct RunLock {
+	path: PathBuf
+}
+
+impl Drop for RunLock {
+	fn drop(&mut self) {
+		let _ = fs::remove_file(&self.path);
+		ACTIVE.lock().unwrap().take();
+	}
+}
+
+/// Takes the run lock, or fails with `AnalyzerError::AlreadyRunning`.
+pub fn acquire(progress: &Reporter) -> AnalyzerResult<RunLock> {
+	let mut active = ACTIVE.lock().unwrap();
+	if active.is_some() {
+		return Err(AnalyzerError::AlreadyRunning);
+	}
+
+	let path = storage::ensure_data_dir()?.join(LOCK_FILE);
+	let info = LockInfo { pid: std::process::id(), started_at: now() };
+	if !lock(&path, &info)? {
+		return Err(AnalyzerError::AlreadyRunning);
+	}
+
+	*active = Some(progress.clone());
+	Ok(RunLock { path })
+}
+
+/// Sends the in-flight run's progress to `callback` as well. Returns false
+/// when nothing is running in this process.
+pub fn attach(callback: ProgressCallback) -> bool {
+	match ACTIVE.lock().unwrap().as_ref() {
+		Some(progress) => {
+			progress.attach(callback);
+			true
+		}
+		None => false
+	}
+}
+
+/// Whether a run is in progress in this process or another one.
+pub fn is_running() -> bool {
+	ACTIVE.lock().unwrap().is_some() || {
+		let path = storage::data_dir().join(LOCK_FILE);
+		fs::read(&path).is_ok_and(|contents| !is_stale(&path, &contents))
+	}
+}
+
+/// Creates the lock file at `path`, taking over a stale one. The lock is
+/// written in full under a name of its own and hard-linked into place, which
+/// fails when a lock exists, so no run ever sees it half-written. Returns
+/// false when another run holds the lock.
+fn lock(path: &Path, info: &LockInfo) -> io::Result<bool> {
+	let staged = sibling(path, &info.pid.to_string());
+	fs::write(&staged, serde_json::to_vec(info)?)?;
+	let _cleanup = scopeguard::guard(&staged, |staged| {
+		let _ = fs::remove_file(staged);
+	});
+
+	match fs::hard_link(&staged, path) {
+		Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+			Ok(take_over(path) && fs::hard_link(&staged, path).is_ok())
+		}
+		result => result.map(|()| true)
+	}
+}
+
+/// Removes the lock at `path` if it is stale. It is renamed to a name of our
+/// own first, so of several runs taking it over only one gets it, and only
+/// removed if it still holds what was found stale. Otherwise another run
+/// replaced it in between and it is put back.
+fn take_over(path: &Path) -> bool {
+	let contents = match fs::read(path) {
+		Ok(contents) => contents,
+		Err(e) => return e.kind() == io::ErrorKind::NotFound
+	};
+	if !is_stale(path, &contents) {
+		return false;
+	}
+
+	let moved = sibling(path, &format!("stale.{}", std::process::id()));
+	if fs::rename(path, &moved).is_err() {
+		return false;
+	}
+	let unchanged = fs::read(&moved).is_ok_and(|moved| moved == contents);
+	if !unchanged {
+		let _ = fs::hard_link(&moved, path);
+	}
+	let _ = fs::remove_file(&moved);
+	unchanged
+}
+
+/// `path` with `suffix` appended to its file name, e.g. "run.lock.123".
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+	let mut name = path.file_name().unwrap_or_default().to_os_string();
+	name.push(format!(".{}", suffix));
+	path.with_file_name(name)
+}
+
+/// A lock is stale when its process is gone, it is too old, or it still
+/// can't be read a while after it was created.
+fn is_stale(path: &Path, contents: &[u8]) -> bool {
+	let Ok(info) = serde_json::from_slice::<LockInfo>(contents) else {
+		let age = fs::metadata(path).and_then(|m| m.modified()).map(|m| m.elapsed());
+		return age.map_or(true, |age| age.unwrap_or_default() > UNREADABLE_GRACE);
+	};
+
+	// Our own pid can only be left over, a live run here would be in ACTIVE
+	info.pid == std::process::id() ||
+		!is_alive(info.pid) ||
+		now().saturating_sub(info.started_at) > STALE_AFTER.as_secs()
+}
+
+/// Signal 0 only checks the process exists. EPERM means it does but belongs
+/// to another user.
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+	let Ok(pid) = libc::pid_t::try_from(pid) else { return false };
+	// SAFETY: kill with signal 0 sends nothing
+	if unsafe { libc::kill(pid, 0) } == 0 {
+		return true;
+	}
+	io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Asks tasklist for the process, which prints only a notice when there is
+/// none. Counts as alive when tasklist can't be run, leaving the lock to
+/// `STALE_AFTER`.
+#[cfg(not(unix))]
+fn is_alive(pid: u32) -> bool {
+	let output = std::process::Command::new("tasklist")
+		.args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+		.output();
+	match output {
+		Ok(output) if output.status.success() => {
+			String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid))
+		}
+		_ => true
+	}
+}
+
+fn now() -> u64 {
+	SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Lock owners are checked with kill(2), elsewhere through tasklist.
+#[cfg(all(test, unix))]
+mod tests {
+	use super::*;
+
+	/// A directory of its own per test, the tests run in parallel
+	fn lock_path(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir()
+			.join(format!("messages-wrapped-lock-{}-{}", name, std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		dir.join(LOCK_FILE)
+	}
+
+	/// pid 1 is init or launchd, always alive and never this test
+	fn other_run(started_at: u64) -> LockInfo {
+		LockInfo { pid: 1, started_at }
+	}
+
+	fn this_run() -> LockInfo {
+		LockInfo { pid: std::process::id(), started_at: now() }
+	}
+
+	fn owner(path: &Path) -> u32 {
+		serde_json::from_slice::<LockInfo>(&fs::read(path).unwrap()).unwrap().pid
+	}
+
+	#[test]
+	fn acquires_a_free_lock_and_refuses_a_held_one() {
+		let path = lock_path("held");
+		assert!(lock(&path, &other_run(now())).unwrap());
+		assert!(!lock(&path, &this_run()).unwrap());
+		assert_eq!(owner(&path), 1);
+		// The staged copies are cleaned up, only the lock remains
+		assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+	}
+
+	#[test]
+	fn dropping_the_lock_releases_it() {
+		let path = lock_path("release");
+		assert!(lock(&path, &other_run(now())).unwrap());
+		drop(RunLock { path: path.clone() });
+		assert!(!path.exists());
+		assert!(lock(&path, &this_run()).unwrap());
+	}
+
+	#[test]
+	fn takes_over_locks_of_dead_and_old_runs() {
+		let path = lock_path("stale");
+		// Not a valid pid, no process can have it
+		let dead = LockInfo { pid: u32::MAX, started_at: now() };
+		fs::write(&path, serde_json::to_vec(&dead).unwrap()).unwrap();
+		assert!(lock(&path, &this_run()).unwrap());
+		assert_eq!(owner(&path), std::process::id());
+
+		let old = other_run(now() - STALE_AFTER.as_secs() - 1);
+		fs::write(&path, serde_json::to_vec(&old).unwrap()).unwrap();
+		assert!(lock(&path, &this_run()).unwrap());
+		assert_eq!(owner(&path), std::process::id());
+		assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+	}
+
+	#[test]
+	fn detects_stale_locks() {
+		let path = lock_path("detect");
+		let contents = |info: LockInfo| serde_json::to_vec(&info).unwrap();
+		assert!(!is_stale(&path, &contents(other_run(now()))));
+		assert!(is_stale(&path, &contents(other_run(now() - STALE_AFTER.as_secs() - 1))));
+		assert!(is_stale(&path, &contents(LockInfo { pid: u32::MAX, started_at: now() })));
+		// A live run of this process would be in ACTIVE
+		assert!(is_stale(&path, &contents(this_run())));
+
+		// A fresh unreadable lock may still be being written
+		fs::write(&path, b"").unwrap();
+		assert!(!is_stale(&path, b""));
+	}
+}
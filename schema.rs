@@ -0,0 +1,72 @@
+//! Detects which macOS release wrote chat.db, so queries can skip columns the
+//! database doesn't have instead of failing on older Catalina-era files. This
+//! covers our own queries on the side tables (link previews, Shared with You,
+//! identities, iCloud history). The message rows themselves come from
+//! imessage_database's `Message::get`, which has its own fallbacks for older
+//! schemas.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use rusqlite::Connection;
+
+use crate::{readonly, AnalyzerResult};
+
+/// The oldest release with each schema change we rely on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SchemaVersion {
+	/// macOS 10.15 and earlier
+	Catalina,
+	/// macOS 11-12, adds inline replies (`thread_originator_guid`)
+	BigSur,
+	/// macOS 13+, adds edits and Shared with You (`syndication_ranges`)
+	Ventura
+}
+
+impl fmt::Display for SchemaVersion {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(match self {
+			SchemaVersion::Catalina => "macOS 10.15 or earlier",
+			SchemaVersion::BigSur => "macOS 11-12",
+			SchemaVersion::Ventura => "macOS 13 or later"
+		})
+	}
+}
+
+pub struct Schema {
+	pub version: SchemaVersion,
+	columns: HashMap<String, HashSet<String>>
+}
+
+impl Schema {
+	pub fn probe(db: &Connection) -> AnalyzerResult<Self> {
+		let mut columns: HashMap<String, HashSet<String>> = HashMap::new();
+		let mut statement = readonly::prepare(
+			db,
+			"SELECT m.name, p.name FROM sqlite_master m, pragma_table_info(m.name) p
+			WHERE m.type = 'table'"
+		)?;
+		let rows = statement
+			.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+		for row in rows {
+			let (table, column) = row?;
+			columns.entry(table).or_default().insert(column);
+		}
+
+		let message = columns.get("message");
+		let has = |column: &str| message.is_some_and(|columns| columns.contains(column));
+		let version = if has("syndication_ranges") || has("date_edited") {
+			SchemaVersion::Ventura
+		} else if has("thread_originator_guid") {
+			SchemaVersion::BigSur
+		} else {
+			SchemaVersion::Catalina
+		};
+
+		Ok(Self { version, columns })
+	}
+
+	pub fn has_column(&self, table: &str, column: &str) -> bool {
+		self.columns.get(table).is_some_and(|columns| columns.contains(column))
+	}
+}
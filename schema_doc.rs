@@ -0,0 +1,109 @@
+//! Machine-readable description of the `YearsStats` payload, generated from
+//! stats.proto so the web viewer and other tools can follow schema changes
+//! without hand-written copies.
+
+use serde::Serialize;
+
+use crate::build_info;
+
+const PROTO: &str = include_str!("stats.proto");
+const ROOT: &str = "YearsStats";
+const SCALARS: &[&str] = &[
+	"double", "float", "int32", "int64", "uint32", "uint64", "sint32", "sint64", "fixed32",
+	"fixed64", "sfixed32", "sfixed64", "bool", "string", "bytes"
+];
+/// Field name suffixes that tell the unit of a number
+const UNITS: &[(&str, &str)] =
+	&[("_seconds", "seconds"), ("_minutes", "minutes"), ("_hours", "hours"), ("_days", "days")];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaDoc {
+	pub package: String,
+	/// Same hash as `BuildInfo.schema_hash` in payloads built from this schema
+	pub schema_hash: String,
+	pub root: &'static str,
+	pub messages: Vec<MessageDoc>
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageDoc {
+	pub name: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub description: Option<String>,
+	pub fields: Vec<FieldDoc>
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldDoc {
+	pub name: String,
+	pub number: u32,
+	/// "required", "optional" or "repeated"
+	pub label: String,
+	/// Scalar type or the name of another message
+	#[serde(rename = "type")]
+	pub type_name: String,
+	pub is_message: bool,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub unit: Option<&'static str>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub description: Option<String>
+}
+
+/// Describes every message in stats.proto, in file order.
+pub fn describe() -> SchemaDoc {
+	let mut package = String::new();
+	let mut messages = Vec::new();
+	let mut current: Option<MessageDoc> = None;
+	let mut comments: Vec<&str> = Vec::new();
+
+	for line in PROTO.lines().map(str::trim) {
+		if let Some(comment) = line.strip_prefix("//") {
+			comments.push(comment.trim());
+			continue;
+		}
+		let description = (!comments.is_empty()).then(|| comments.join(" "));
+		comments.clear();
+
+		if let Some(name) = line.strip_prefix("package ") {
+			package = name.trim_end_matches(';').trim().to_string();
+		} else if let Some(name) = line.strip_prefix("message ") {
+			let name = name.trim_end_matches('{').trim().to_string();
+			current = Some(MessageDoc { name, description, fields: Vec::new() });
+		} else if line == "}" {
+			messages.extend(current.take());
+		} else if let Some(message) = current.as_mut() {
+			if let Some(field) = parse_field(line, description) {
+				message.fields.push(field);
+			}
+		}
+	}
+
+	SchemaDoc { package, schema_hash: build_info::schema_hash(), root: ROOT, messages }
+}
+
+/// Parses `label type name = number; // comment`.
+fn parse_field(line: &str, description: Option<String>) -> Option<FieldDoc> {
+	let (declaration, trailing) = match line.split_once("//") {
+		Some((declaration, comment)) => (declaration, Some(comment.trim().to_string())),
+		None => (line, None)
+	};
+	let (left, number) = declaration.trim().trim_end_matches(';').split_once('=')?;
+	let mut parts = left.split_whitespace();
+	let (label, type_name, name) = (parts.next()?, parts.next()?, parts.next()?);
+	if !matches!(label, "required" | "optional" | "repeated") {
+		return None;
+	}
+
+	Some(FieldDoc {
+		name: name.to_string(),
+		number: number.trim().parse().ok()?,
+		label: label.to_string(),
+		type_name: type_name.to_string(),
+		is_message: !SCALARS.contains(&type_name),
+		unit: UNITS.iter().find(|(suffix, _)| name.ends_with(suffix)).map(|&(_, unit)| unit),
+		description: trailing.or(description)
+	})
+}
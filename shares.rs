@@ -0,0 +1,121 @@
+use std::io;
+use std::time::{Duration, SystemTime};
+
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::network::PinnedClient;
+use crate::{storage, AnalyzerResult};
+
+/// Receipts sealed with the device key
+const RECEIPTS_FILE: &str = "shares.sealed";
+/// Plaintext receipts written by earlier versions, moved into
+/// `RECEIPTS_FILE` on the next save
+const LEGACY_RECEIPTS_FILE: &str = "shares.json";
+
+/// Local record of a share uploaded from this machine. The share URL carries
+/// the decryption key in its fragment and the delete token controls the
+/// share, so receipts are only ever written sealed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareReceipt {
+	pub id: String,
+	pub base_url: String,
+	pub share_url: String,
+	pub delete_token: Option<String>,
+	pub created_at: u64,
+	#[serde(default)]
+	pub years: Vec<i32>,
+	/// Hex SHA-256 of the plaintext payload that was uploaded
+	#[serde(default)]
+	pub payload_hash: Option<String>,
+	/// GUID of the chat a group chat wrapped was made for
+	#[serde(default)]
+	pub group_chat: Option<String>
+}
+
+impl ShareReceipt {
+	pub fn new(
+		id: String, base_url: String, share_url: String, delete_token: Option<String>,
+		years: Vec<i32>, payload_hash: String, group_chat: Option<String>
+	) -> Self {
+		Self {
+			id,
+			base_url,
+			share_url,
+			delete_token,
+			created_at: SystemTime::now()
+				.duration_since(SystemTime::UNIX_EPOCH)
+				.unwrap_or_default()
+				.as_secs(),
+			years,
+			payload_hash: Some(payload_hash),
+			group_chat
+		}
+	}
+
+	/// The base64 key stored in the share URL fragment.
+	pub fn key(&self) -> &str {
+		self.share_url.split_once('#').map(|(_, key)| key).unwrap_or_default()
+	}
+}
+
+pub fn load() -> AnalyzerResult<Vec<ShareReceipt>> {
+	let mut receipts: Vec<ShareReceipt> = storage::read_json(LEGACY_RECEIPTS_FILE)?;
+	receipts.extend(storage::read_sealed_json::<Vec<ShareReceipt>>(RECEIPTS_FILE)?);
+	Ok(receipts)
+}
+
+pub fn save(receipts: &[ShareReceipt]) -> AnalyzerResult<()> {
+	storage::write_sealed_json(RECEIPTS_FILE, &receipts)?;
+	Ok(storage::remove(LEGACY_RECEIPTS_FILE)?)
+}
+
+pub fn record(receipt: ShareReceipt) -> AnalyzerResult<()> {
+	let mut receipts = load()?;
+	receipts.push(receipt);
+	save(&receipts)
+}
+
+/// Returns the last share uploaded for the same years (and group chat) to the
+/// same server if its payload is identical to `payload_hash`, so it can be
+/// reused instead of creating a duplicate.
+pub fn find_unchanged(
+	base_url: &str, years: &[i32], group_chat: Option<&str>, payload_hash: &str
+) -> AnalyzerResult<Option<ShareReceipt>> {
+	let last = load()?.into_iter().rev().find(|receipt| {
+		receipt.base_url == base_url &&
+			receipt.years == years &&
+			receipt.group_chat.as_deref() == group_chat
+	});
+
+	Ok(last.filter(|receipt| receipt.payload_hash.as_deref() == Some(payload_hash)))
+}
+
+/// Asks the server that hosts a share to delete it.
+pub async fn delete_remote(receipt: &ShareReceipt) -> AnalyzerResult<()> {
+	let delete_url = format!("{}/api/upload/{}", receipt.base_url, receipt.id);
+
+	let network = PinnedClient::new(&receipt.base_url).await?;
+	let mut request = network.request(Method::DELETE, &delete_url)?.timeout(Duration::from_secs(30));
+	if let Some(token) = &receipt.delete_token {
+		request = request.header("X-Delete-Token", token);
+	}
+
+	let response = request.send().await.map_err(|e| {
+		io::Error::new(io::ErrorKind::Other, format!("Delete failed: {} (URL: {})", e, delete_url))
+	})?;
+	let response = network.verify(response)?;
+
+	// A share that is already gone counts as deleted
+	let status = response.status();
+	if !status.is_success() && status != reqwest::StatusCode::NOT_FOUND {
+		return Err(io::Error::new(
+			io::ErrorKind::Other,
+			format!("Delete of share {} failed with status {}", receipt.id, status)
+		)
+		.into());
+	}
+
+	Ok(())
+}
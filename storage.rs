@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+use std::{fs, io};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{archive, paths, AnalyzerResult};
+
+/// Root directory for everything the analyzer persists between runs (share
+/// receipts, caches, archives). Removing it removes all local state.
+pub fn data_dir() -> PathBuf {
+	paths::data_dir()
+}
+
+pub fn ensure_data_dir() -> io::Result<PathBuf> {
+	let dir = data_dir();
+	fs::create_dir_all(&dir)?;
+	Ok(dir)
+}
+
+/// Reads a JSON document from the data directory, returning the default value
+/// when it has never been written.
+pub fn read_json<T>(name: &str) -> AnalyzerResult<T>
+where
+	T: DeserializeOwned + Default
+{
+	match fs::read(data_dir().join(name)) {
+		Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+		Err(e) => Err(e.into())
+	}
+}
+
+/// Writes a JSON document to the data directory. The write goes through a
+/// temporary file so a crash never leaves a half-written document behind.
+pub fn write_json<T>(name: &str, value: &T) -> AnalyzerResult<()>
+where
+	T: Serialize
+{
+	write(name, &serde_json::to_vec_pretty(value)?)
+}
+
+/// Reads a JSON document written by `write_sealed_json`, returning the
+/// default value when it has never been written.
+pub fn read_sealed_json<T>(name: &str) -> AnalyzerResult<T>
+where
+	T: DeserializeOwned + Default
+{
+	match fs::read(data_dir().join(name)) {
+		Ok(contents) => Ok(serde_json::from_slice(&archive::unseal(&contents)?)?),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+		Err(e) => Err(e.into())
+	}
+}
+
+/// Like `write_json`, for documents that hold secrets: the JSON is sealed
+/// with the device key before it's written.
+pub fn write_sealed_json<T>(name: &str, value: &T) -> AnalyzerResult<()>
+where
+	T: Serialize
+{
+	write(name, &archive::seal(&serde_json::to_vec(value)?)?)
+}
+
+/// Deletes one document from the data directory, if it's there.
+pub fn remove(name: &str) -> io::Result<()> {
+	match fs::remove_file(data_dir().join(name)) {
+		Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+		_ => Ok(())
+	}
+}
+
+fn write(name: &str, contents: &[u8]) -> AnalyzerResult<()> {
+	let dir = ensure_data_dir()?;
+	let tmp_path = dir.join(format!("{}.tmp", name));
+	fs::write(&tmp_path, contents)?;
+	fs::rename(&tmp_path, dir.join(name))?;
+	Ok(())
+}
+
+/// Deletes the whole data directory. Returns whether anything was removed.
+pub fn remove_all() -> io::Result<bool> {
+	match fs::remove_dir_all(data_dir()) {
+		Ok(()) => Ok(true),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+		Err(e) => Err(e)
+	}
+}
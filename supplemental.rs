@@ -0,0 +1,174 @@
+//! Supplemental chat.db copies (e.g. an archive kept before old messages were
+//! deleted) whose messages are merged into the analysis.
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::Path;
+
+use imessage_database::tables::messages::Message;
+use rusqlite::Connection;
+
+use crate::from_query::QueryAll;
+use crate::progress::Reporter;
+use crate::{busy, readonly, storage, AnalyzerResult};
+
+const REGISTRY_FILE: &str = "supplemental_databases.json";
+
+pub fn list() -> AnalyzerResult<Vec<String>> {
+	storage::read_json(REGISTRY_FILE)
+}
+
+/// Registers a chat.db copy. Returns false if it was already registered.
+pub fn add(path: &str) -> AnalyzerResult<bool> {
+	if !Path::new(path).is_file() {
+		let message = format!("{} does not exist", path);
+		return Err(io::Error::new(io::ErrorKind::NotFound, message).into());
+	}
+	let mut paths = list()?;
+	if paths.iter().any(|p| p == path) {
+		return Ok(false);
+	}
+	paths.push(path.to_string());
+	storage::write_json(REGISTRY_FILE, &paths)?;
+	Ok(true)
+}
+
+/// Returns whether the path was registered.
+pub fn remove(path: &str) -> AnalyzerResult<bool> {
+	let mut paths = list()?;
+	let before = paths.len();
+	paths.retain(|p| p != path);
+	if paths.len() == before {
+		return Ok(false);
+	}
+	storage::write_json(REGISTRY_FILE, &paths)?;
+	Ok(true)
+}
+
+/// Appends the messages of every registered database that aren't already in
+/// `messages`, matched by GUID. Handles and chats are mapped onto the main
+/// database by identifier. Chats it doesn't know are dropped; handles it
+/// doesn't know get ids of their own, see `map_handle`. Merged messages get
+/// negative ROWIDs so they never pick up another message's attachments or
+/// previews. Returns how many were added.
+pub fn merge_into(
+	main_db: &Connection, messages: &mut Vec<Message>, progress: &Reporter
+) -> AnalyzerResult<usize> {
+	let paths = list()?;
+	if paths.is_empty() {
+		return Ok(0);
+	}
+
+	let main_handles = identifiers(main_db, "SELECT ROWID, id FROM handle")?;
+	let main_chats = identifiers(main_db, "SELECT ROWID, guid FROM chat")?;
+	let mut seen: HashSet<String> = messages.iter().map(|m| m.guid.clone()).collect();
+	let mut next_rowid = -1;
+	let mut next_unresolved = -1;
+	let mut added = 0;
+
+	for path in paths {
+		let db = match readonly::open(&path) {
+			Ok(db) => db,
+			Err(e) => {
+				eprintln!("Skipping supplemental database {}: {:?}", path, e);
+				continue;
+			}
+		};
+		if let Err(e) = busy::configure(&db) {
+			eprintln!("Failed to set busy timeout on {}: {:?}", path, e);
+		}
+		let handles = remap(&db, "SELECT ROWID, id FROM handle", &main_handles)?;
+		let mut unresolved = HashMap::new();
+		let chats = remap(&db, "SELECT ROWID, guid FROM chat", &main_chats)?;
+
+		for mut message in busy::with_retry(progress, || Ok(Message::query_all(&db, [])?))? {
+			if !seen.insert(message.guid.clone()) {
+				continue;
+			}
+			message.rowid = next_rowid;
+			next_rowid -= 1;
+			message.handle_id = message
+				.handle_id
+				.map(|id| map_handle(id, &handles, &mut unresolved, &mut next_unresolved));
+			message.chat_id = message.chat_id.and_then(|id| chats.get(&id).copied());
+			messages.push(message);
+			added += 1;
+		}
+		let _ = db.close();
+	}
+
+	Ok(added)
+}
+
+/// Maps a handle ROWID of a supplemental database onto the main database. 0
+/// stays 0. A handle the main database doesn't know gets a fresh negative id,
+/// the same one for all of its messages. Mapping it to 0 instead would count
+/// that person's messages as mine.
+fn map_handle(
+	id: i32, known: &HashMap<i32, i32>, unresolved: &mut HashMap<i32, i32>,
+	next_unresolved: &mut i32
+) -> i32 {
+	if id == 0 {
+		return 0;
+	}
+	if let Some(&main_id) = known.get(&id) {
+		return main_id;
+	}
+	*unresolved.entry(id).or_insert_with(|| {
+		let fresh = *next_unresolved;
+		*next_unresolved -= 1;
+		fresh
+	})
+}
+
+fn identifiers(db: &Connection, query: &str) -> AnalyzerResult<HashMap<String, i32>> {
+	let mut statement = readonly::prepare(db, query)?;
+	let rows = statement
+		.query_map([], |row| Ok((row.get::<_, String>(1)?, row.get::<_, i32>(0)?)))?;
+	let mut ids = HashMap::new();
+	for row in rows {
+		let (identifier, rowid) = row?;
+		ids.insert(identifier, rowid);
+	}
+	Ok(ids)
+}
+
+/// Maps the ROWIDs of `db` onto the main database's ROWIDs for the same
+/// identifier.
+fn remap(
+	db: &Connection, query: &str, main: &HashMap<String, i32>
+) -> AnalyzerResult<HashMap<i32, i32>> {
+	Ok(identifiers(db, query)?
+		.into_iter()
+		.filter_map(|(identifier, rowid)| Some((rowid, *main.get(&identifier)?)))
+		.collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn maps_known_handles_onto_the_main_database() {
+		let known = HashMap::from([(3, 7)]);
+		let (mut unresolved, mut next) = (HashMap::new(), -1);
+		assert_eq!(map_handle(3, &known, &mut unresolved, &mut next), 7);
+		assert_eq!(map_handle(0, &known, &mut unresolved, &mut next), 0);
+		assert!(unresolved.is_empty());
+	}
+
+	#[test]
+	fn unknown_handles_are_never_me() {
+		let known = HashMap::new();
+		let (mut unresolved, mut next) = (HashMap::new(), -1);
+		let first = map_handle(4, &known, &mut unresolved, &mut next);
+		let second = map_handle(5, &known, &mut unresolved, &mut next);
+		assert!(first < 0 && second < 0 && first != second);
+		assert_eq!(map_handle(4, &known, &mut unresolved, &mut next), first);
+
+		// Later databases continue the sequence instead of reusing ids
+		let mut later = HashMap::new();
+		let third = map_handle(4, &known, &mut later, &mut next);
+		assert!(third < 0 && third != first && third != second);
+	}
+}
@@ -0,0 +1,38 @@
+use std::collections::HashSet;
+
+use rusqlite::Connection;
+
+use crate::schema::Schema;
+use crate::{readonly, AnalyzerResult};
+
+/// Messages whose content was picked up by Shared with You. chat.db marks
+/// them with `syndication_ranges`, a column added in macOS 13; older
+/// databases simply have none.
+pub struct Syndicated {
+	messages: HashSet<i32>
+}
+
+impl Syndicated {
+	pub fn new(db: &Connection, schema: &Schema) -> AnalyzerResult<Self> {
+		let mut messages = HashSet::new();
+		if !schema.has_column("message", "syndication_ranges") {
+			return Ok(Self { messages });
+		}
+
+		let mut statement = readonly::prepare(
+			db,
+			"SELECT ROWID FROM message
+			WHERE syndication_ranges IS NOT NULL AND length(syndication_ranges) > 0"
+		)?;
+
+		for row in statement.query_map([], |row| row.get::<_, i32>(0))? {
+			messages.insert(row?);
+		}
+
+		Ok(Self { messages })
+	}
+
+	pub fn contains(&self, message_id: i32) -> bool {
+		self.messages.contains(&message_id)
+	}
+}
@@ -0,0 +1,36 @@
+//! Rows chat.db stores for group events ("You named the conversation…",
+//! someone joining or leaving) rather than for something a person typed.
+//! They're kept out of every stat and only counted on their own.
+
+use imessage_database::tables::messages::Message;
+
+/// What kind of system message a row is, by its `item_type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemKind {
+	/// Someone was added to or removed from a group
+	MemberChange,
+	/// The group was named or renamed
+	Rename,
+	/// Someone left, or the group photo changed
+	GroupEvent,
+	/// Any other non-message item, e.g. location sharing notices
+	Other
+}
+
+/// `None` for messages people actually sent.
+pub fn kind(message: &Message) -> Option<SystemKind> {
+	match message.item_type {
+		0 => None,
+		1 => Some(SystemKind::MemberChange),
+		2 => Some(SystemKind::Rename),
+		3 => Some(SystemKind::GroupEvent),
+		_ => Some(SystemKind::Other)
+	}
+}
+
+/// Splits messages into (messages, system messages), keeping date order.
+pub fn split(messages: Vec<Message>) -> (Vec<Message>, Vec<Message>) {
+	let (system, messages): (Vec<Message>, Vec<Message>) =
+		messages.into_iter().partition(|message| kind(message).is_some());
+	(messages, system)
+}
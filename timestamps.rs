@@ -0,0 +1,99 @@
+//! chat.db dates are Apple-epoch nanoseconds on modern macOS and seconds on
+//! databases migrated from older versions, sometimes mixed within one file.
+//! Broken rows also carry negative or far-future dates. Everything is
+//! normalized to nanoseconds up front so no stat sees a decades-long gap.
+
+use std::time::SystemTime;
+
+use imessage_database::tables::messages::Message;
+
+use crate::insights::APPLE_EPOCH_OFFSET;
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+/// Dates below this are in seconds. Nanosecond dates pass it within a second
+/// of 2001-01-01, second dates won't reach it for thousands of years.
+const NANOSECOND_THRESHOLD: i64 = 1_000_000_000_000;
+/// Clock skew allowed before a date counts as being in the future
+const FUTURE_SLACK_SECONDS: i64 = 24 * 60 * 60;
+
+/// Converts every date of every message to nanoseconds and clears dates that
+/// can't be right: negative ones, and ones after tomorrow. A cleared sent date
+/// becomes 0 and the message is then dropped by `remove_undated`. Returns how
+/// many messages had a date corrected.
+pub fn normalize(messages: &mut [Message]) -> usize {
+	let now = SystemTime::now()
+		.duration_since(SystemTime::UNIX_EPOCH)
+		.map_or(i64::MAX, |d| d.as_secs() as i64);
+	let latest = (now - APPLE_EPOCH_OFFSET + FUTURE_SLACK_SECONDS).saturating_mul(NANOS_PER_SECOND);
+
+	let mut corrected = 0;
+	for message in messages.iter_mut() {
+		let mut changed = false;
+		for date in [&mut message.date, &mut message.date_read, &mut message.date_delivered] {
+			let normalized = normalize_date(*date, latest);
+			if normalized != *date {
+				*date = normalized;
+				changed = true;
+			}
+		}
+		if changed {
+			corrected += 1;
+		}
+	}
+	corrected
+}
+
+/// Drops messages without a sent date. Date 0 is the Apple epoch, so left in
+/// they would count as messages sent on 2001-01-01 and skew reply times and
+/// streaks. Returns how many were dropped.
+pub fn remove_undated(messages: &mut Vec<Message>) -> usize {
+	let before = messages.len();
+	messages.retain(|message| message.date != 0);
+	before - messages.len()
+}
+
+fn normalize_date(date: i64, latest: i64) -> i64 {
+	let nanos = if date <= 0 {
+		return 0;
+	} else if date < NANOSECOND_THRESHOLD {
+		date.saturating_mul(NANOS_PER_SECOND)
+	} else {
+		date
+	};
+	if nanos > latest { 0 } else { nanos }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// 2024-01-01 in Apple-epoch seconds
+	const SECONDS: i64 = 725_760_000;
+	const LATEST: i64 = (SECONDS + 365 * 24 * 60 * 60) * NANOS_PER_SECOND;
+
+	#[test]
+	fn scales_seconds_to_nanoseconds() {
+		assert_eq!(normalize_date(SECONDS, LATEST), SECONDS * NANOS_PER_SECOND);
+	}
+
+	#[test]
+	fn keeps_nanoseconds() {
+		let nanos = SECONDS * NANOS_PER_SECOND + 123;
+		assert_eq!(normalize_date(nanos, LATEST), nanos);
+	}
+
+	#[test]
+	fn clears_zero_and_negative_dates() {
+		assert_eq!(normalize_date(0, LATEST), 0);
+		assert_eq!(normalize_date(-1, LATEST), 0);
+		assert_eq!(normalize_date(-SECONDS * NANOS_PER_SECOND, LATEST), 0);
+	}
+
+	#[test]
+	fn clears_future_dates() {
+		assert_eq!(normalize_date(LATEST, LATEST), LATEST);
+		assert_eq!(normalize_date(LATEST + 1, LATEST), 0);
+		let future_seconds = LATEST / NANOS_PER_SECOND + 1;
+		assert_eq!(normalize_date(future_seconds, LATEST), 0);
+	}
+}
@@ -0,0 +1,261 @@
+//! Where the encrypted payload goes. `send_stats` only encrypts and hands the
+//! bytes to a `Transport`, so embedders can route uploads elsewhere without
+//! touching the pipeline.
+
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::time::Duration;
+use std::{fs, io};
+
+use napi::bindgen_prelude::{Buffer, Promise};
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction};
+use reqwest::Method;
+
+use crate::attestation::{self, Attestation};
+use crate::network::PinnedClient;
+use crate::options::FetchOptions;
+use crate::{envelope, AnalyzerResult};
+
+const DEFAULT_API_URL: &str = "https://messageswrapped.com";
+
+/// JS function given the encrypted payload. It resolves to the URL or location
+/// the payload was stored at, the decryption key is appended as the fragment.
+pub type UploadCallback = ThreadsafeFunction<Buffer, ErrorStrategy::Fatal>;
+
+pub type SendFuture<'a> = Pin<Box<dyn Future<Output = AnalyzerResult<Delivery>> + Send + 'a>>;
+
+/// Where a payload ended up.
+#[derive(Debug, Clone)]
+pub struct Delivery {
+	/// Share id for the HTTP API, the payload location for everything else
+	pub id: String,
+	pub delete_token: Option<String>
+}
+
+pub trait Transport: Send + Sync {
+	fn send(&self, payload: Vec<u8>) -> SendFuture<'_>;
+
+	/// The URL the share can be opened at, with `key` in the fragment.
+	fn share_url(&self, delivery: &Delivery, key: &str) -> String {
+		format!("{}#{}", delivery.id, key)
+	}
+
+	/// Server that keeps shares and can delete them again. Only shares with a
+	/// server get receipts, so they can be reused and purged later.
+	fn server(&self) -> Option<&str> {
+		None
+	}
+}
+
+/// Picks the transport configured in `options`. A JS `callback` always wins.
+pub fn select(
+	api_url: String, options: &FetchOptions, callback: Option<UploadCallback>
+) -> AnalyzerResult<Box<dyn Transport>> {
+	pick(HttpTransport::new(Some(api_url)), options, callback)
+}
+
+/// `select` for a group chat wrapped.
+pub fn select_group(
+	api_url: String, options: &FetchOptions, callback: Option<UploadCallback>
+) -> AnalyzerResult<Box<dyn Transport>> {
+	pick(HttpTransport::group(Some(api_url)), options, callback)
+}
+
+fn pick(
+	http: HttpTransport, options: &FetchOptions, callback: Option<UploadCallback>
+) -> AnalyzerResult<Box<dyn Transport>> {
+	if let Some(callback) = callback {
+		return Ok(Box::new(CallbackTransport { callback }));
+	}
+
+	let target = || {
+		options.upload_target.clone().ok_or_else(|| {
+			io::Error::new(io::ErrorKind::InvalidInput, "This upload transport needs uploadTarget")
+		})
+	};
+	match options.upload_transport.as_deref().unwrap_or("http") {
+		"http" => Ok(Box::new(http)),
+		"presigned" => Ok(Box::new(PresignedTransport { url: target()? })),
+		"file" => Ok(Box::new(FileTransport { path: PathBuf::from(target()?) })),
+		other => Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			format!("Unknown upload transport {}", other)
+		)
+		.into())
+	}
+}
+
+/// POSTs to the Messages Wrapped API, which stores the payload and returns a
+/// share id. Uploads carry a size attestation when the API publishes a key.
+pub struct HttpTransport {
+	base_url: String,
+	/// "stats" for a wrapped, "group" for a group chat wrapped. The server
+	/// keeps it with the share and the viewer decodes by it
+	kind: &'static str
+}
+
+impl HttpTransport {
+	pub fn new(api_url: Option<String>) -> Self {
+		Self { base_url: api_url.unwrap_or_else(|| String::from(DEFAULT_API_URL)), kind: "stats" }
+	}
+
+	/// Uploads a `GroupWrapped` instead of `YearsStats`.
+	pub fn group(api_url: Option<String>) -> Self {
+		Self { kind: "group", ..Self::new(api_url) }
+	}
+}
+
+impl Transport for HttpTransport {
+	fn send(&self, payload: Vec<u8>) -> SendFuture<'_> {
+		Box::pin(async move {
+			let upload_url = format!("{}/api/upload", self.base_url);
+			let network = PinnedClient::new(&self.base_url).await?;
+			// The server may reject uploads without one, but that's its call
+			let attestation = match attestation::fetch_key(&network, &self.base_url).await {
+				Ok(key) => Some(Attestation::new(&key, payload.len())?),
+				Err(e) => {
+					eprintln!("Uploading without a size attestation: {:?}", e);
+					None
+				}
+			};
+
+			let mut request = network
+				.request(Method::POST, &upload_url)?
+				.timeout(Duration::from_secs(30))
+				.header("Content-Type", "application/octet-stream")
+				.header("X-Payload-Version", envelope::VERSION.to_string())
+				.header("X-Payload-Kind", self.kind);
+			for (name, value) in attestation.iter().flat_map(Attestation::headers) {
+				request = request.header(name, value);
+			}
+			let response = request
+				.body(payload)
+				.send()
+				.await
+				.map_err(|e| request_error(e, &upload_url))?;
+			let response = check_status(network.verify(response)?).await?;
+
+			let response_data: serde_json::Value = response
+				.json()
+				.await
+				.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+			let id = response_data["id"].as_str().filter(|id| !id.is_empty()).ok_or_else(|| {
+				io::Error::new(io::ErrorKind::InvalidData, "The server returned no share id")
+			})?;
+			Ok(Delivery {
+				id: id.to_string(),
+				delete_token: response_data["deleteToken"].as_str().map(String::from)
+			})
+		})
+	}
+
+	fn share_url(&self, delivery: &Delivery, key: &str) -> String {
+		let path = if self.kind == "group" { "g" } else { "s" };
+		format!("{}/{}/{}#{}", self.base_url, path, delivery.id, key)
+	}
+
+	fn server(&self) -> Option<&str> {
+		Some(&self.base_url)
+	}
+}
+
+/// PUTs to a presigned object storage URL, e.g. from S3. The share URL is the
+/// object URL without the signature.
+pub struct PresignedTransport {
+	url: String
+}
+
+impl Transport for PresignedTransport {
+	fn send(&self, payload: Vec<u8>) -> SendFuture<'_> {
+		Box::pin(async move {
+			let network = PinnedClient::new(&self.url).await?;
+			let response = network
+				.request(Method::PUT, &self.url)?
+				.timeout(Duration::from_secs(30))
+				.body(payload)
+				.send()
+				.await
+				.map_err(|e| request_error(e, self.object_url()))?;
+			check_status(network.verify(response)?).await?;
+
+			Ok(Delivery { id: self.object_url().to_string(), delete_token: None })
+		})
+	}
+}
+
+impl PresignedTransport {
+	fn object_url(&self) -> &str {
+		self.url.split_once('?').map_or(self.url.as_str(), |(object, _)| object)
+	}
+}
+
+/// Writes the payload to a local file.
+pub struct FileTransport {
+	path: PathBuf
+}
+
+impl Transport for FileTransport {
+	fn send(&self, payload: Vec<u8>) -> SendFuture<'_> {
+		Box::pin(async move {
+			fs::write(&self.path, payload)?;
+			Ok(Delivery { id: self.path.display().to_string(), delete_token: None })
+		})
+	}
+}
+
+/// Hands the payload to a JS function.
+pub struct CallbackTransport {
+	callback: UploadCallback
+}
+
+impl Transport for CallbackTransport {
+	fn send(&self, payload: Vec<u8>) -> SendFuture<'_> {
+		Box::pin(async move {
+			let location = self
+				.callback
+				.call_async::<Promise<String>>(payload.into())
+				.await
+				.map_err(|e| io::Error::new(io::ErrorKind::Other, e.reason))?
+				.await
+				.map_err(|e| io::Error::new(io::ErrorKind::Other, e.reason))?;
+
+			Ok(Delivery { id: location, delete_token: None })
+		})
+	}
+}
+
+fn request_error(e: reqwest::Error, url: &str) -> io::Error {
+	let error_msg = if e.is_timeout() {
+		format!("Request timed out while uploading to {}", url)
+	} else if e.is_connect() {
+		format!("Failed to connect to {}. Please check your internet connection", url)
+	} else {
+		format!("Upload failed: {} (URL: {})", e, url)
+	};
+	io::Error::new(io::ErrorKind::Other, error_msg)
+}
+
+async fn check_status(response: reqwest::Response) -> AnalyzerResult<reqwest::Response> {
+	if response.status().is_success() {
+		return Ok(response);
+	}
+
+	let status = response.status();
+	let error_body = response.text().await.unwrap_or_default();
+	Err(io::Error::new(
+		io::ErrorKind::Other,
+		format!(
+			"Upload failed with status {}: {}. Server response: {}",
+			status,
+			status.canonical_reason().unwrap_or("Unknown error"),
+			if error_body.is_empty() {
+				"No error details provided"
+			} else {
+				&error_body
+			}
+		)
+	)
+	.into())
+}
@@ -0,0 +1,380 @@
+//! TypeScript declarations for what the napi functions return. napi-rs only
+//! sees `string` for the JSON responses, so the functions name these types in
+//! `ts_return_type` and the app keeps the generated file next to napi's
+//! index.d.ts. The stats interfaces are generated from stats.proto, the rest
+//! mirrors the responses built in lib.rs.
+
+use crate::schema_doc::{self, FieldDoc, MessageDoc};
+
+/// `errorType` values of failed responses
+pub const ERROR_TYPES: &[&str] = &[
+	"already_running",
+	"analysis_failed",
+	"export_failed",
+	"demo_failed",
+	"import_failed",
+	"upload_failed",
+	"archive_entry_not_found",
+	"no_pending_upload",
+	"group_chat_not_found"
+];
+/// `code` values of warnings
+pub const WARNING_CODES: &[&str] = &[
+	"skipped_rows",
+	"sync_gap",
+	"unresolved_handles",
+	"truncated_stats",
+	"contacts_unavailable",
+	"timestamps_corrected",
+	"duplicates_removed",
+	"supplemental_merged",
+	"identities_claimed",
+	"excluded_messages",
+	"icloud_history_incomplete"
+];
+/// `stage` values of progress events, in the order a run goes through them
+pub const PROGRESS_STAGES: &[&str] = &[
+	"openingDatabase",
+	"loadingMessages",
+	"waitingForDatabase",
+	"loadingContacts",
+	"loadingHandles",
+	"loadingAttachments",
+	"loadingChats",
+	"loadingLinkPreviews",
+	"loadingSharedWithYou",
+	"computingStats",
+	"computingInsights",
+	"uploading",
+	"done"
+];
+
+const HEADER: &str = "\
+// Generated by generate_type_definitions, do not edit.
+// Ambient declarations: include this file in tsconfig next to index.d.ts.
+
+/** A JSON string that parses to `T` */
+type Json<T> = string & { readonly __json?: T }
+
+interface Success<T> {
+  success: true
+  data: T
+}
+
+interface Failure {
+  success: false
+  error: {
+    message: string
+    /** API the upload went to */
+    url?: string
+    details: {
+      errorType: ErrorType
+      fullError?: string
+    }
+  }
+}
+
+type Response<T> = Success<T> | Failure
+";
+
+const RESULTS: &str = "
+interface Warning {
+  code: WarningCode
+  message: string
+}
+
+interface PayloadMetrics {
+  originalSize: number
+  compressedSize: number
+  encryptedSize: number
+  codec: string
+  compressionRatio: number
+}
+
+interface UploadedData {
+  shareUrl: string
+  encryptionKey: string
+  /** `null` when an identical earlier share was reused */
+  metrics: PayloadMetrics | null
+  build: BuildInfo | null
+}
+
+/** One person of a batch run, told apart by the label they were passed with */
+type BatchPersonResult<T> = { label: string } & (Success<T> | Failure)
+
+interface BatchData {
+  people: BatchPersonResult<PrepareUploadData>[]
+}
+
+interface BatchUploadedData {
+  people: BatchPersonResult<UploadedData>[]
+}
+
+interface GroupWrappedData {
+  shareUrl: string
+  encryptionKey: string
+  /** `null` when an identical earlier share was reused */
+  metrics: PayloadMetrics | null
+  wrapped: GroupWrapped
+}
+
+interface LocalStatsData {
+  stats: YearsStats
+  warnings: Warning[]
+}
+
+interface ExportStatsData {
+  path: string
+  size: number
+  warnings: Warning[]
+}
+
+interface ReportHtmlData {
+  path: string
+  size: number
+}
+
+interface ReportPdfData {
+  path: string
+  size: number
+}
+
+interface ShareCardsData {
+  cards: {
+    year: number
+    kind: "messageCount" | "topContact" | "topEmoji" | "wordCloud"
+    path: string
+  }[]
+}
+
+interface UploadReport {
+  years: number[]
+  categories: string[]
+  verbatimTexts: number
+  payloadSize: number
+  statSizes: { field: string; bytes: number; share: number }[]
+  contactNames: string[]
+  skippedStats: string[]
+}
+
+interface PrepareUploadData {
+  report: UploadReport
+  /** The time budget ran out before every stat was computed */
+  partial: boolean
+  warnings: Warning[]
+}
+
+interface RoundtripData {
+  matches: boolean
+  metrics: PayloadMetrics
+  stats: YearsStats
+}
+
+interface TestVector {
+  envelopeVersion: number
+  codec: string
+  stats: YearsStats
+  plaintext: string
+  compressed: string
+  key: string
+  keyBase64url: string
+  nonce: string
+  envelope: string
+  roundTrips: boolean
+}
+
+/** `success` is false when some shares could not be deleted remotely */
+interface PurgeData {
+  remoteDeleted: number
+  remoteFailures: { id: string; error: string }[]
+  localDataRemoved: boolean
+  pendingUploadDiscarded: boolean
+}
+
+interface SocialGraphData {
+  path: string
+  nodes: number
+  edges: number
+}
+
+interface AttachData {
+  running: boolean
+  attached: boolean
+}
+
+interface ArchiveEntry {
+  id: string
+  /** Unix milliseconds */
+  createdAt: number
+  years: number[]
+  size: number
+}
+
+interface YearComparison {
+  firstYear: number
+  secondYear: number
+  volume: {
+    firstSent: number
+    firstReceived: number
+    secondSent: number
+    secondReceived: number
+    percentChange: number
+  }
+  sharedTopContacts: string[]
+  biggestRisers: { name: string; firstCount: number; secondCount: number }[]
+}
+
+type CacheStatus =
+  | { cached: false }
+  | {
+      cached: true
+      /** chat.db did not change since the cache was written */
+      current: boolean
+      years: number[]
+      watermark: number
+      /** Unix milliseconds */
+      chatDbModified: number
+      sizeBytes: number
+    }
+
+interface Import {
+  chatDbPath: string
+  addressBookPath: string
+  messages: number
+  chats: number
+  contacts: number
+  skipped: number
+  /** Decrypted from an encrypted backup, kept until removed or purged */
+  decrypted: boolean
+}
+
+interface IosBackupsData {
+  backups: {
+    id: string
+    deviceName: string | null
+    productVersion: string | null
+    /** Unix milliseconds */
+    lastBackup: number | null
+    encrypted: boolean
+    hasMessages: boolean
+  }[]
+}
+
+interface IdentitiesData {
+  identities: { handle: string; messages: number }[]
+}
+
+interface ReadOnlyAttestation {
+  readOnly: boolean
+  readOnlyConnections: number
+  queryOnlyConnections: number
+  checkedStatements: number
+  blockedWrites: number
+}
+
+interface AddressBookSourcesData {
+  sources: { id: string; name: string | null; contacts: number }[]
+}
+
+type AccessStatus =
+  | "ok"
+  | "not_found"
+  | "permission_denied"
+  | "not_a_database"
+  | "locked"
+  | "error"
+
+interface Access {
+  path: string
+  readable: boolean
+  status: AccessStatus
+  errno: number | null
+  message: string | null
+  remediation: string | null
+}
+
+interface PermissionCheck {
+  granted: boolean
+  chatDb: Access
+  addressBook: Access
+}
+
+interface ChatDbDiagnosis {
+  likelyIncomplete: boolean
+  reasons: (
+    | "empty_chats"
+    | "orphaned_references"
+    | "attachments_not_downloaded"
+    | "short_history"
+  )[]
+  icloudSync: boolean
+  messages: number
+  firstMessage: number | null
+  historyDays: number
+  chats: number
+  emptyChats: number
+  references: number
+  orphanedReferences: number
+  checkedAttachments: number
+  missingAttachments: number
+  warning: Warning | null
+}
+";
+
+/// The whole declaration file.
+pub fn generate() -> String {
+	let schema = schema_doc::describe();
+	let mut out = String::from(HEADER);
+	out.push_str(&union("ErrorType", ERROR_TYPES));
+	out.push_str(&union("WarningCode", WARNING_CODES));
+	out.push_str(&union("ProgressStage", PROGRESS_STAGES));
+	out.push_str(RESULTS);
+	out.push_str(&format!("\n// Generated from stats.proto, schema {}\n", schema.schema_hash));
+	for message in &schema.messages {
+		out.push_str(&interface(message));
+	}
+	out
+}
+
+fn union(name: &str, values: &[&str]) -> String {
+	let values: Vec<String> = values.iter().map(|value| format!("  | \"{}\"", value)).collect();
+	format!("\ntype {} =\n{}\n", name, values.join("\n"))
+}
+
+fn interface(message: &MessageDoc) -> String {
+	let mut out = String::from("\n");
+	if let Some(description) = &message.description {
+		out.push_str(&format!("/** {} */\n", description));
+	}
+	out.push_str(&format!("interface {} {{\n", message.name));
+	for field in &message.fields {
+		let doc = match (&field.description, field.unit) {
+			(Some(description), _) => Some(description.clone()),
+			(None, Some(unit)) => Some(format!("In {}", unit)),
+			(None, None) => None
+		};
+		if let Some(doc) = doc {
+			out.push_str(&format!("  /** {} */\n", doc));
+		}
+		out.push_str(&format!("  {}: {}\n", field.name, field_type(field)));
+	}
+	out.push_str("}\n");
+	out
+}
+
+/// Stats are serialized with serde: 64-bit integers as numbers, bytes as
+/// arrays of numbers and missing optional fields as `null`.
+fn field_type(field: &FieldDoc) -> String {
+	let base = match field.type_name.as_str() {
+		_ if field.is_message => field.type_name.as_str(),
+		"bool" => "boolean",
+		"string" => "string",
+		"bytes" => "number[]",
+		_ => "number"
+	};
+	match field.label.as_str() {
+		"repeated" => format!("{}[]", base),
+		"optional" => format!("{} | null", base),
+		_ => base.to_string()
+	}
+}
@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+
+use imessage_database::tables::messages::Message;
+use serde::Serialize;
+
+use crate::contacts::Contacts;
+use crate::handles::Handles;
+use crate::insights::{local_time, unix_seconds};
+use crate::stats::stats::YearsStats;
+
+/// Gaps between messages longer than this look like missing sync
+const SYNC_GAP_DAYS: i64 = 30;
+/// Only flag gaps in histories with enough messages to expect daily traffic
+const SYNC_GAP_MIN_MESSAGES: usize = 1000;
+/// Share of people without a contact name above which names look broken
+const UNRESOLVED_HANDLES_THRESHOLD: f64 = 0.5;
+
+/// Something that degraded the run or changed which messages it counted,
+/// without failing it, returned to the caller alongside the results.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Warning {
+	/// Stable identifier the app can match on, e.g. "contacts_unavailable"
+	pub code: &'static str,
+	pub message: String
+}
+
+impl Warning {
+	pub fn new(code: &'static str, message: impl Into<String>) -> Self {
+		Self { code, message: message.into() }
+	}
+}
+
+/// Checks the finished run for issues that make numbers look wrong without
+/// being errors, and adds them to `warnings`.
+pub fn collect(
+	warnings: &mut Vec<Warning>, messages: &[Message], contacts: &Contacts, handles: &Handles,
+	stats: &YearsStats
+) {
+	if messages.len() >= SYNC_GAP_MIN_MESSAGES {
+		let gap = messages
+			.iter()
+			.filter(|m| m.date != 0)
+			.zip(messages.iter().filter(|m| m.date != 0).skip(1))
+			.max_by_key(|(a, b)| unix_seconds(b.date) - unix_seconds(a.date));
+		if let Some((before, after)) = gap {
+			let days = (unix_seconds(after.date) - unix_seconds(before.date)) / 86_400;
+			if days > SYNC_GAP_DAYS {
+				let date = |m: &Message| {
+					local_time(m.date).map(|t| t.format("%Y-%m-%d").to_string()).unwrap_or_default()
+				};
+				warnings.push(Warning::new(
+					"sync_gap",
+					format!(
+						"No messages between {} and {} ({} days), some may not have synced to \
+						 this Mac",
+						date(before),
+						date(after),
+						days
+					)
+				));
+			}
+		}
+	}
+
+	let contacts_missing = warnings.iter().any(|w| w.code == "contacts_unavailable");
+	let people: HashSet<i32> =
+		messages.iter().filter_map(|m| m.handle_id).filter(|&handle| handle != 0).collect();
+	if !contacts_missing && !people.is_empty() {
+		let unresolved = people
+			.iter()
+			.filter_map(|&handle| handles.get(handle))
+			.filter(|handle_id| contacts.get_name(handle_id).is_none())
+			.count();
+		let share = unresolved as f64 / people.len() as f64;
+		if share > UNRESOLVED_HANDLES_THRESHOLD {
+			warnings.push(Warning::new(
+				"unresolved_handles",
+				format!(
+					"{:.0}% of the people you texted have no contact name, check which \
+					 AddressBook sources are selected",
+					share * 100.0
+				)
+			));
+		}
+	}
+
+	let truncated: HashSet<&str> = stats
+		.stats
+		.iter()
+		.flat_map(|year| year.skipped_stats.iter().map(String::as_str))
+		.collect();
+	if !truncated.is_empty() {
+		let mut names: Vec<&str> = truncated.into_iter().collect();
+		names.sort_unstable();
+		warnings.push(Warning::new(
+			"truncated_stats",
+			format!(
+				"Left out because the time budget ran out or they failed: {}",
+				names.join(", ")
+			)
+		));
+	}
+}